@@ -16,7 +16,7 @@
           pname = "pageturtle";
           version = "0.1.1";
           src = pkgs.lib.cleanSource ./.;
-          cargoHash = "sha256-aml6dyRSsG/Dq5dck0WRUS6EZb24qIqFbxDHxpPFijo=";
+          cargoHash = pkgs.lib.fakeHash;
         };
         devShell = with pkgs; mkShell {
           buildInputs = [ 
@@ -0,0 +1,126 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    build::{build, BuildContext},
+    read_config, CONFIG_FILE,
+};
+
+/// Timings that the benchmark compares its results against by default
+pub const BENCH_BASELINE_FILE: &str = ".pageturtle-bench.json";
+
+/// Builds a synthesized blog, printing how long each stage took compared to
+/// the baseline
+pub fn run_benchmark(posts: usize, words: usize, baseline: &Path, save_baseline: bool) {
+    let blog_root = std::env::temp_dir().join("pageturtle-bench");
+    if blog_root.exists() {
+        fs::remove_dir_all(&blog_root).unwrap();
+    }
+
+    println!("Generating {} posts of {} words", posts, words);
+    synthesize_blog(&blog_root, posts, words);
+
+    let config = read_config(&blog_root);
+    let context = BuildContext::new(&config);
+    let timings = build(&blog_root, &blog_root.join("dist"), &config, &context).timings;
+
+    let previous: HashMap<String, f64> = fs::read_to_string(baseline)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let mut total = Duration::ZERO;
+    println!(
+        "{:<12} {:>12} {:>12} {:>8}",
+        "stage", "time", "baseline", "change"
+    );
+    for (stage, duration) in timings.stages() {
+        total += duration;
+        print_bench_row(stage, duration, previous.get(stage));
+    }
+    print_bench_row("total", total, previous.get("total"));
+
+    if save_baseline {
+        let mut results: BTreeMap<&str, f64> = timings
+            .stages()
+            .iter()
+            .map(|(stage, duration)| (*stage, duration.as_secs_f64() * 1e3))
+            .collect();
+        results.insert("total", total.as_secs_f64() * 1e3);
+
+        fs::write(baseline, serde_json::to_string_pretty(&results).unwrap()).unwrap();
+        println!(
+            "Saved the results as the baseline in {}",
+            baseline.display()
+        );
+    }
+
+    fs::remove_dir_all(&blog_root).unwrap();
+}
+
+fn print_bench_row(stage: &str, duration: Duration, baseline: Option<&f64>) {
+    let milliseconds = duration.as_secs_f64() * 1e3;
+
+    match baseline {
+        Some(baseline) => println!(
+            "{:<12} {:>10.1}ms {:>10.1}ms {:>+7.1}%",
+            stage,
+            milliseconds,
+            baseline,
+            (milliseconds - baseline) / baseline * 100.0
+        ),
+        None => println!(
+            "{:<12} {:>10.1}ms {:>12} {:>8}",
+            stage, milliseconds, "-", "-"
+        ),
+    }
+}
+
+// Writes a blog with the given amount of posts, made of the kinds of content
+// that posts usually have, e.g: headings, lists and code blocks
+fn synthesize_blog(blog_root: &Path, posts: usize, words: usize) {
+    const WORDS: &[&str] = &[
+        "turtle", "page", "markdown", "render", "static", "blog", "post", "feed", "build", "fast",
+        "write", "read", "shell", "tree", "node", "index",
+    ];
+
+    let posts_dir = blog_root.join("posts");
+    fs::create_dir_all(&posts_dir).unwrap();
+    fs::write(
+        blog_root.join(CONFIG_FILE),
+        "blog_title = \"Benchmark\"\nbase_url = \"http://localhost\"\nauthor = \"pageturtle\"\n",
+    )
+    .unwrap();
+
+    for i in 0..posts {
+        let mut content = format!(
+            "---\ntitle: Post number {}\ndate: 2023-01-{:02}\ntags:\n  - tag-{}\n---\n\n",
+            i,
+            i % 28 + 1,
+            i % 10
+        );
+
+        // Every paragraph has 100 words, and every fifth one is followed by
+        // a section with a list and a code block
+        for paragraph in 0..words.div_ceil(100) {
+            let sentence: Vec<&str> = (0..(words - paragraph * 100).min(100))
+                .map(|w| WORDS[(i + paragraph + w) % WORDS.len()])
+                .collect();
+            content.push_str(&sentence.join(" "));
+            content.push_str(".\n\n");
+
+            if paragraph % 5 == 4 {
+                content.push_str(&format!(
+                    "## Section {}\n\n- first\n- second\n\n```rust\nfn main() {{\n    println!(\"{}\");\n}}\n```\n\n",
+                    paragraph, i
+                ));
+            }
+        }
+
+        fs::write(posts_dir.join(format!("post-{}.md", i)), content).unwrap();
+    }
+}
@@ -0,0 +1,1208 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::SecondsFormat;
+use comrak::{nodes::AstNode, Arena, ComrakOptions, ComrakPlugins};
+use pageturtle_core::{
+    activitypub,
+    blog::{
+        adjacent_posts, build_blog_post, markdown_options, next_scheduled_post,
+        prepare_for_publish, sort_posts, withhold_scheduled, BlogConfiguration,
+        ContentConfiguration, FeedAliasMode, ParsedBlogPost, PostCompiler, PostRenderers,
+        PublishableBlogPost,
+    },
+    changelog,
+    defaults::FrontmatterDefaults,
+    discussion::CommentCountQuery,
+    feed, formatter, hashing, highlighting,
+    hosting::{self, Redirect},
+    link_preview::{self, LinkPreview},
+    related, remote_images,
+    rendering::{self, BuildInfo, DashboardEntry, RenderError},
+    responsive_images, resume,
+    routes::{self, Page},
+    search, shortcodes, sitemap, talks, thumbnails, urls,
+};
+use rayon::{prelude::*, ThreadPoolBuilder};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{
+    blog_stylesheet, check_allowed_filetype,
+    manifest::write_build_manifest,
+    server::{DASHBOARD_FILE, DRAFT_PREVIEW_DIR, SOCIAL_PREVIEW_DIR},
+    source_format, theme_directory, BuildPostError,
+};
+
+/// Maximum amount of files written at the same time
+const IO_THREADS: usize = 8;
+
+/// File in the blog directory caching the previews of linked pages
+const LINK_PREVIEWS_FILE: &str = ".pageturtle-link-previews.json";
+
+/// Directory in the blog caching the images that posts link from other sites
+const REMOTE_IMAGES_DIR: &str = ".pageturtle-remote-images";
+
+/// Compiles the posts found in a directory, recording the ones that failed.
+/// Posts are parsed and rendered to HTML in parallel, each one with a compiler
+/// of its own since their ASTs can't be shared between threads. Drafts are
+/// only compiled when `drafts` is set.
+pub fn read_posts(
+    directory: &Path,
+    context: &BuildContext,
+    config: &BlogConfiguration,
+    previews: Option<&LinkPreviews>,
+    remote_images: Option<&RemoteImages>,
+    drafts: bool,
+    failures: &mut Vec<BuildPostError>,
+) -> Vec<PublishableBlogPost> {
+    let results: Vec<Result<Option<PublishableBlogPost>, BuildPostError>> =
+        post_files(directory, config)
+            .into_par_iter()
+            .map(|filepath| {
+                compile_post(
+                    filepath,
+                    directory,
+                    context,
+                    config,
+                    previews,
+                    remote_images,
+                    drafts,
+                )
+            })
+            .collect();
+
+    let mut posts = vec![];
+    for result in results {
+        match result {
+            Ok(Some(post)) => posts.push(post),
+            Ok(None) => continue,
+            Err(e) => failures.push(e),
+        }
+    }
+
+    posts
+}
+
+/// Files of the posts in a content directory
+pub fn post_files(directory: &Path, config: &BlogConfiguration) -> Vec<PathBuf> {
+    walk_content(directory, &config.content)
+        .filter(|entry| !entry.file_type().is_dir())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(check_allowed_filetype)
+        })
+        .map(DirEntry::into_path)
+        .collect()
+}
+
+fn compile_post(
+    filepath: PathBuf,
+    directory: &Path,
+    context: &BuildContext,
+    config: &BlogConfiguration,
+    previews: Option<&LinkPreviews>,
+    remote_images: Option<&RemoteImages>,
+    drafts: bool,
+) -> Result<Option<PublishableBlogPost>, BuildPostError> {
+    let source = fs::read_to_string(&filepath).unwrap();
+    let mut content = match source_format(&filepath).to_markdown(&source) {
+        Ok(content) => content,
+        Err(e) => {
+            return Err(BuildPostError {
+                filepath,
+                content: source,
+                line: e.line,
+                column: e.column,
+                message: e.message,
+            })
+        }
+    };
+    if config.shortcodes {
+        content = shortcodes::expand(&content);
+    }
+
+    let defaults = match FrontmatterDefaults::load(directory, &filepath) {
+        Ok(defaults) => defaults,
+        Err(message) => {
+            return Err(BuildPostError {
+                filepath,
+                content,
+                line: 1,
+                column: 1,
+                message,
+            })
+        }
+    };
+
+    let arena = Arena::new();
+    let plugins = context.plugins();
+    let compiler = PostCompiler::new(arena, &context.options, &plugins);
+
+    let mut parsed = match build_blog_post(&content, &compiler, config, &defaults) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return Err(BuildPostError {
+                filepath,
+                content,
+                line: e.line,
+                column: e.column,
+                message: e.message,
+            })
+        }
+    };
+
+    if parsed.post.metadata.draft && !drafts {
+        return Ok(None);
+    }
+
+    if let Some(previews) = previews {
+        previews.render(parsed.ast);
+    }
+    if let Some(remote_images) = remote_images {
+        remote_images.localize(&mut parsed);
+    }
+
+    Ok(Some(prepare_for_publish(
+        parsed, filepath, &compiler, config,
+    )))
+}
+
+/// Walks a content directory following the configured policy for symlinks and
+/// hidden files
+pub fn walk_content(
+    directory: &Path,
+    config: &ContentConfiguration,
+) -> impl Iterator<Item = DirEntry> {
+    let include_hidden = config.include_hidden;
+
+    WalkDir::new(directory)
+        .follow_links(config.follow_symlinks)
+        .into_iter()
+        .filter_entry(move |e| include_hidden || e.depth() == 0 || !is_hidden(e.file_name()))
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            // e.g: symlinks that loop back to one of their parents
+            Err(e) => {
+                println!("Skipping {}", e);
+                None
+            }
+        })
+}
+
+pub fn is_hidden(name: &OsStr) -> bool {
+    name.to_str().is_some_and(|name| name.starts_with('.'))
+}
+
+/// State that is expensive to set up and doesn't change between builds, so
+/// that it can be reused when rebuilding the blog
+pub struct BuildContext {
+    pub options: ComrakOptions,
+    pub renderers: PostRenderers,
+    pub syntax_css: String,
+}
+
+impl BuildContext {
+    pub fn new(config: &BlogConfiguration) -> Self {
+        BuildContext {
+            options: markdown_options(),
+            renderers: PostRenderers::new(config),
+            syntax_css: highlighting::stylesheet(&config.syntax_theme),
+        }
+    }
+
+    pub fn plugins(&self) -> ComrakPlugins<'_> {
+        self.renderers.plugins()
+    }
+}
+
+/// Outcome of a build
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub timings: BuildTimings,
+    /// Posts that failed to compile, which were left out of the blog
+    pub failures: Vec<BuildPostError>,
+    /// Posts that were published
+    pub posts: Vec<PublishableBlogPost>,
+}
+
+/// How long each stage of a build took
+#[derive(Debug, Default)]
+pub struct BuildTimings {
+    /// Reading the posts, parsing their markdown and rendering it to HTML,
+    /// which happen together since posts are compiled in parallel
+    compiling: Duration,
+    /// Rendering the pages, feeds and other files of the blog
+    templating: Duration,
+    writing: Duration,
+}
+
+impl BuildTimings {
+    pub fn stages(&self) -> [(&'static str, Duration); 3] {
+        [
+            ("compiling", self.compiling),
+            ("templating", self.templating),
+            ("writing", self.writing),
+        ]
+    }
+}
+
+pub fn build(
+    blog_root: &Path,
+    output_directory: &Path,
+    config: &BlogConfiguration,
+    context: &BuildContext,
+) -> BuildReport {
+    let mut timings = BuildTimings::default();
+    let mut stage_start = Instant::now();
+
+    let previews = config.link_previews.then(|| LinkPreviews::load(blog_root));
+    let remote_images = config
+        .localize_remote_images
+        .then(|| RemoteImages::new(blog_root));
+    // Drafts are compiled when they are published along with the other
+    // posts, or previewed on their own
+    let compile_drafts =
+        config.include_drafts || config.is_dev_server || config.draft_preview_secret.is_some();
+
+    let mut failures: Vec<BuildPostError> = vec![];
+    let posts = read_posts(
+        &blog_root.join("posts"),
+        context,
+        config,
+        previews.as_ref(),
+        remote_images.as_ref(),
+        compile_drafts,
+        &mut failures,
+    );
+    let (mut publishable_posts, mut drafts): (Vec<_>, Vec<_>) = posts
+        .into_iter()
+        .partition(|post| !post.post.metadata.draft || config.include_drafts);
+
+    if let Some(previews) = previews {
+        previews.save();
+    }
+
+    timings.compiling = stage_start.elapsed();
+    stage_start = Instant::now();
+
+    let output_dir = Path::new(output_directory);
+
+    if !output_dir.exists() {
+        fs::create_dir_all(output_dir).unwrap();
+    }
+
+    // Future-dated posts are left out of the index, the feeds and the
+    // sitemap until their time comes, unless drafts are shown as well
+    let scheduled = match config.include_drafts || config.is_dev_server {
+        true => vec![],
+        false => withhold_scheduled(&mut publishable_posts, config),
+    };
+
+    sort_posts(&mut publishable_posts, config.sort_by);
+    related::link_related_posts(&mut publishable_posts, config);
+
+    if config.fetch_comment_counts {
+        for post in &mut publishable_posts {
+            if let Some(ref discussion) = post.post.metadata.discussion {
+                post.comment_count = fetch_comment_count(discussion);
+            }
+        }
+    }
+
+    // Files are only written once everything is rendered, so that they can
+    // be written concurrently
+    let mut outputs: Vec<OutputFile> = vec![];
+    let mut render_errors: Vec<RenderError> = vec![];
+    let mut redirects: Vec<Redirect> = vec![];
+    let build_info = match config.reproducible {
+        true => BuildInfo::latest_post(&publishable_posts, config),
+        false => BuildInfo::now(),
+    };
+
+    // create the pages of the blog's routes besides the posts: the index,
+    // the tags page, a page for each tag along with its feed, and the search
+    // page
+    let routes = routes::collect_routes(&publishable_posts, config);
+    for route in &routes {
+        let page = match route.page {
+            Page::Index => rendering::render_index(&publishable_posts, config, &build_info),
+            Page::Tags => rendering::render_tags_page(&publishable_posts, config),
+            Page::Tag(ref tag) => {
+                if let Some(ref feed_path) = route.feed {
+                    let feed = feed::build_tag_feed(&publishable_posts, tag, config);
+                    outputs.extend(OutputFile::rendered(
+                        output_dir.join(feed_path),
+                        rendering::render_feed(&feed),
+                        &mut render_errors,
+                    ));
+                }
+                rendering::render_tag_page(tag, &publishable_posts, config)
+            }
+            Page::Search => rendering::render_search_page(config),
+            // Posts are written below, along with their assets
+            Page::Post(_) => continue,
+        };
+
+        let path = output_dir.join(&route.path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        outputs.extend(OutputFile::rendered(path, page, &mut render_errors));
+    }
+
+    // setup images directory
+    let img_dir = output_dir.join("img");
+    fs::create_dir_all(&img_dir).unwrap();
+    fs::create_dir_all(output_dir.join("audio")).unwrap();
+
+    let social_preview_dir = output_dir.join(SOCIAL_PREVIEW_DIR);
+    if config.is_dev_server {
+        fs::create_dir_all(&social_preview_dir).unwrap();
+    }
+
+    // write posts
+    for post in &publishable_posts {
+        let path = output_dir.join(&post.output_filename);
+        // Posts of sections may be published in directories of their own
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let (previous, next) = adjacent_posts(post, &publishable_posts);
+        let page = rendering::render_post_page(post, previous, next, config, &build_info);
+        outputs.extend(OutputFile::rendered(path, page, &mut render_errors));
+
+        if config.is_dev_server {
+            let preview = rendering::render_social_preview(post, config);
+            let preview_path = social_preview_dir.join(&post.output_filename);
+            fs::create_dir_all(preview_path.parent().unwrap()).unwrap();
+            outputs.extend(OutputFile::rendered(
+                preview_path,
+                preview,
+                &mut render_errors,
+            ));
+        }
+
+        outputs.extend(copy_post_assets(post, output_dir, &mut failures));
+    }
+
+    // copy the files of the static directory as they are, along with the
+    // ones of the theme that the blog doesn't replace
+    let static_dir = blog_root.join(&config.static_directory);
+    let mut static_files = match static_dir.is_dir() {
+        true => copy_static_files(&static_dir, output_dir),
+        false => vec![],
+    };
+    let theme_static_dir = theme_directory(blog_root, config).map(|t| t.join("static"));
+    if let Some(ref theme_static_dir) = theme_static_dir.filter(|d| d.is_dir()) {
+        let replaced: HashSet<PathBuf> = static_files
+            .iter()
+            .filter_map(|file| match file {
+                OutputFile::Copy { to, .. } => Some(to.clone()),
+                _ => None,
+            })
+            .collect();
+        static_files.extend(
+            copy_static_files(theme_static_dir, output_dir)
+                .into_iter()
+                .filter(
+                    |file| !matches!(file, OutputFile::Copy { to, .. } if replaced.contains(to)),
+                ),
+        );
+    }
+    outputs.extend(static_files);
+
+    // the stylesheets and scripts that posts include are published along
+    // with the other static files
+    let static_includes: BTreeSet<&str> = publishable_posts
+        .iter()
+        .chain(&drafts)
+        .flat_map(|post| {
+            post.post
+                .metadata
+                .extra_css
+                .iter()
+                .chain(&post.post.metadata.extra_js)
+        })
+        .map(|path| path.trim_start_matches('/'))
+        .collect();
+    for include in static_includes {
+        let in_theme = theme_static_dir
+            .as_ref()
+            .is_some_and(|d| d.join(include).is_file());
+        if !static_dir.join(include).is_file() && !in_theme {
+            println!(
+                "Included file {} does not exist",
+                static_dir.join(include).display()
+            );
+        }
+    }
+
+    // write the development server's dashboard, along with previews of drafts
+    if config.is_dev_server {
+        let draft_dir = output_dir.join(DRAFT_PREVIEW_DIR);
+        fs::create_dir_all(&draft_dir).unwrap();
+
+        for draft in &drafts {
+            let page = rendering::render_post_page(draft, None, None, config, &build_info);
+            outputs.extend(OutputFile::rendered(
+                draft_dir.join(&draft.output_filename),
+                page,
+                &mut render_errors,
+            ));
+
+            let preview = rendering::render_social_preview(draft, config);
+            let preview_path = social_preview_dir.join(&draft.output_filename);
+            outputs.extend(OutputFile::rendered(
+                preview_path,
+                preview,
+                &mut render_errors,
+            ));
+
+            outputs.extend(copy_post_assets(draft, output_dir, &mut failures));
+        }
+
+        let published_entries = publishable_posts.iter().map(|post| DashboardEntry {
+            post,
+            status: post.post.metadata.status(config),
+            preview_url: format!("/{}", post.url_path()),
+        });
+        let draft_entries = drafts.iter().map(|post| DashboardEntry {
+            post,
+            status: post.post.metadata.status(config),
+            preview_url: format!("/{}/{}", DRAFT_PREVIEW_DIR, post.url_path()),
+        });
+
+        let mut entries: Vec<DashboardEntry> = published_entries.chain(draft_entries).collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.post.post.metadata.date));
+
+        outputs.extend(OutputFile::rendered(
+            output_dir.join(DASHBOARD_FILE),
+            rendering::render_dashboard(&entries, config),
+            &mut render_errors,
+        ));
+    }
+
+    // write draft previews, which are not linked from anywhere else. They
+    // are written last since they are moved to their secret paths
+    if let Some(ref secret) = config.draft_preview_secret {
+        fs::create_dir_all(output_dir.join("drafts")).unwrap();
+
+        for draft in &mut drafts {
+            draft.move_to_preview(secret, config);
+
+            let page = rendering::render_post_page(draft, None, None, config, &build_info);
+            outputs.extend(OutputFile::rendered(
+                output_dir.join(&draft.output_filename),
+                page,
+                &mut render_errors,
+            ));
+            outputs.extend(copy_post_assets(draft, output_dir, &mut failures));
+
+            println!(
+                "Draft preview for {:?}: {}",
+                draft.post.metadata.title,
+                draft.url(config)
+            );
+        }
+    }
+
+    // map the source images to their published URLs, for the build manifest
+    let mut images = BTreeMap::new();
+    for post in &publishable_posts {
+        for img in post.images.iter().chain(&post.cover) {
+            let source = post.filepath.parent().unwrap().join(&img.original_path);
+            let source = source.strip_prefix(blog_root).unwrap_or(&source);
+            images.insert(urls::from_path(source), img.url());
+        }
+    }
+
+    // write rss feed
+    if config.enable_rss {
+        let feed = feed::build_feed(&publishable_posts, config);
+        let mut pages = match config.feed_page_size {
+            Some(page_size) => feed::paginate(feed, routes::FEED_FILE, page_size, config),
+            None => vec![(routes::FEED_FILE.to_owned(), feed)],
+        };
+        // Only the first page is linked from the pages and aliased
+        for (path, page) in pages.drain(1..) {
+            outputs.extend(OutputFile::rendered(
+                output_dir.join(path),
+                rendering::render_feed(&page),
+                &mut render_errors,
+            ));
+        }
+
+        let (_, ref feed) = pages[0];
+        match rendering::render_feed(feed) {
+            Ok(feed_xml) => {
+                match config.feed_alias_mode {
+                    FeedAliasMode::Copy => {
+                        for alias in &config.feed_aliases {
+                            let path = output_dir.join(alias.trim_start_matches('/'));
+                            fs::create_dir_all(path.parent().unwrap()).unwrap();
+                            outputs.push(OutputFile::write(path, feed_xml.as_str()));
+                        }
+                    }
+                    FeedAliasMode::Redirect => {
+                        redirects.extend(config.feed_aliases.iter().map(|alias| Redirect {
+                            from: format!("/{}", alias.trim_start_matches('/')),
+                            to: format!("/{}", routes::FEED_FILE),
+                        }));
+                    }
+                }
+
+                outputs.push(OutputFile::write(
+                    output_dir.join(routes::FEED_FILE),
+                    feed_xml,
+                ));
+                outputs.push(OutputFile::write(
+                    output_dir.join("feed.xsl"),
+                    rendering::feed_stylesheet(),
+                ));
+            }
+            Err(e) => render_errors.push(e),
+        }
+    }
+
+    for (name, section) in &config.sections {
+        let path = match section.feed {
+            Some(ref feed) => output_dir.join(feed.trim_start_matches('/')),
+            None => continue,
+        };
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let feed = feed::build_section_feed(&publishable_posts, name, config);
+        outputs.extend(OutputFile::rendered(
+            path,
+            rendering::render_feed(&feed),
+            &mut render_errors,
+        ));
+    }
+
+    if config.enable_json_feed {
+        let feed = feed::build_feed(&publishable_posts, config);
+        outputs.push(OutputFile::write(
+            output_dir.join("feed.json"),
+            serde_json::to_string_pretty(&feed::build_json_feed(&feed, config)).unwrap(),
+        ));
+    }
+
+    // write the index of the search page
+    if config.enable_search {
+        outputs.push(OutputFile::write(
+            output_dir.join("search-index.json"),
+            serde_json::to_string(&search::build_search_index(&publishable_posts, config)).unwrap(),
+        ));
+    }
+
+    // write sitemap
+    let sitemap = sitemap::build_sitemap(&routes);
+    outputs.extend(OutputFile::rendered(
+        output_dir.join("sitemap.xml"),
+        rendering::render_sitemap(&sitemap),
+        &mut render_errors,
+    ));
+
+    // write ActivityPub documents
+    if let Some(ref settings) = config.activitypub {
+        let feed = feed::build_feed(&publishable_posts, config);
+        let documents = activitypub::build_documents(&feed, settings, config);
+
+        fs::create_dir_all(output_dir.join(".well-known")).unwrap();
+        fs::create_dir_all(output_dir.join("activitypub")).unwrap();
+
+        outputs.push(OutputFile::write(
+            output_dir.join(".well-known").join("webfinger"),
+            documents.webfinger,
+        ));
+        outputs.push(OutputFile::write(
+            output_dir.join("activitypub").join("actor.json"),
+            documents.actor,
+        ));
+        outputs.push(OutputFile::write(
+            output_dir.join("activitypub").join("outbox.json"),
+            documents.outbox,
+        ));
+    }
+
+    // write changelog, if the blog has one
+    let changelog_dir = blog_root.join("changelog");
+    if changelog_dir.is_dir() {
+        let changelog_entries = read_posts(
+            &changelog_dir,
+            context,
+            config,
+            None,
+            remote_images.as_ref(),
+            config.include_drafts,
+            &mut failures,
+        );
+
+        let releases = changelog::group_releases(&changelog_entries);
+        outputs.extend(OutputFile::rendered(
+            output_dir.join("changelog.html"),
+            rendering::render_changelog(&releases, config),
+            &mut render_errors,
+        ));
+
+        let feed = feed::build_changelog_feed(&releases, config);
+        outputs.extend(OutputFile::rendered(
+            output_dir.join("changelog.xml"),
+            rendering::render_feed(&feed),
+            &mut render_errors,
+        ));
+
+        for entry in &changelog_entries {
+            outputs.extend(copy_post_assets(entry, output_dir, &mut failures));
+        }
+    }
+
+    // write talks page, if the blog lists them
+    let talks_path = blog_root.join("data").join("talks.yaml");
+    if talks_path.exists() {
+        let content = fs::read_to_string(&talks_path).unwrap();
+        match talks::parse_talks(&content) {
+            Ok(talks) => {
+                let years = talks::group_by_year(&talks);
+                outputs.extend(OutputFile::rendered(
+                    output_dir.join("talks.html"),
+                    rendering::render_talks(&years, config),
+                    &mut render_errors,
+                ));
+            }
+            Err(e) => println!("Failed to read {}: {}", talks_path.display(), e),
+        }
+    }
+
+    // write resume page, if the blog has one
+    let resume_path = ["resume.yaml", "resume.json"]
+        .iter()
+        .map(|f| blog_root.join("data").join(f))
+        .find(|p| p.exists());
+    if let Some(resume_path) = resume_path {
+        let content = fs::read_to_string(&resume_path).unwrap();
+        match resume::parse_resume(&content) {
+            Ok(resume) => {
+                outputs.extend(OutputFile::rendered(
+                    output_dir.join("resume.html"),
+                    rendering::render_resume(&resume, config),
+                    &mut render_errors,
+                ));
+            }
+            Err(e) => println!("Failed to read {}: {}", resume_path.display(), e),
+        }
+    }
+
+    for failure in &failures {
+        println!("Failed to compile {}", failure);
+    }
+
+    for error in &render_errors {
+        println!("Error: {}", error);
+    }
+
+    outputs.push(OutputFile::write(
+        output_dir.join(config.assets.path("styles.css")),
+        blog_stylesheet(blog_root, config),
+    ));
+
+    outputs.push(OutputFile::write(
+        output_dir.join(config.assets.path("syntax.css")),
+        context.syntax_css.as_str(),
+    ));
+
+    if config.navigation.enabled() {
+        outputs.push(OutputFile::write(
+            output_dir.join(config.assets.path("navigation.js")),
+            rendering::navigation_script(),
+        ));
+    }
+
+    if config.enable_search {
+        outputs.push(OutputFile::write(
+            output_dir.join(config.assets.path("search.js")),
+            rendering::search_script(),
+        ));
+    }
+
+    outputs.push(OutputFile::write(
+        output_dir.join("assets.json"),
+        config.assets.to_json(),
+    ));
+
+    for file in hosting::host_files(config, &redirects, &config.assets.published_paths()) {
+        outputs.push(OutputFile::write(output_dir.join(file.path), file.contents));
+    }
+
+    // Links to the blog itself are made relative to each page, e.g: to
+    // browse a committed `docs` directory from wherever it is served
+    if config.relative_urls && !config.is_dev_server {
+        for output in outputs.iter_mut() {
+            if let OutputFile::Write { path, contents } = output {
+                if path.extension().is_some_and(|e| e == "html") {
+                    let page = path.strip_prefix(output_dir).unwrap_or(path.as_path());
+                    *contents = urls::relativize(contents, &config.base_url, page);
+                }
+            }
+        }
+    }
+
+    if config.format_html {
+        for output in outputs.iter_mut() {
+            if let OutputFile::Write { path, contents } = output {
+                if path.extension().is_some_and(|e| e == "html") {
+                    *contents = formatter::format_html(contents);
+                }
+            }
+        }
+    }
+
+    timings.templating = stage_start.elapsed();
+    stage_start = Instant::now();
+
+    write_outputs(outputs);
+
+    let next_post = next_scheduled_post(&scheduled, config)
+        .or_else(|| next_scheduled_post(&publishable_posts, config));
+    let next_post_at = next_post.map(|post| {
+        let published_at = post.post.metadata.published_at(config);
+        println!(
+            "Next scheduled post: {:?}, published at {}",
+            post.post.metadata.title, published_at
+        );
+        published_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    });
+    write_build_manifest(output_dir, &routes, images, next_post_at);
+
+    timings.writing = stage_start.elapsed();
+
+    BuildReport {
+        timings,
+        failures,
+        posts: publishable_posts,
+    }
+}
+
+/// Renders a post from its markdown as it would be published, without writing
+/// anything to the output directory. Images are looked up relative to
+/// `filepath`, but only the ones that were already published are shown.
+pub fn render_preview(
+    content: &str,
+    filepath: &Path,
+    posts_dir: &Path,
+    config: &BlogConfiguration,
+    context: &BuildContext,
+) -> Result<String, String> {
+    let arena = Arena::new();
+    let plugins = context.plugins();
+    let compiler = PostCompiler::new(arena, &context.options, &plugins);
+
+    let content = source_format(filepath)
+        .to_markdown(content)
+        .map_err(|e| e.message)?;
+    let content = match config.shortcodes {
+        true => shortcodes::expand(&content),
+        false => content,
+    };
+
+    let defaults = FrontmatterDefaults::load(posts_dir, filepath)?;
+    let parsed = build_blog_post(&content, &compiler, config, &defaults).map_err(|e| e.message)?;
+    let publishable = prepare_for_publish(parsed, filepath.to_owned(), &compiler, config);
+
+    rendering::render_post_page(&publishable, None, None, config, &BuildInfo::now())
+        .map_err(|e| e.to_string())
+}
+
+/// A file produced by the build
+enum OutputFile {
+    Write {
+        path: PathBuf,
+        contents: String,
+    },
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Thumbnail {
+        from: PathBuf,
+        to: PathBuf,
+        width: u32,
+        height: u32,
+    },
+    /// Image written in the format of its destination's extension
+    Converted {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Smaller version of an image, keeping its aspect ratio
+    Resized {
+        from: PathBuf,
+        to: PathBuf,
+        width: u32,
+    },
+}
+
+impl OutputFile {
+    fn write(path: PathBuf, contents: impl Into<String>) -> Self {
+        OutputFile::Write {
+            path,
+            contents: contents.into(),
+        }
+    }
+
+    /// Output of a rendered page. Pages that failed to render are left out
+    /// and their error is collected, so that the rest of the blog is still
+    /// built
+    fn rendered(
+        path: PathBuf,
+        page: Result<String, RenderError>,
+        errors: &mut Vec<RenderError>,
+    ) -> Option<Self> {
+        match page {
+            Ok(contents) => Some(OutputFile::Write { path, contents }),
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        }
+    }
+}
+
+// Writes the output files concurrently, bounded by a dedicated pool so that
+// the build doesn't open too many files at once
+fn write_outputs(outputs: Vec<OutputFile>) {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(IO_THREADS)
+        .build()
+        .unwrap();
+
+    pool.install(|| {
+        outputs.into_par_iter().for_each(|output| match output {
+            OutputFile::Write { path, contents } => fs::write(path, contents).unwrap(),
+            OutputFile::Copy { from, to } => {
+                fs::copy(from, to).unwrap();
+            }
+            OutputFile::Thumbnail {
+                from,
+                to,
+                width,
+                height,
+            } => {
+                // Thumbnails are named after their source, so existing ones
+                // are up to date
+                if to.exists() {
+                    return;
+                }
+
+                if let Err(e) = thumbnails::generate(&from, &to, width, height) {
+                    println!("Failed to generate thumbnail {}: {}", to.display(), e);
+                }
+            }
+            OutputFile::Converted { from, to } => {
+                // As thumbnails, converted images are named after their
+                // source
+                if to.exists() {
+                    return;
+                }
+
+                if let Err(e) = responsive_images::convert(&from, &to) {
+                    println!("Failed to convert image {}: {}", to.display(), e);
+                }
+            }
+            OutputFile::Resized { from, to, width } => {
+                // As thumbnails, resized images are named after their source
+                if to.exists() {
+                    return;
+                }
+
+                if let Err(e) = responsive_images::generate(&from, &to, width) {
+                    println!("Failed to resize image {}: {}", to.display(), e);
+                }
+            }
+        })
+    });
+}
+
+// Fetches the amount of comments in a discussion thread, for the sites that
+// support it
+fn fetch_comment_count(discussion: &str) -> Option<u64> {
+    let query = CommentCountQuery::from_discussion_url(discussion)?;
+
+    match ureq::get(&query.api_url).call() {
+        Ok(response) => {
+            let body: serde_json::Value = response.into_json().ok()?;
+            query.comment_count(&body)
+        }
+        Err(e) => {
+            println!("Failed to fetch comment count of {}: {}", discussion, e);
+            None
+        }
+    }
+}
+
+/// Previews of the links that are alone in a paragraph, which are rendered as
+/// preview cards. Previews are cached in the blog's root, so that pages are
+/// only fetched once and the blog can still be built offline
+pub struct LinkPreviews {
+    cache_path: PathBuf,
+    previews: Mutex<HashMap<String, LinkPreview>>,
+    fetched_any: AtomicBool,
+}
+
+impl LinkPreviews {
+    fn load(blog_root: &Path) -> Self {
+        let cache_path = blog_root.join(LINK_PREVIEWS_FILE);
+        let previews = fs::read_to_string(&cache_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        LinkPreviews {
+            cache_path,
+            previews: Mutex::new(previews),
+            fetched_any: AtomicBool::new(false),
+        }
+    }
+
+    // Fetches the previews missing from the cache, then renders them in the
+    // post. The cache isn't locked while fetching, so that posts compiled in
+    // parallel don't wait on each other's requests
+    fn render<'a>(&self, ast: &'a AstNode<'a>) {
+        for url in link_preview::bare_urls(ast) {
+            if self.previews.lock().unwrap().contains_key(&url) {
+                continue;
+            }
+
+            if let Some(preview) = fetch_link_preview(&url) {
+                self.previews.lock().unwrap().insert(url, preview);
+                self.fetched_any.store(true, Ordering::Relaxed);
+            }
+        }
+
+        link_preview::render_previews(ast, &self.previews.lock().unwrap());
+    }
+
+    fn save(self) {
+        if self.fetched_any.into_inner() {
+            let previews = self.previews.into_inner().unwrap();
+            fs::write(
+                self.cache_path,
+                serde_json::to_string_pretty(&previews).unwrap(),
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Images that posts link from other sites, which are downloaded to the
+/// blog's root once and then published along with the blog's own images
+pub struct RemoteImages {
+    cache_dir: PathBuf,
+}
+
+impl RemoteImages {
+    fn new(blog_root: &Path) -> Self {
+        // Images are referenced by their full path, as posts resolve them
+        // relative to their own directory
+        let blog_root = fs::canonicalize(blog_root).unwrap();
+
+        RemoteImages {
+            cache_dir: blog_root.join(REMOTE_IMAGES_DIR),
+        }
+    }
+
+    // Downloads the images of the post and its cover that are missing from
+    // the cache, then points the post to them
+    fn localize(&self, parsed: &mut ParsedBlogPost) {
+        let metadata = &mut parsed.post.metadata;
+        let cover = metadata
+            .image
+            .clone()
+            .filter(|url| remote_images::is_remote(url));
+
+        let mut files = HashMap::new();
+        for url in remote_images::remote_urls(parsed.ast)
+            .into_iter()
+            .chain(cover)
+        {
+            if let Some(file) = self.download(&url) {
+                files.insert(url, file);
+            }
+        }
+
+        remote_images::localize(parsed.ast, &files);
+        if let Some(file) = metadata.image.as_ref().and_then(|url| files.get(url)) {
+            metadata.image = Some(file.display().to_string());
+        }
+    }
+
+    // Each image is kept in a directory named after a hash of its URL, under
+    // the name of the file it was downloaded from
+    fn download(&self, url: &str) -> Option<PathBuf> {
+        let name = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .unwrap_or_default();
+        // Names such as `..` would point out of the image's directory
+        let name: String = name
+            .chars()
+            .map(
+                |c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    true => c,
+                    false => '_',
+                },
+            )
+            .collect();
+        let filename = match name.trim_start_matches('.') {
+            "" => "image",
+            name => name,
+        };
+
+        let path = self
+            .cache_dir
+            .join(hashing::content_hash(url))
+            .join(filename);
+        if path.is_file() {
+            return Some(path);
+        }
+
+        let response = match ureq::get(url).call() {
+            Ok(response) if response.content_type().starts_with("image/") => response,
+            Ok(response) => {
+                println!(
+                    "Failed to download {}: expected an image, got {}",
+                    url,
+                    response.content_type()
+                );
+                return None;
+            }
+            Err(e) => {
+                println!("Failed to download {}: {}", url, e);
+                return None;
+            }
+        };
+
+        let mut image = Vec::new();
+        if let Err(e) = response.into_reader().read_to_end(&mut image) {
+            println!("Failed to download {}: {}", url, e);
+            return None;
+        }
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, image).unwrap();
+        Some(path)
+    }
+}
+
+fn fetch_link_preview(url: &str) -> Option<LinkPreview> {
+    match ureq::get(url).call() {
+        Ok(response) => {
+            let html = response.into_string().ok()?;
+            LinkPreview::from_html(url, &html)
+        }
+        Err(e) => {
+            println!("Failed to fetch link preview of {}: {}", url, e);
+            None
+        }
+    }
+}
+
+// Copies the images referenced by a post, its cover and audio version, if
+// any. Files that can't be read are reported as failures of the post
+fn copy_post_assets(
+    post: &PublishableBlogPost,
+    output_dir: &Path,
+    failures: &mut Vec<BuildPostError>,
+) -> Vec<OutputFile> {
+    let img_dir = output_dir.join("img");
+    let audio_dir = output_dir.join("audio");
+
+    // Images published in another format, e.g: WebP, are converted to it
+    let images = post.images.iter().chain(&post.cover).map(|img| {
+        let converted = Path::new(&img.original_path).extension() != img.final_path.extension();
+        (&img.original_path, img_dir.join(&img.final_path), converted)
+    });
+    let audio = post.audio.iter().map(|audio| {
+        (
+            &audio.original_path,
+            audio_dir.join(&audio.final_path),
+            false,
+        )
+    });
+
+    let mut copies = Vec::new();
+
+    if let Some(ref thumbnail) = post.thumbnail {
+        let source = post
+            .filepath
+            .parent()
+            .unwrap()
+            .join(&thumbnail.original_path);
+        copies.push(OutputFile::Thumbnail {
+            from: source,
+            to: img_dir.join(&thumbnail.final_path),
+            width: thumbnail.width,
+            height: thumbnail.height,
+        });
+    }
+
+    for img in post.images.iter().chain(&post.cover) {
+        let source = post.filepath.parent().unwrap().join(&img.original_path);
+        for variant in &img.variants {
+            copies.push(OutputFile::Resized {
+                from: source.clone(),
+                to: img_dir.join(&variant.final_path),
+                width: variant.width,
+            });
+        }
+    }
+
+    for (original_path, to, converted) in images.chain(audio) {
+        let post_parent = post.filepath.parent().unwrap().join(original_path);
+        match fs::canonicalize(post_parent) {
+            Ok(from) if converted => copies.push(OutputFile::Converted { from, to }),
+            Ok(from) => copies.push(OutputFile::Copy { from, to }),
+            Err(e) => failures.push(BuildPostError {
+                filepath: post.filepath.clone(),
+                content: String::new(),
+                line: 1,
+                column: 1,
+                message: format!("{}: {}", original_path, e),
+            }),
+        };
+    }
+
+    copies
+}
+
+// Copies the files of the static directory to the same paths in the output
+fn copy_static_files(static_dir: &Path, output_dir: &Path) -> Vec<OutputFile> {
+    let mut copies = Vec::new();
+
+    for entry in WalkDir::new(static_dir).into_iter().filter_map(Result::ok) {
+        let relative = entry.path().strip_prefix(static_dir).unwrap();
+        let to = output_dir.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(to).unwrap();
+        } else {
+            copies.push(OutputFile::Copy {
+                from: entry.into_path(),
+                to,
+            });
+        }
+    }
+
+    copies
+}
@@ -0,0 +1,195 @@
+use std::{collections::HashSet, path::Path};
+
+use pageturtle_core::{
+    blog::{sort_posts, withhold_scheduled, BlogConfiguration, PublishableBlogPost},
+    checks,
+    duplicates::{self, DuplicateReason},
+    golden, related, routes, urls,
+};
+use walkdir::WalkDir;
+
+use crate::{
+    build::{read_posts, BuildContext},
+    theme_directory,
+};
+
+/// Checks the blog without building it: its posts must compile, be published
+/// at different paths and the images and pages that they reference must
+/// exist. Returns whether all checks passed
+pub fn check_blog(blog_root: &Path, config: &BlogConfiguration) -> bool {
+    let mut problems: Vec<String> = vec![];
+
+    let context = BuildContext::new(config);
+    let mut failures = vec![];
+    let posts = read_posts(
+        &blog_root.join("posts"),
+        &context,
+        config,
+        None,
+        None,
+        true,
+        &mut failures,
+    );
+    for failure in &failures {
+        problems.push(failure.to_string());
+    }
+
+    for (page, filepaths) in checks::shared_pages(&posts) {
+        let filepaths: Vec<String> = filepaths.iter().map(|f| f.display().to_string()).collect();
+        problems.push(format!(
+            "{} are all published at {}",
+            filepaths.join(", "),
+            page.display()
+        ));
+    }
+
+    // Posts published at the same page were already reported
+    let duplicates = duplicates::find_duplicates(&posts)
+        .into_iter()
+        .filter(|d| d.first.output_filename != d.second.output_filename);
+    for duplicate in duplicates {
+        let reason = match duplicate.reason {
+            DuplicateReason::SameTitle => "have the same title".to_owned(),
+            DuplicateReason::SimilarContent(similarity) => {
+                format!("are {:.0}% the same", similarity * 100.0)
+            }
+        };
+        problems.push(format!(
+            "{} and {} {}",
+            duplicate.first.filepath.display(),
+            duplicate.second.filepath.display(),
+            reason
+        ));
+    }
+
+    let pages = blog_pages(blog_root, &posts, config);
+    for post in &posts {
+        let post_dir = post.filepath.parent().unwrap();
+        let files = post
+            .images
+            .iter()
+            .chain(&post.cover)
+            .map(|image| &image.original_path)
+            .chain(post.audio.iter().map(|audio| &audio.original_path));
+        for file in files.filter(|f| !f.contains("://")) {
+            if !post_dir.join(file).is_file() {
+                problems.push(format!(
+                    "{}: {} does not exist",
+                    post.filepath.display(),
+                    file
+                ));
+            }
+        }
+
+        for link in checks::broken_links(post, &pages, config) {
+            problems.push(format!(
+                "{}: {} does not exist",
+                post.filepath.display(),
+                link
+            ));
+        }
+    }
+
+    for problem in &problems {
+        println!("[check] {}", problem);
+    }
+    if problems.is_empty() {
+        println!("[check] No problems found in {} posts", posts.len());
+    }
+
+    problems.is_empty()
+}
+
+/// Compares the pages of the blog with the golden files of `golden_dir`, or
+/// writes them when `update` is set. Returns whether all of them matched
+pub fn check_golden(
+    blog_root: &Path,
+    golden_dir: &Path,
+    update: bool,
+    config: &BlogConfiguration,
+) -> bool {
+    let context = BuildContext::new(config);
+    // Posts that fail to compile were already reported
+    let mut failures = vec![];
+    let mut posts = read_posts(
+        &blog_root.join("posts"),
+        &context,
+        config,
+        None,
+        None,
+        false,
+        &mut failures,
+    );
+    // The pages are the ones that a build publishes now
+    withhold_scheduled(&mut posts, config);
+    sort_posts(&mut posts, config.sort_by);
+    related::link_related_posts(&mut posts, config);
+
+    let pages = match golden::render_pages(&posts, config) {
+        Ok(pages) => pages,
+        Err(e) => {
+            println!("[check] {}", e);
+            return false;
+        }
+    };
+
+    let mismatches = match golden::compare(golden_dir, &pages, update) {
+        Ok(mismatches) => mismatches,
+        Err(e) => {
+            println!("[check] {}: {}", golden_dir.display(), e);
+            return false;
+        }
+    };
+
+    for mismatch in &mismatches {
+        println!("[check] {}", mismatch);
+    }
+    match update {
+        true => println!(
+            "[check] Wrote {} golden files to {}",
+            pages.len(),
+            golden_dir.display()
+        ),
+        false if mismatches.is_empty() => {
+            println!("[check] {} pages match their golden files", pages.len())
+        }
+        false => {}
+    }
+
+    mismatches.is_empty()
+}
+
+// Pages that the blog publishes, relative to its root
+fn blog_pages(
+    blog_root: &Path,
+    posts: &[PublishableBlogPost],
+    config: &BlogConfiguration,
+) -> HashSet<String> {
+    let mut pages: HashSet<String> = routes::collect_routes(posts, config)
+        .into_iter()
+        .map(|route| route.path)
+        .collect();
+    // Published when the blog has their data
+    for page in ["changelog.html", "talks.html", "resume.html"] {
+        pages.insert(page.to_owned());
+    }
+
+    let theme_static_dir = theme_directory(blog_root, config).map(|t| t.join("static"));
+    for static_dir in [
+        Some(blog_root.join(&config.static_directory)),
+        theme_static_dir,
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|d| d.is_dir())
+    {
+        for entry in WalkDir::new(&static_dir).into_iter().filter_map(Result::ok) {
+            if entry.path().extension().is_some_and(|e| e == "html") {
+                let relative = entry.path().strip_prefix(&static_dir).unwrap();
+                pages.insert(urls::from_path(relative));
+            }
+        }
+    }
+
+    pages
+}
@@ -1,37 +1,89 @@
+mod bench;
+mod build;
+mod check;
+mod manifest;
+mod posts;
+mod publish;
+mod samples;
+mod server;
+mod summary;
+mod tunnel;
+mod watch;
+
 use std::{
+    ffi::OsStr,
     fs,
     path::{Path, PathBuf},
-    println, thread,
+    process::Command as Process,
     time::Instant,
 };
 
 use clap::{Parser, Subcommand};
-use comrak::{Arena, ComrakExtensionOptions, ComrakOptions, ComrakPlugins};
-use crossbeam_channel::{unbounded, Receiver};
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use crossbeam_channel::never;
 use pageturtle_core::{
-    self,
-    blog::{
-        build_blog_post, prepare_for_publish, BlogConfiguration, BlogPost, HeadingRenderer,
-        PostCompiler, PublishableBlogPost,
-    },
-    feed, rendering,
+    blog::BlogConfiguration, formats::SourceFormat, highlighting, hosting::Host, rendering,
+    taxonomy::TagInfo, user_templates::UserTemplates,
+};
+
+use crate::{
+    bench::{run_benchmark, BENCH_BASELINE_FILE},
+    build::{build, BuildContext},
+    check::{check_blog, check_golden},
+    manifest::{diff_manifests, BUILD_MANIFEST_FILE},
+    posts::{init_blog, new_post, suggest_post_tags},
+    publish::{announce_new_posts, commit_output, GIT_OUTPUT_DIR},
+    samples::{check_samples, tangle_posts},
+    server::{random_token, start_dev_server},
+    summary::print_build_summary,
+    tunnel::open_tunnel,
+    watch::{run_command, watch, WatchEnd},
 };
-use rouille::{router, try_or_400, websocket, Response};
-use walkdir::WalkDir;
 
 #[derive(Debug)]
 /// Error that can happen when building a post from a filepath.
 /// Contains OS-level metadata such as filepath or file content.
-struct BuildPostError {
-    filepath: PathBuf,
-    content: String,
-    line: u32,
-    column: u32,
-    message: String,
+pub struct BuildPostError {
+    pub filepath: PathBuf,
+    pub content: String,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for BuildPostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.filepath.display(),
+            self.line,
+            self.column,
+            self.message
+        )?;
+
+        // Shows the line that failed, when the post could be read
+        let source_line = (self.line as usize)
+            .checked_sub(1)
+            .and_then(|index| self.content.lines().nth(index));
+        match source_line {
+            Some(source_line) => write!(f, "\n{:>5} | {}", self.line, source_line),
+            None => Ok(()),
+        }
+    }
 }
 
-const CONFIG_FILE: &'static str = "pageturtle.toml";
+pub const CONFIG_FILE: &str = "pageturtle.toml";
+
+/// Directory in the blog and in themes with the templates that replace the
+/// built-in ones
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Directory in the blog with a directory for each of its themes
+pub const THEMES_DIR: &str = "themes";
+
+/// Directory in the blog where `pageturtle tangle` extracts the code of the
+/// posts by default
+const TANGLE_DIR: &str = ".pageturtle-tangle";
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -53,9 +105,40 @@ enum Command {
         /// Port that the development server will listen
         port: u32,
 
+        #[clap(long, default_value_t = String::from("localhost"), forbid_empty_values = true)]
+        /// Address that the development server will bind to. Use 0.0.0.0 to
+        /// make it reachable from other devices in the local network
+        host: String,
+
         #[clap(short, long, forbid_empty_values = true)]
         /// Output directory
         output_directory: Option<String>,
+
+        #[clap(long, forbid_empty_values = true)]
+        /// Token required by the development server's API. A random one is
+        /// generated when it isn't given
+        api_token: Option<String>,
+
+        #[clap(long)]
+        /// Publishes the drafts along with the other posts, e.g: to preview
+        /// them
+        drafts: bool,
+
+        #[clap(long)]
+        /// Exposes the development server to the internet through a tunnel,
+        /// e.g: to have a draft reviewed before it is published. Opens a quick
+        /// tunnel with cloudflared unless a tunnel command is given
+        tunnel: bool,
+
+        #[clap(long, requires = "tunnel", forbid_empty_values = true)]
+        /// Command opening the tunnel instead, e.g: `ssh -R
+        /// 80:localhost:{port} me@example.com`, with `{port}` replaced by the
+        /// port of the development server
+        tunnel_command: Option<String>,
+
+        #[clap(long, requires = "tunnel", forbid_empty_values = true)]
+        /// Public URL of the tunnel, when the tunnel command doesn't print it
+        tunnel_url: Option<String>,
     },
     /// Builds the blog
     Build {
@@ -66,6 +149,63 @@ enum Command {
         #[clap(short, long, forbid_empty_values = true)]
         /// Output directory
         output_directory: Option<String>,
+
+        #[clap(long)]
+        /// Publishes the drafts along with the other posts, e.g: to preview
+        /// them
+        drafts: bool,
+
+        #[clap(long)]
+        /// Builds the blog to be committed along with it, e.g: for GitHub
+        /// Pages. The output goes to `docs` by default, is the same for the
+        /// same posts and isn't processed by Jekyll
+        for_git: bool,
+
+        #[clap(long, requires = "for_git")]
+        /// Commits the output once it is built, unless some posts failed to
+        /// compile
+        commit: bool,
+
+        #[clap(long, conflicts_with = "drafts")]
+        /// Pings the webhooks of `[notify]` with the posts that weren't
+        /// announced yet, once the blog is built without failures, e.g: as
+        /// the last step of deploying it
+        announce: bool,
+    },
+    /// Rebuilds the blog whenever its files change
+    Watch {
+        #[clap(short, long, default_value_t = String::from("."), forbid_empty_values = true)]
+        /// Blog directory
+        directory: String,
+
+        #[clap(short, long, forbid_empty_values = true)]
+        /// Output directory
+        output_directory: Option<String>,
+
+        #[clap(short, long, forbid_empty_values = true)]
+        /// Shell command run after each successful rebuild. The changed paths
+        /// are given as its arguments and in the PAGETURTLE_CHANGED_PATHS
+        /// environment variable
+        exec: Option<String>,
+
+        #[clap(long)]
+        /// Publishes the drafts along with the other posts, e.g: to preview
+        /// them
+        drafts: bool,
+    },
+    /// Creates a post with its frontmatter filled in, dated today
+    New {
+        #[clap(forbid_empty_values = true)]
+        /// Title of the post, which its file is named after
+        title: String,
+
+        #[clap(short, long, default_value_t = String::from("."), forbid_empty_values = true)]
+        /// Blog directory
+        directory: String,
+
+        #[clap(long)]
+        /// Creates the post as a draft
+        draft: bool,
     },
     /// Stars a new blog
     Init {
@@ -73,6 +213,96 @@ enum Command {
         /// Blog directory
         directory: String,
     },
+    /// Times the stages of building a synthesized blog
+    Bench {
+        #[clap(long, default_value_t = 500)]
+        /// Amount of posts in the blog
+        posts: usize,
+
+        #[clap(long, default_value_t = 1000)]
+        /// Amount of words in each post
+        words: usize,
+
+        #[clap(long, default_value_t = String::from(BENCH_BASELINE_FILE), forbid_empty_values = true)]
+        /// File with the timings that the results are compared against
+        baseline: String,
+
+        #[clap(long)]
+        /// Stores the results as the new baseline
+        save_baseline: bool,
+    },
+    /// Lists the URLs that were added, changed or removed since a previous
+    /// build, e.g: to purge them from a CDN after deploying
+    Diff {
+        #[clap(forbid_empty_values = true)]
+        /// Manifest of the previous build
+        old_manifest: String,
+
+        #[clap(short, long, default_value_t = String::from("."), forbid_empty_values = true)]
+        /// Blog directory
+        directory: String,
+
+        #[clap(short, long, forbid_empty_values = true)]
+        /// Output directory of the current build
+        output_directory: Option<String>,
+    },
+    /// Checks the posts of the blog without building it
+    Check {
+        #[clap(short, long, default_value_t = String::from("."), forbid_empty_values = true)]
+        /// Blog directory
+        directory: String,
+
+        #[clap(long)]
+        /// Runs the code blocks flagged with `run`, comparing what they print
+        /// with the `output` code block that follows them, if any
+        run_samples: bool,
+
+        #[clap(long, forbid_empty_values = true)]
+        /// Compares the pages of the blog with the golden files of this
+        /// directory, e.g: to catch unintended changes to a theme's templates
+        golden: Option<String>,
+
+        #[clap(long, requires = "golden")]
+        /// Writes the golden files with the current pages instead of
+        /// comparing them, e.g: after an intended change to the templates
+        update: bool,
+    },
+    /// Suggests tags for a post, preferring the tags of the posts it is
+    /// similar to, so that the blog's tags stay consistent
+    SuggestTags {
+        #[clap(forbid_empty_values = true)]
+        /// Path of the post
+        post: String,
+
+        #[clap(short, long, default_value_t = String::from("."), forbid_empty_values = true)]
+        /// Blog directory
+        directory: String,
+
+        #[clap(short, long, default_value_t = 5)]
+        /// Maximum amount of tags suggested
+        limit: usize,
+
+        #[clap(long)]
+        /// Adds the suggested tags to the post's frontmatter
+        write: bool,
+    },
+    /// Extracts the code blocks annotated with a file, e.g:
+    /// `file="src/main.rs"`, to source files, so that the code of tutorials
+    /// can be checked
+    Tangle {
+        #[clap(short, long, default_value_t = String::from("."), forbid_empty_values = true)]
+        /// Blog directory
+        directory: String,
+
+        #[clap(short, long, forbid_empty_values = true)]
+        /// Directory the code is extracted to, in a directory for each post
+        output_directory: Option<String>,
+
+        #[clap(long)]
+        /// Runs the `tangle` command of each post in the directory of its
+        /// code, e.g: to build or test it
+        run: bool,
+    },
 }
 
 fn main() {
@@ -81,19 +311,159 @@ fn main() {
         Command::Build {
             directory,
             output_directory,
+            drafts,
+            for_git,
+            commit,
+            announce,
         } => {
             let blog_root = Path::new(directory);
             let output = match output_directory {
                 Some(o) => Path::new(o).to_owned(),
+                None if *for_git => blog_root.join(GIT_OUTPUT_DIR),
                 None => blog_root.join("dist"),
             };
 
-            let config = read_config(blog_root);
+            let mut config = BlogConfiguration {
+                include_drafts: *drafts,
+                reproducible: *for_git,
+                ..read_config(blog_root)
+            };
+            // The output is published by GitHub Pages, which gets its
+            // `.nojekyll` and `CNAME` files
+            if *for_git && !config.hosting.hosts.contains(&Host::GithubPages) {
+                config.hosting.hosts.push(Host::GithubPages);
+            }
 
             let start = Instant::now();
-            build(blog_root, &output, &config);
+            let context = BuildContext::new(&config);
+            let report = build(blog_root, &output, &config, &context);
             let duration = start.elapsed();
             println!("Succesfully build blog in {:?}", duration);
+
+            print_build_summary(&output, &config.budgets);
+
+            if *commit {
+                if !report.failures.is_empty() {
+                    println!("[git] Not committing the blog, as some posts failed to compile");
+                    std::process::exit(1);
+                }
+                if let Err(msg) = commit_output(&output) {
+                    println!("[git] Failed to commit the blog: {}", msg);
+                    std::process::exit(1);
+                }
+            }
+
+            if *announce {
+                if !report.failures.is_empty() {
+                    println!("[notify] Not announcing new posts, as some posts failed to compile");
+                    std::process::exit(1);
+                }
+                announce_new_posts(blog_root, &report.posts, &config);
+            }
+        }
+        Command::Bench {
+            posts,
+            words,
+            baseline,
+            save_baseline,
+        } => {
+            let baseline = Path::new(baseline);
+            run_benchmark(*posts, *words, baseline, *save_baseline);
+        }
+        Command::Diff {
+            old_manifest,
+            directory,
+            output_directory,
+        } => {
+            let blog_root = Path::new(directory);
+            let output = match output_directory {
+                Some(o) => Path::new(o).to_owned(),
+                None => blog_root.join("dist"),
+            };
+
+            let config = read_config(blog_root);
+            let old = Path::new(old_manifest);
+            if let Err(msg) = diff_manifests(old, &output.join(BUILD_MANIFEST_FILE), &config) {
+                println!("Failed to compare the builds: {}", msg);
+            }
+        }
+        Command::Check {
+            directory,
+            run_samples,
+            golden,
+            update,
+        } => {
+            let blog_root = Path::new(directory);
+
+            // Reading the configuration panics when it is invalid, so it is
+            // checked beforehand
+            let config_path = blog_root.join(CONFIG_FILE);
+            let parsed = fs::read_to_string(&config_path)
+                .map_err(|e| e.to_string())
+                .and_then(|c| BlogConfiguration::from_toml(&c).map_err(|e| e.to_string()));
+            if let Err(e) = parsed {
+                println!("[check] {}: {}", config_path.display(), e);
+                std::process::exit(1);
+            }
+
+            let config = read_config(blog_root);
+            let mut passed = check_blog(blog_root, &config);
+            if *run_samples {
+                passed &= check_samples(&blog_root.join("posts"), &config);
+            }
+            if let Some(golden_dir) = golden {
+                passed &= check_golden(blog_root, Path::new(golden_dir), *update, &config);
+            }
+            if !passed {
+                std::process::exit(1);
+            }
+        }
+        Command::SuggestTags {
+            post,
+            directory,
+            limit,
+            write,
+        } => {
+            let blog_root = Path::new(directory);
+            let config = read_config(blog_root);
+            let post = Path::new(post);
+
+            if let Err(e) =
+                suggest_post_tags(&blog_root.join("posts"), post, &config, *limit, *write)
+            {
+                println!("Failed to suggest tags for {}: {}", post.display(), e);
+                std::process::exit(1);
+            }
+        }
+        Command::Tangle {
+            directory,
+            output_directory,
+            run,
+        } => {
+            let blog_root = Path::new(directory);
+            let output = match output_directory {
+                Some(o) => Path::new(o).to_owned(),
+                None => blog_root.join(TANGLE_DIR),
+            };
+
+            let config = read_config(blog_root);
+            if !tangle_posts(&blog_root.join("posts"), &output, &config, *run) {
+                std::process::exit(1);
+            }
+        }
+        Command::New {
+            title,
+            directory,
+            draft,
+        } => {
+            let posts_dir = Path::new(directory).join("posts");
+            match new_post(&posts_dir, title, *draft) {
+                Ok(path) => println!("Created {}", path.display()),
+                Err(msg) => {
+                    println!("Failed to create the post: {}", msg);
+                    std::process::exit(1);
+                }
+            }
         }
         Command::Init { directory } => {
             let path = Path::new(directory);
@@ -106,8 +476,14 @@ fn main() {
         }
         Command::Dev {
             port,
+            host,
             directory,
             output_directory,
+            api_token,
+            drafts,
+            tunnel,
+            tunnel_command,
+            tunnel_url,
         } => {
             let root = Path::new(directory);
             let output = match output_directory {
@@ -115,288 +491,217 @@ fn main() {
                 None => root.join("dist"),
             };
 
-            start_dev_server(*port, root, &output);
-        }
-    }
-}
+            let tunnel = match tunnel {
+                true => match open_tunnel(tunnel_command.as_deref(), tunnel_url.clone(), *port) {
+                    Ok(tunnel) => Some(tunnel),
+                    Err(msg) => {
+                        println!("[tunnel] {}", msg);
+                        std::process::exit(1);
+                    }
+                },
+                false => None,
+            };
 
-fn build(blog_root: &Path, output_directory: &Path, config: &BlogConfiguration) {
-    // The returned nodes are created in the supplied Arena, and are bound by its lifetime.
-    let arena = Arena::new();
-
-    // let adapter = SyntectAdapter::new("base16-ocean.dark");
-    // plugins.render.codefence_syntax_highlighter = Some(&adapter);
-    let options = &ComrakOptions {
-        extension: ComrakExtensionOptions {
-            front_matter_delimiter: Some("---".to_owned()),
-            ..ComrakExtensionOptions::default()
-        },
-        ..ComrakOptions::default()
-    };
-
-    let adapter = HeadingRenderer::default();
-    let mut plugins = ComrakPlugins::default();
-    plugins.render.heading_adapter = Some(&adapter);
-
-    let compiler = PostCompiler::new(arena, options, &plugins);
-
-    let mut posts: Vec<(PathBuf, BlogPost)> = vec![];
-    let mut failures: Vec<BuildPostError> = vec![];
-    let posts_dir = blog_root.join("posts");
-
-    // TODO: parse files in parallel
-    let walker = WalkDir::new(posts_dir).into_iter();
-    for entry in walker {
-        let entry = entry.unwrap();
-        if entry.file_type().is_dir() {
-            continue;
-        };
+            let api_token = api_token.clone().unwrap_or_else(random_token);
+            start_dev_server(
+                host,
+                *port,
+                root,
+                &output,
+                api_token,
+                *drafts,
+                tunnel.as_ref().map(|(_, url)| url.as_str()),
+            );
+
+            if let Some((mut process, _)) = tunnel {
+                let _ = process.kill();
+            }
+        }
+        Command::Watch {
+            directory,
+            output_directory,
+            exec,
+            drafts,
+        } => {
+            let root = Path::new(directory);
+            let output = match output_directory {
+                Some(o) => Path::new(o).to_owned(),
+                None => root.join("dist"),
+            };
 
-        let filepath = entry.path();
-        match filepath.extension() {
-            Some(e) => {
-                if !check_allowed_filetype(e.to_str().unwrap()) {
-                    continue;
+            println!("Watching {} for changes", root.display());
+
+            loop {
+                let config = BlogConfiguration {
+                    include_drafts: *drafts,
+                    ..read_config(root)
+                };
+                let context = BuildContext::new(&config);
+                build(root, &output, &config, &context);
+
+                let end = watch(
+                    root,
+                    &output,
+                    &config,
+                    &context,
+                    never(),
+                    |paths, failures| match (exec, failures.is_empty()) {
+                        (Some(command), true) => run_command(command, paths),
+                        (Some(command), false) => println!(
+                            "[exec] skipped {} since {} post(s) failed to build",
+                            command,
+                            failures.len()
+                        ),
+                        (None, _) => {}
+                    },
+                );
+
+                match end {
+                    WatchEnd::ConfigChanged => println!("{} changed, rebuilding", CONFIG_FILE),
+                    WatchEnd::Stopped => break,
                 }
             }
-            None => continue,
         }
+    }
+}
 
-        let content = fs::read_to_string(filepath).unwrap();
-
-        match build_blog_post(&content, &compiler) {
-            Ok(post) => posts.push((filepath.to_owned(), post)),
-            Err(e) => failures.push(BuildPostError {
-                filepath: filepath.into(),
-                content,
-                line: e.line,
-                column: e.column,
-                message: e.message,
-            }),
-        };
+/// Runs a user command through the shell
+pub fn shell(command: &str) -> Process {
+    if cfg!(windows) {
+        let mut p = Process::new("cmd");
+        p.arg("/C").arg(command);
+        p
+    } else {
+        let mut p = Process::new("sh");
+        p.arg("-c").arg(command);
+        p
     }
+}
 
-    let output_dir = Path::new(output_directory);
+pub fn check_allowed_filetype(extension: &str) -> bool {
+    SourceFormat::from_extension(extension).is_some()
+}
 
-    if !output_dir.exists() {
-        fs::create_dir_all(output_dir).unwrap();
-    }
+/// Format of a post from the extension of its file, which was already checked
+/// to be allowed
+pub fn source_format(filepath: &Path) -> SourceFormat {
+    filepath
+        .extension()
+        .and_then(OsStr::to_str)
+        .and_then(SourceFormat::from_extension)
+        .unwrap_or(SourceFormat::Markdown)
+}
 
-    let mut publishable_posts: Vec<PublishableBlogPost> = posts
-        .iter()
-        .filter(|(_, post)| !post.metadata.draft)
-        .map(|(path, post)| prepare_for_publish(post, path, &compiler))
-        .collect();
-
-    publishable_posts.sort_by(|a, b| b.post.metadata.date.cmp(&a.post.metadata.date));
-
-    // create index page
-    let index_html = rendering::render_index(&publishable_posts, config);
-    let path = output_dir.join("index.html");
-    fs::write(path, index_html).unwrap();
-
-    // create tags page
-    let tags_html = rendering::render_tags_page(&publishable_posts, config);
-    let tags_path = output_dir.join("tags.html");
-    fs::write(tags_path, tags_html).unwrap();
-
-    // setup images directory
-    let img_dir = output_dir.join("img");
-    fs::create_dir_all(&img_dir).unwrap();
-
-    // write posts
-    for post in &publishable_posts {
-        let path = output_dir.join(&post.output_filename);
-        // println!("writing file {:?}", path);
-        let page = rendering::render_post_page(post, config);
-        fs::write(path, page).unwrap();
-
-        // Copy post images, if any
-        for img in &post.images {
-            let post_parent = post.filepath.parent().unwrap().join(&img.original_path);
-            match fs::canonicalize(post_parent) {
-                Ok(from) => {
-                    let to = img_dir.join(&img.final_path);
-                    fs::copy(from, to).unwrap();
-                }
-                Err(e) => {
-                    dbg!(e);
-                    // TODO: handle properly
+pub fn read_config(blog_root: &Path) -> BlogConfiguration {
+    // TODO: nice error messages!!
+    let config_file = fs::read_to_string(blog_root.join(CONFIG_FILE)).unwrap();
+    let mut config = BlogConfiguration::from_toml(&config_file).unwrap();
+
+    // Tags described in the configuration file take precedence
+    let tags_dir = blog_root.join("tags");
+    if tags_dir.is_dir() {
+        for entry in fs::read_dir(tags_dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = match path.file_stem() {
+                Some(stem) if path.extension().is_some_and(|e| e == "md") => {
+                    stem.to_str().unwrap().to_owned()
                 }
+                _ => continue,
             };
+
+            if config.tags.info(&config.tags.slug(&name)).is_some() {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path).unwrap();
+            match TagInfo::from_markdown(&content) {
+                Ok(info) => {
+                    config.tags.info.insert(name, info);
+                }
+                Err(e) => println!("Failed to read {}: {}", path.display(), e),
+            }
         }
     }
 
-    // write rss feed
-    if config.enable_rss {
-        let feed = feed::build_feed(&publishable_posts, config);
-        let feed_xml = rendering::render_feed(&feed);
-        fs::write(output_dir.join("atom.xml"), feed_xml).unwrap();
+    let theme = theme_directory(blog_root, &config);
+    if let (Some(name), None) = (&config.theme, &theme) {
+        println!("Theme {} does not exist in {}", name, THEMES_DIR);
     }
 
-    dbg!(&failures);
-
-    fs::write(output_dir.join("styles.css"), rendering::stylesheet()).unwrap();
-}
+    let mut template_dirs = vec![blog_root.join(TEMPLATES_DIR)];
+    template_dirs.extend(theme.as_ref().map(|t| t.join(TEMPLATES_DIR)));
+    match UserTemplates::load(&template_dirs) {
+        Ok(templates) => config.templates = templates,
+        Err(e) => println!("Failed to load the templates of the blog: {:?}", e),
+    }
 
-fn init_blog(target_directory: &Path) -> Result<(), String> {
-    let config = include_bytes!("other/pageturtle.toml");
-    let getting_started = include_bytes!("other/getting_started.md");
+    // Templates of sections take precedence over the ones of the blog, which
+    // they may extend
+    for (name, section) in config.sections.iter_mut() {
+        let directory = match section.templates {
+            Some(ref directory) => blog_root.join(directory),
+            None => continue,
+        };
+        if !directory.is_dir() {
+            println!(
+                "Templates of section {} do not exist in {}",
+                name,
+                directory.display()
+            );
+            continue;
+        }
 
-    if target_directory.exists() && !target_directory.is_dir() {
-        return Err("output path already exists and is not a directory".to_owned());
+        let dirs: Vec<PathBuf> = [directory]
+            .into_iter()
+            .chain(template_dirs.clone())
+            .collect();
+        match UserTemplates::load(&dirs) {
+            Ok(templates) => section.loaded_templates = templates,
+            Err(e) => println!("Failed to load the templates of section {}: {:?}", name, e),
+        }
     }
 
-    if !target_directory.exists() {
-        fs::create_dir_all(target_directory).unwrap();
+    let stylesheet = blog_stylesheet(blog_root, &config);
+    let unknown_themes = config.syntax_theme.unknown_themes();
+    if !unknown_themes.is_empty() {
+        println!(
+            "Syntax themes {} do not exist, using the default ones instead. The available themes are: {}",
+            unknown_themes.join(", "),
+            highlighting::theme_names().join(", ")
+        );
     }
 
-    fs::write(target_directory.join(CONFIG_FILE), config).unwrap();
-
-    let posts_dir = target_directory.join("posts");
-    fs::create_dir_all(&posts_dir).unwrap();
-    fs::write(posts_dir.join("getting_started.md"), getting_started).unwrap();
-
-    Ok(())
-}
+    let syntax_css = highlighting::stylesheet(&config.syntax_theme);
+    config.assets.insert("styles.css", stylesheet.as_bytes());
+    config.assets.insert("syntax.css", syntax_css.as_bytes());
+    if config.navigation.enabled() {
+        let script = rendering::navigation_script();
+        config.assets.insert("navigation.js", script.as_bytes());
+    }
+    if config.enable_search {
+        let script = rendering::search_script();
+        config.assets.insert("search.js", script.as_bytes());
+    }
 
-fn start_dev_server(port: u32, blog_root: &Path, output_directory: &Path) {
-    let output_2 = output_directory.to_owned();
-    let output = output_directory.to_owned();
-
-    let host = format!("localhost:{}", port);
-    let config = BlogConfiguration {
-        base_url: format!("http://{}", host),
-        is_dev_server: true,
-        ..read_config(blog_root)
-    };
-
-    build(blog_root, output_directory, &config);
-
-    // Create a channel to receive the events.
-    let (event_tx, event_rx) = unbounded();
-    let (changes_tx, changes_rx) = unbounded();
-
-    let changes_rx_2 = changes_rx;
-
-    let root = blog_root.to_owned();
-
-    thread::spawn(move || {
-        let mut watcher = RecommendedWatcher::new(event_tx, Config::default()).unwrap();
-
-        // Create a watcher object, delivering debounced events.
-        // The notification back-end is selected based on the platform.
-        watcher.watch(&root, RecursiveMode::Recursive).unwrap();
-
-        for res in event_rx {
-            match res {
-                Ok(Event {
-                    kind,
-                    paths,
-                    attrs: _,
-                }) => {
-                    match kind {
-                        notify::EventKind::Create(_) => {}
-                        notify::EventKind::Modify(_) | notify::EventKind::Remove(_) => {
-                            // TODO: prevent duplicate entries when saving a file
-                            // currently it is building more than once unnecessarily
-                            let path = paths.first().unwrap();
-                            if let Some(ext) = path.extension() {
-                                if check_allowed_filetype(ext.to_str().unwrap()) {
-                                    let start = Instant::now();
-                                    build(&root, &output, &config);
-                                    let duration = start.elapsed();
-                                    println!("[rebuilt] {:?}", duration);
-                                    changes_tx.send(path.clone()).unwrap();
-                                }
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-                Err(e) => println!("watch error: {:?}", e),
-            }
+    if let Some(threshold) = config.inline_styles_threshold {
+        let styles = format!("{}\n{}", stylesheet, syntax_css);
+        if styles.len() <= threshold {
+            config.inline_styles = Some(styles);
         }
-    });
-
-    thread::spawn(move || {
-        let host = format!("localhost:{}", port);
-
-        println!("pageturtle server listening on {}", &host);
-        rouille::start_server(host, move |request| {
-            {
-                if request.url() == "/" {
-                    return Response::redirect_303("/index.html");
-                }
-
-                // The `match_assets` function tries to find a file whose name corresponds to the URL
-                // of the request. The second parameter (`"."`) tells where the files to look for are
-                // located.
-                // In order to avoid potential security threats, `match_assets` will never return any
-                // file outside of this directory even if the URL is for example `/../../foo.txt`.
-                let response = rouille::match_assets(request, &output_2);
-
-                // If a file is found, the `match_assets` function will return a response with a 200
-                // status code and the content of the file. If no file is found, it will instead return
-                // an empty 404 response.
-                // Here we check whether if a file is found, and if so we return the response.
-                if response.is_success() {
-                    return response;
-                }
-            }
-
-            // This point of the code is reached only if no static file matched the request URL.
-
-            router!(request,
-                (GET) (/ws) => {
-                    // This is the websockets route.
-
-                    // In order to start using websockets we call `websocket::start`.
-                    // The function returns an error if the client didn't request websockets, in which
-                    // case we return an error 400 to the client thanks to the `try_or_400!` macro.
-                    //
-                    // The function returns a response to send back as part of the `start_server`
-                    // function, and a `websocket` variable of type `Receiver<Websocket>`.
-                    // Once the response has been sent back to the client, the `Receiver` will be
-                    // filled by rouille with a `Websocket` object representing the websocket.
-                    let (response, websocket) = try_or_400!(websocket::start(request, Some("handshake")));
-
-                    // Because of the nature of I/O in Rust, we need to spawn a separate thread for
-                    // each websocket.
-                    let changes_rx_3 = changes_rx_2.clone();
-
-                    thread::spawn(move || {
-                        // This line will block until the `response` above has been returned.
-                        let ws = websocket.recv().unwrap();
-                        // We use a separate function for better readability.
-                        // TODO: figure out about the probalby certain race conditon/batching that seems to be occuring
-                        websocket_handling_thread(ws, changes_rx_3);
-                    });
-
-                    response
-                },
-                _ => Response::empty_404()
-            )
-        });
-    }).join().unwrap();
-}
-
-// Function run in a separate thread.
-fn websocket_handling_thread(mut websocket: websocket::Websocket, rx: Receiver<PathBuf>) {
-    for msg in rx {
-        match websocket.send_text(msg.to_str().unwrap()) {
-            Ok(_) => (),
-            Err(_) => return, // probably the WS was closed
-        };
     }
+
+    config
 }
 
-fn check_allowed_filetype(extension: &str) -> bool {
-    vec!["md", "markdown"].contains(&extension)
+/// The stylesheet of the blog's theme, or the built-in one
+pub fn blog_stylesheet(blog_root: &Path, config: &BlogConfiguration) -> String {
+    theme_directory(blog_root, config)
+        .and_then(|t| fs::read_to_string(t.join("styles.css")).ok())
+        .unwrap_or_else(rendering::stylesheet)
 }
 
-fn read_config(blog_root: &Path) -> BlogConfiguration {
-    // TODO: nice error messages!!
-    let config_file = fs::read_to_string(blog_root.join(CONFIG_FILE)).unwrap();
-    BlogConfiguration::from_toml(&config_file).unwrap()
+/// Directory of the blog's theme, if it has one and it exists
+pub fn theme_directory(blog_root: &Path, config: &BlogConfiguration) -> Option<PathBuf> {
+    let directory = blog_root.join(THEMES_DIR).join(config.theme.as_ref()?);
+    directory.is_dir().then_some(directory)
 }
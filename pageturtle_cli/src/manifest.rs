@@ -0,0 +1,105 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use pageturtle_core::{blog::BlogConfiguration, hashing, routes::Route, urls};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+/// File in the output directory mapping the path of each published file to a
+/// hash of its contents, so that builds can be compared
+pub const BUILD_MANIFEST_FILE: &str = "manifest.json";
+
+/// Contents of the build manifest
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildManifest {
+    /// Hash of each file, by its path relative to the output directory
+    files: BTreeMap<String, String>,
+
+    /// Pages generated from the posts, relative to the output directory,
+    /// along with the path of their feed, if any
+    #[serde(default)]
+    routes: BTreeMap<String, Option<String>>,
+
+    /// URL of each image of the posts, by its path relative to the blog
+    /// directory, e.g: to find the published version of an image
+    #[serde(default)]
+    images: BTreeMap<String, String>,
+
+    /// When the next future-dated post is published, so that the next build
+    /// can be scheduled for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_scheduled_post: Option<String>,
+}
+
+/// Hashes every file in the output directory, so that the next build can be
+/// compared against this one
+pub fn write_build_manifest(
+    output_dir: &Path,
+    routes: &[Route],
+    images: BTreeMap<String, String>,
+    next_scheduled_post: Option<String>,
+) {
+    let mut manifest = BuildManifest {
+        images,
+        routes: routes
+            .iter()
+            .map(|route| (route.path.clone(), route.feed.clone()))
+            .collect(),
+        next_scheduled_post,
+        ..BuildManifest::default()
+    };
+
+    for entry in WalkDir::new(output_dir).into_iter().filter_map(Result::ok) {
+        let relative = entry.path().strip_prefix(output_dir).unwrap();
+        if !entry.file_type().is_file() || relative == Path::new(BUILD_MANIFEST_FILE) {
+            continue;
+        }
+
+        manifest.files.insert(
+            urls::from_path(relative),
+            hashing::content_hash(fs::read(entry.path()).unwrap()),
+        );
+    }
+
+    fs::write(
+        output_dir.join(BUILD_MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest).unwrap(),
+    )
+    .unwrap();
+}
+
+fn read_build_manifest(path: &Path) -> Result<BuildManifest, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Prints the URLs of the files that were added, changed or removed between
+/// two builds, one per line
+pub fn diff_manifests(old: &Path, new: &Path, config: &BlogConfiguration) -> Result<(), String> {
+    let old = read_build_manifest(old)?.files;
+    let new = read_build_manifest(new)?.files;
+
+    let paths: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut changes = 0;
+
+    for path in paths {
+        let change = match (old.get(path), new.get(path)) {
+            (None, Some(_)) => "added",
+            (Some(_), None) => "removed",
+            (Some(a), Some(b)) if a != b => "changed",
+            _ => continue,
+        };
+
+        println!("{:<8} {}/{}", change, config.base_url, path);
+        changes += 1;
+    }
+
+    if changes == 0 {
+        println!("No files changed");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use pageturtle_core::{
+    blog::BlogConfiguration,
+    defaults::FrontmatterDefaults,
+    formats::SourceFormat,
+    tag_suggestions::{self, PostTerms},
+};
+use serde_json::json;
+
+use crate::{build::post_files, source_format, CONFIG_FILE};
+
+/// Creates a post named after its title in the posts directory, returning its
+/// path
+pub fn new_post(posts_dir: &Path, title: &str, draft: bool) -> Result<PathBuf, String> {
+    let slug = slug::slugify(title);
+    if slug.is_empty() {
+        return Err("the post needs a title".to_owned());
+    }
+
+    let path = posts_dir.join(&slug).with_extension("md");
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+
+    fs::create_dir_all(posts_dir).map_err(|e| e.to_string())?;
+    fs::write(&path, new_post_markdown(title, &[], draft, "")).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+pub fn suggest_post_tags(
+    posts_dir: &Path,
+    post: &Path,
+    config: &BlogConfiguration,
+    limit: usize,
+    write: bool,
+) -> Result<(), String> {
+    let read_terms = |filepath: &Path| -> Result<PostTerms, String> {
+        let source = fs::read_to_string(filepath).map_err(|e| e.to_string())?;
+        let content = source_format(filepath)
+            .to_markdown(&source)
+            .map_err(|e| e.message)?;
+        let defaults = FrontmatterDefaults::load(posts_dir, filepath)?;
+        PostTerms::parse(&content, config, &defaults)
+    };
+
+    let post_terms = read_terms(post)?;
+
+    // Posts that can't be read are left out, as `check` reports them
+    let post_path = fs::canonicalize(post).ok();
+    let corpus: Vec<PostTerms> = post_files(posts_dir, config)
+        .iter()
+        .filter(|filepath| fs::canonicalize(filepath).ok() != post_path)
+        .filter_map(|filepath| read_terms(filepath).ok())
+        .collect();
+
+    let suggestions = tag_suggestions::suggest_tags(&post_terms, &corpus, config, limit);
+    if suggestions.is_empty() {
+        println!("No tags to suggest for {}", post.display());
+        return Ok(());
+    }
+
+    for suggestion in &suggestions {
+        println!(
+            "{:<24} {:.3}{}",
+            suggestion.tag,
+            suggestion.score,
+            if suggestion.existing { "" } else { " (new)" }
+        );
+    }
+
+    if write {
+        if source_format(post) != SourceFormat::Markdown {
+            return Err("only the frontmatter of markdown posts can be written".to_owned());
+        }
+
+        let tags: Vec<&str> = suggestions.iter().map(|s| s.tag.as_str()).collect();
+        let source = fs::read_to_string(post).map_err(|e| e.to_string())?;
+        fs::write(post, add_frontmatter_tags(&source, &tags)?).map_err(|e| e.to_string())?;
+        println!("Added {} tags to {}", tags.len(), post.display());
+    }
+
+    Ok(())
+}
+
+// Adds tags to the `tags` list of a post's frontmatter, keeping the rest of
+// the file as it is. The list may be either a flow (`tags: [a, b]`) or a
+// block one. The added lines end like the rest of the file's, e.g: `\r\n`
+fn add_frontmatter_tags(source: &str, tags: &[&str]) -> Result<String, String> {
+    let newline = match source.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+    let mut lines: Vec<String> = source.split_inclusive('\n').map(str::to_owned).collect();
+    if lines.first().map(|l| l.trim_end()) != Some("---") {
+        return Err("the post has no frontmatter".to_owned());
+    }
+    let end = lines
+        .iter()
+        .skip(1)
+        .position(|l| l.trim_end() == "---")
+        .map(|i| i + 1)
+        .ok_or("the post's frontmatter isn't closed")?;
+
+    let quoted: Vec<String> = tags.iter().map(|tag| json!(tag).to_string()).collect();
+    let tags_line = match (1..end).find(|&i| lines[i].starts_with("tags:")) {
+        Some(i) => i,
+        None => {
+            lines.insert(end, format!("tags: [{}]{}", quoted.join(", "), newline));
+            return Ok(lines.concat());
+        }
+    };
+
+    let value = lines[tags_line]["tags:".len()..].trim().to_owned();
+    if let Some(listed) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        let listed = listed.trim().trim_end_matches(',');
+        let all: Vec<&str> = [listed]
+            .into_iter()
+            .filter(|l| !l.is_empty())
+            .chain(quoted.iter().map(String::as_str))
+            .collect();
+        lines[tags_line] = format!("tags: [{}]{}", all.join(", "), newline);
+    } else if value.is_empty() || value.starts_with('#') {
+        // Items of block lists are either indented or start with `-`
+        let items = (tags_line + 1..end)
+            .take_while(|&i| lines[i].starts_with([' ', '\t', '-']))
+            .count();
+        let indent = lines[tags_line + 1..tags_line + 1 + items]
+            .iter()
+            .find(|l| l.trim_start().starts_with('-'))
+            .map_or("  ", |l| &l[..l.len() - l.trim_start().len()])
+            .to_owned();
+
+        for (n, tag) in quoted.iter().enumerate() {
+            let line = format!("{}- {}{}", indent, tag, newline);
+            lines.insert(tags_line + 1 + items + n, line);
+        }
+    } else {
+        return Err("the post's tags aren't a list".to_owned());
+    }
+
+    Ok(lines.concat())
+}
+
+/// Markdown of a new post dated today. JSON strings are valid YAML, which
+/// takes care of escaping
+pub fn new_post_markdown(title: &str, tags: &[String], draft: bool, content: &str) -> String {
+    let tags = match tags.is_empty() {
+        true => " []\n".to_owned(),
+        false => tags
+            .iter()
+            .map(|tag| format!("\n  - {}", json!(tag)))
+            .chain(["\n".to_owned()])
+            .collect(),
+    };
+
+    format!(
+        "---\ntitle: {}\ndate: {}\n{}tags:{}---\n\n{}\n",
+        json!(title),
+        chrono::Local::now().format("%Y-%m-%d"),
+        if draft { "draft: true\n" } else { "" },
+        tags,
+        content.trim_end()
+    )
+}
+
+pub fn init_blog(target_directory: &Path) -> Result<(), String> {
+    let config = include_bytes!("other/pageturtle.toml");
+    let getting_started = include_bytes!("other/getting_started.md");
+
+    if target_directory.exists() && !target_directory.is_dir() {
+        return Err("output path already exists and is not a directory".to_owned());
+    }
+
+    if !target_directory.exists() {
+        fs::create_dir_all(target_directory).unwrap();
+    }
+
+    fs::write(target_directory.join(CONFIG_FILE), config).unwrap();
+
+    let posts_dir = target_directory.join("posts");
+    fs::create_dir_all(&posts_dir).unwrap();
+    fs::write(posts_dir.join("getting_started.md"), getting_started).unwrap();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_tags_to_the_frontmatter() {
+        let cases = [
+            (
+                "---\ntitle: Post\n---\n\nContent\n",
+                "---\ntitle: Post\ntags: [\"rust\", \"web\"]\n---\n\nContent\n",
+            ),
+            (
+                "---\ntitle: Post\ntags: [notes]\n---\n",
+                "---\ntitle: Post\ntags: [notes, \"rust\", \"web\"]\n---\n",
+            ),
+            (
+                "---\ntags: []\ntitle: Post\n---\n",
+                "---\ntags: [\"rust\", \"web\"]\ntitle: Post\n---\n",
+            ),
+            (
+                "---\ntags:\n    - notes\ntitle: Post\n---\n",
+                "---\ntags:\n    - notes\n    - \"rust\"\n    - \"web\"\ntitle: Post\n---\n",
+            ),
+            (
+                "---\ntags: # by topic\n---\n",
+                "---\ntags: # by topic\n  - \"rust\"\n  - \"web\"\n---\n",
+            ),
+            (
+                "---\r\ntitle: Post\r\ntags:\r\n- notes\r\n---\r\nContent\r\n",
+                "---\r\ntitle: Post\r\ntags:\r\n- notes\r\n- \"rust\"\r\n- \"web\"\r\n---\r\nContent\r\n",
+            ),
+            (
+                "---\r\ntitle: Post\r\n---\r\n",
+                "---\r\ntitle: Post\r\ntags: [\"rust\", \"web\"]\r\n---\r\n",
+            ),
+            (
+                "---\r\ntags: [notes]\r\n---\r\n",
+                "---\r\ntags: [notes, \"rust\", \"web\"]\r\n---\r\n",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(
+                add_frontmatter_tags(source, &["rust", "web"]).as_deref(),
+                Ok(expected),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn fails_to_add_tags_without_a_frontmatter_list() {
+        let cases = [
+            ("# Post\n\nContent\n", "the post has no frontmatter"),
+            ("", "the post has no frontmatter"),
+            ("---\ntitle: Post\n", "the post's frontmatter isn't closed"),
+            ("---\ntags: notes\n---\n", "the post's tags aren't a list"),
+        ];
+
+        for (source, error) in cases {
+            assert_eq!(
+                add_frontmatter_tags(source, &["rust"]),
+                Err(error.to_owned()),
+                "{:?}",
+                source
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    process::Command as Process,
+};
+
+use pageturtle_core::blog::{BlogConfiguration, PublishableBlogPost};
+
+/// File in the blog directory listing the posts that were already announced
+/// to each of the notification webhooks
+const PUBLISHED_FILE: &str = ".pageturtle-published";
+
+/// Directory in the blog where `pageturtle build --for-git` writes the blog
+/// by default, which GitHub Pages can publish from
+pub const GIT_OUTPUT_DIR: &str = "docs";
+
+/// Message of the commits made by `pageturtle build --commit`
+const GIT_COMMIT_MESSAGE: &str = "Publish blog";
+
+/// Pings each configured webhook with the posts it wasn't told about yet.
+/// Posts are only recorded as announced to a webhook once it was notified,
+/// so that the ones of failed notifications are announced by the next run.
+/// The first time a webhook is notified, all the existing posts are
+/// considered as already announced, so that the whole blog doesn't get
+/// announced at once
+pub fn announce_new_posts(
+    blog_root: &Path,
+    posts: &[PublishableBlogPost],
+    config: &BlogConfiguration,
+) {
+    let published_path = blog_root.join(PUBLISHED_FILE);
+    let mut announced = read_announced_posts(&published_path, config);
+
+    for webhook in &config.notify.webhooks {
+        if !announced.contains_key(&webhook.url) {
+            announced.insert(
+                webhook.url.clone(),
+                posts.iter().map(|p| p.url_path()).collect(),
+            );
+            continue;
+        }
+        let known = announced.get_mut(&webhook.url).unwrap();
+
+        let new_posts: Vec<&PublishableBlogPost> = posts
+            .iter()
+            .filter(|p| !known.contains(&p.url_path()))
+            .collect();
+        if new_posts.is_empty() {
+            continue;
+        }
+
+        let mut request = ureq::post(&webhook.url);
+        if let Some(ref token) = webhook.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        match request.send_json(webhook.payload(&new_posts, config)) {
+            Ok(_) => {
+                println!(
+                    "[notify] Notified {} about {} new posts",
+                    webhook.url,
+                    new_posts.len()
+                );
+                known.extend(new_posts.iter().map(|p| p.url_path()));
+            }
+            Err(e) => println!("[notify] Failed to notify {}: {}", webhook.url, e),
+        }
+    }
+
+    fs::write(
+        published_path,
+        serde_json::to_string_pretty(&announced).unwrap(),
+    )
+    .unwrap();
+}
+
+// Posts that each webhook was told about, by the webhook's URL. Lists of
+// posts written before the webhooks were told apart are taken as announced
+// to all of them
+fn read_announced_posts(
+    path: &Path,
+    config: &BlogConfiguration,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return BTreeMap::new(),
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|_| {
+        let posts: BTreeSet<String> = content.lines().map(str::to_owned).collect();
+        config
+            .notify
+            .webhooks
+            .iter()
+            .map(|webhook| (webhook.url.clone(), posts.clone()))
+            .collect()
+    })
+}
+
+/// Commits the changes to the output directory, if there are any, without
+/// including other changes that were already staged
+pub fn commit_output(output_dir: &Path) -> Result<(), String> {
+    let git = |args: &[&str]| -> Result<bool, String> {
+        Process::new("git")
+            .args(args)
+            .arg("--")
+            .arg(output_dir)
+            .status()
+            .map(|status| status.success())
+            .map_err(|e| format!("failed to run git: {}", e))
+    };
+
+    if !git(&["add", "--all"])? {
+        return Err(format!("failed to stage {}", output_dir.display()));
+    }
+
+    // Exits successfully when nothing is staged
+    if git(&["diff", "--cached", "--quiet"])? {
+        println!("[git] The blog didn't change, nothing to commit");
+        return Ok(());
+    }
+
+    if !git(&["commit", "--quiet", "--message", GIT_COMMIT_MESSAGE])? {
+        return Err(format!("failed to commit {}", output_dir.display()));
+    }
+
+    println!("[git] Committed {}", output_dir.display());
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+    process::Stdio,
+    thread,
+    time::{Duration, Instant},
+};
+
+use pageturtle_core::{
+    blog::BlogConfiguration,
+    defaults::FrontmatterDefaults,
+    samples::{self, CodeSample, SamplesConfiguration},
+    tangle,
+};
+
+use crate::{build::post_files, shell, source_format};
+
+/// Extracts the code of each post to its own directory in `output`, named
+/// after the post's file, running the post's `tangle` command there when
+/// `run` is set. Returns whether all the code was extracted and checked
+pub fn tangle_posts(
+    directory: &Path,
+    output: &Path,
+    config: &BlogConfiguration,
+    run: bool,
+) -> bool {
+    let mut succeeded = true;
+
+    for filepath in post_files(directory, config) {
+        let tangled = source_format(&filepath)
+            .to_markdown(&fs::read_to_string(&filepath).unwrap())
+            .map_err(|e| e.message)
+            .and_then(|content| {
+                let defaults = FrontmatterDefaults::load(directory, &filepath)?;
+                tangle::tangle(&content, config, &defaults)
+            });
+        let tangled = match tangled {
+            Ok(tangled) if tangled.files.is_empty() => continue,
+            Ok(tangled) => tangled,
+            Err(e) => {
+                println!("[tangle] {}: {}", filepath.display(), e);
+                succeeded = false;
+                continue;
+            }
+        };
+
+        // Files of previous runs that the post no longer has could make its
+        // command succeed
+        let post_output = output.join(filepath.file_stem().unwrap());
+        let _ = fs::remove_dir_all(&post_output);
+        for file in &tangled.files {
+            let path = post_output.join(&file.path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &file.contents).unwrap();
+        }
+        println!(
+            "[tangle] {} -> {} ({} files)",
+            filepath.display(),
+            post_output.display(),
+            tangled.files.len()
+        );
+
+        let command = match tangled.command {
+            Some(command) if run => command,
+            _ => continue,
+        };
+        match shell(&command).current_dir(&post_output).status() {
+            Ok(status) if status.success() => println!("[tangle] {} passed", command),
+            Ok(status) => {
+                println!("[tangle] {} failed with {}", command, status);
+                succeeded = false;
+            }
+            Err(e) => {
+                println!("[tangle] failed to run {}: {}", command, e);
+                succeeded = false;
+            }
+        }
+    }
+
+    succeeded
+}
+
+/// Runs the code samples of the posts. Returns whether all of them passed
+pub fn check_samples(directory: &Path, config: &BlogConfiguration) -> bool {
+    let mut passed = true;
+    let mut sample_count = 0;
+
+    for filepath in post_files(directory, config) {
+        let post_samples = source_format(&filepath)
+            .to_markdown(&fs::read_to_string(&filepath).unwrap())
+            .map_err(|e| e.message)
+            .and_then(|content| {
+                let defaults = FrontmatterDefaults::load(directory, &filepath)?;
+                samples::extract_samples(&content, config, &defaults)
+            });
+
+        // Posts that can't be read were already reported
+        let post_samples = match post_samples {
+            Ok(post_samples) => post_samples,
+            Err(_) => continue,
+        };
+
+        for sample in post_samples {
+            // Each sample runs in a directory of its own, so that the files
+            // of one can't affect the others
+            let directory = std::env::temp_dir().join(format!(
+                "pageturtle-sample-{}-{}",
+                std::process::id(),
+                sample_count
+            ));
+            sample_count += 1;
+
+            fs::create_dir_all(&directory).unwrap();
+            let result = run_sample(&sample, &config.samples, &directory);
+            let _ = fs::remove_dir_all(&directory);
+
+            if let Err(e) = result {
+                println!("[check] {}:{}: {}", filepath.display(), sample.line, e);
+                passed = false;
+            }
+        }
+    }
+
+    println!("[check] Ran {} code samples", sample_count);
+
+    passed
+}
+
+// Runs a code sample with the runner of its language in `directory`. The
+// runner only sees `PATH` from the environment, and it is stopped once it
+// runs for longer than the timeout. Returns why the sample failed, if it did
+fn run_sample(
+    sample: &CodeSample,
+    config: &SamplesConfiguration,
+    directory: &Path,
+) -> Result<(), String> {
+    let runner = config
+        .runner(&sample.language)
+        .ok_or_else(|| format!("no runner for {} samples", sample.language))?;
+
+    let mut process = shell(runner);
+    process
+        .current_dir(directory)
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(path) = std::env::var_os("PATH") {
+        process.env("PATH", path);
+    }
+
+    let mut child = process
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", runner, e))?;
+
+    // The pipes are handled by other threads, as the runner could wait for
+    // its output to be read before reading the rest of the sample
+    let mut stdin = child.stdin.take().unwrap();
+    let code = sample.code.clone();
+    thread::spawn(move || {
+        let _ = stdin.write_all(code.as_bytes());
+    });
+    let read_pipe = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            text
+        })
+    };
+    let stdout = read_pipe(Box::new(child.stdout.take().unwrap()));
+    let stderr = read_pipe(Box::new(child.stderr.take().unwrap()));
+
+    let timeout = Duration::from_secs(config.timeout);
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() > timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {} seconds", config.timeout));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    let output = stdout.join().unwrap();
+    if !status.success() {
+        let errors = stderr.join().unwrap();
+        return Err(format!(
+            "{} failed with {}\n{}",
+            runner,
+            status,
+            errors.trim_end()
+        ));
+    }
+
+    if !sample.matches(&output) {
+        return Err(format!(
+            "expected the output\n{}\nbut got\n{}",
+            sample
+                .expected_output
+                .as_deref()
+                .unwrap_or_default()
+                .trim_end(),
+            output.trim_end()
+        ));
+    }
+
+    Ok(())
+}
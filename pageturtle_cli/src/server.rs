@@ -0,0 +1,566 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path as UrlPath, Query, Request, State,
+    },
+    http::{
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        HeaderMap, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossbeam_channel::{unbounded, Sender};
+use pageturtle_core::{blog::BlogConfiguration, urls};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use tokio::{
+    net::TcpListener,
+    sync::{
+        broadcast::{self, error::RecvError},
+        oneshot,
+    },
+};
+use tower_http::services::ServeDir;
+
+use crate::{
+    build::{build, BuildContext},
+    posts::new_post_markdown,
+    tunnel::print_qr_code,
+    watch::{watch, WatchEnd},
+    BuildPostError, CONFIG_FILE,
+};
+
+/// Directory inside the output where the development server keeps the social
+/// card previews of the posts
+pub const SOCIAL_PREVIEW_DIR: &str = "__preview/social";
+
+/// Where the development server keeps the pages of drafts
+pub const DRAFT_PREVIEW_DIR: &str = "__preview/drafts";
+
+/// Page of the development server listing all posts
+pub const DASHBOARD_FILE: &str = "__preview/dashboard.html";
+
+pub fn start_dev_server(
+    host: &str,
+    port: u32,
+    blog_root: &Path,
+    output_directory: &Path,
+    api_token: String,
+    include_drafts: bool,
+    tunnel_url: Option<&str>,
+) {
+    // When listening on all interfaces, links need to point to the LAN
+    // address so that the blog can be browsed from other devices
+    let lan_ip = match host {
+        "0.0.0.0" => local_ip_address::local_ip().ok(),
+        _ => None,
+    };
+
+    // Links of the pages served through a tunnel point to its public URL
+    let base_url = match (tunnel_url, lan_ip) {
+        (Some(url), _) => url.to_owned(),
+        (None, Some(ip)) => format!("http://{}:{}", ip, port),
+        (None, None) => format!("http://{}:{}", host, port),
+    };
+
+    if lan_ip.is_some() || tunnel_url.is_some() {
+        print_qr_code(&base_url);
+    }
+
+    println!("Development server API token: {}", api_token);
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    // The configuration is only read at startup, so the whole server is
+    // started again when it changes
+    loop {
+        let config = BlogConfiguration {
+            base_url: base_url.clone(),
+            is_dev_server: true,
+            include_drafts,
+            ..read_config(blog_root)
+        };
+
+        let basic_auth = dev_server_credentials(&config).map(|(user, password)| {
+            format!(
+                "Basic {}",
+                STANDARD.encode(format!("{}:{}", user, password))
+            )
+        });
+        if tunnel_url.is_some() && basic_auth.is_none() {
+            println!(
+                "[tunnel] Anyone with the URL can read the blog unless dev_server_auth is set"
+            );
+        }
+
+        let context = BuildContext::new(&config);
+        let report = build(blog_root, output_directory, &config, &context);
+
+        // Every connected page subscribes to this channel to know when to reload
+        let (reloads, _) = broadcast::channel(16);
+        let compile_errors = Arc::new(Mutex::new(compile_errors(&report.failures)));
+
+        // Rebuilds and previews requested through the API are run by the watcher
+        // thread, so that builds never run concurrently
+        let (watcher, watcher_requests) = unbounded();
+
+        let (config_changed_tx, config_changed) = oneshot::channel();
+
+        let root = blog_root.to_owned();
+        let output = output_directory.to_owned();
+        let reloads_tx = reloads.clone();
+        let watcher_errors = compile_errors.clone();
+
+        let watcher_thread = thread::spawn(move || {
+            let end = watch(
+                &root,
+                &output,
+                &config,
+                &context,
+                watcher_requests,
+                |paths, failures| {
+                    let errors = compile_errors(failures);
+                    *watcher_errors.lock().unwrap() = errors.clone();
+
+                    // Pages of posts that failed to compile weren't written,
+                    // so the errors are shown over the outdated pages instead
+                    let event = match errors.is_empty() {
+                        true => DevServerEvent::Reload {
+                            path: urls::from_path(&paths[0]),
+                        },
+                        false => DevServerEvent::CompileErrors { errors },
+                    };
+
+                    // Fails when there are no pages connected, which is fine
+                    let _ = reloads_tx.send(event);
+                },
+            );
+
+            if let WatchEnd::ConfigChanged = end {
+                let _ = config_changed_tx.send(());
+            }
+            end
+        });
+
+        let state = DevServerState {
+            blog_root: blog_root.to_owned(),
+            output_directory: output_directory.to_owned(),
+            reloads,
+            compile_errors,
+            watcher,
+            api_token: api_token.clone(),
+            basic_auth,
+            shutdown: broadcast::channel(1).0,
+        };
+
+        runtime.block_on(serve(format!("{}:{}", host, port), state, config_changed));
+
+        // The watcher stops by itself once the server is gone
+        match watcher_thread.join() {
+            Ok(WatchEnd::ConfigChanged) => continue,
+            _ => break,
+        }
+    }
+}
+
+// User and password of the development server, from the environment or from
+// the configuration
+fn dev_server_credentials(config: &BlogConfiguration) -> Option<(String, String)> {
+    let user = std::env::var("PAGETURTLE_DEV_USER");
+    let password = std::env::var("PAGETURTLE_DEV_PASSWORD");
+
+    match (user, password) {
+        (Ok(user), Ok(password)) => Some((user, password)),
+        _ => config
+            .dev_server_auth
+            .as_ref()
+            .map(|c| (c.user.clone(), c.password.clone())),
+    }
+}
+
+/// Generates a token that can't be guessed, from the randomness of the
+/// operating system
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the operating system provides random numbers");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Whether the request's `Authorization` header holds the expected value. It
+// is compared in constant time, so that the value can't be guessed from how
+// long it takes to be rejected
+fn has_authorization(headers: &HeaderMap, expected: &str) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .is_some_and(|value| value.as_bytes().ct_eq(expected.as_bytes()).into())
+}
+
+#[derive(Clone)]
+struct DevServerState {
+    blog_root: PathBuf,
+    output_directory: PathBuf,
+    /// Events sent to the connected pages after each rebuild
+    reloads: broadcast::Sender<DevServerEvent>,
+    /// Errors of the latest build, shown to the pages as soon as they connect
+    compile_errors: Arc<Mutex<Vec<CompileError>>>,
+    watcher: Sender<WatcherRequest>,
+    api_token: String,
+    /// Value of the `Authorization` header that the pages must be requested
+    /// with, if the server has credentials
+    basic_auth: Option<String>,
+    /// Tells the connected pages that the server is going away
+    shutdown: broadcast::Sender<CloseFrame<'static>>,
+}
+
+/// Message sent to the connected pages after a rebuild
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum DevServerEvent {
+    /// The blog was rebuilt, so the page should be reloaded
+    Reload {
+        /// Path that changed, with forward slashes whatever the platform is
+        path: String,
+    },
+    /// Posts failed to compile, which the page shows in an overlay
+    CompileErrors { errors: Vec<CompileError> },
+}
+
+/// Post that failed to compile, as shown in the pages' error overlay
+#[derive(Debug, Clone, Serialize)]
+struct CompileError {
+    file: String,
+    line: u32,
+    column: u32,
+    message: String,
+}
+
+fn compile_errors(failures: &[BuildPostError]) -> Vec<CompileError> {
+    failures
+        .iter()
+        .map(|failure| CompileError {
+            file: failure.filepath.display().to_string(),
+            line: failure.line,
+            column: failure.column,
+            message: failure.message.clone(),
+        })
+        .collect()
+}
+
+/// Work that the development server asks the watcher thread to do
+pub enum WatcherRequest {
+    Rebuild,
+    /// Renders a post that isn't saved, replying with its page
+    Preview {
+        content: String,
+        filepath: PathBuf,
+        reply: oneshot::Sender<Result<String, String>>,
+    },
+}
+
+impl DevServerState {
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        has_authorization(headers, &format!("Bearer {}", self.api_token))
+    }
+}
+
+async fn serve(host: String, state: DevServerState, config_changed: oneshot::Receiver<()>) {
+    let shutdown = state.shutdown.clone();
+
+    // The API is already protected by its token
+    let api = Router::new()
+        .route("/__api/rebuild", post(api_rebuild))
+        .route("/__api/new-post", post(api_new_post))
+        .route("/__api/preview", post(preview_post));
+
+    let app = Router::new()
+        .route("/ws", get(reload_websocket))
+        .route("/__preview/social/:slug", get(social_preview))
+        .route("/__dashboard", get(dashboard))
+        .fallback_service(ServeDir::new(&state.output_directory))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_credentials,
+        ))
+        .merge(api)
+        .with_state(state);
+
+    let listener = TcpListener::bind(&host).await.unwrap();
+
+    println!("pageturtle server listening on {}", &host);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(config_changed, shutdown))
+        .await
+        .unwrap();
+}
+
+// Asks for the credentials of the development server, when it has them
+async fn require_credentials(
+    State(state): State<DevServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = state
+        .basic_auth
+        .as_ref()
+        .is_none_or(|expected| has_authorization(request.headers(), expected));
+
+    match authorized {
+        true => next.run(request).await,
+        false => (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Basic realm=\"pageturtle\"")],
+        )
+            .into_response(),
+    }
+}
+
+// Resolves when the server is interrupted or has to restart, closing the
+// connections of the pages with the reason
+async fn shutdown_signal(
+    config_changed: oneshot::Receiver<()>,
+    shutdown: broadcast::Sender<CloseFrame<'static>>,
+) {
+    let frame = tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            println!("shutting down the development server");
+            CloseFrame {
+                code: close_code::AWAY,
+                reason: "server shutting down".into(),
+            }
+        }
+        // Also fails when the watcher stops without the configuration changing
+        Ok(()) = config_changed => {
+            println!("{} changed, restarting the development server", CONFIG_FILE);
+            CloseFrame {
+                code: close_code::RESTART,
+                reason: "server restarting".into(),
+            }
+        }
+    };
+
+    // Fails when there are no pages connected, which is fine
+    let _ = shutdown.send(frame);
+
+    // Each page stops listening once its connection is closed. They are
+    // waited for, as they would be dropped along with the runtime otherwise
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while shutdown.receiver_count() > 0 && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+// Notifies the page that it should reload whenever the blog is rebuilt, or
+// that posts failed to compile
+async fn reload_websocket(ws: WebSocketUpgrade, State(state): State<DevServerState>) -> Response {
+    let mut reloads = state.reloads.subscribe();
+    let mut shutdown = state.shutdown.subscribe();
+    let errors = state.compile_errors.lock().unwrap().clone();
+
+    ws.protocols(["handshake"])
+        .on_upgrade(|mut socket: WebSocket| async move {
+            if !errors.is_empty() {
+                let event = DevServerEvent::CompileErrors { errors };
+                let message = Message::Text(serde_json::to_string(&event).unwrap());
+                if socket.send(message).await.is_err() {
+                    return;
+                }
+            }
+
+            loop {
+                let reload = tokio::select! {
+                    reload = reloads.recv() => reload,
+                    frame = shutdown.recv() => {
+                        if let Ok(frame) = frame {
+                            let _ = socket.send(Message::Close(Some(frame))).await;
+                        }
+                        return;
+                    }
+                };
+
+                let event = match reload {
+                    Ok(event) => event,
+                    // Only the latest change matters for reloading the page
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                };
+
+                let message = Message::Text(serde_json::to_string(&event).unwrap());
+                if socket.send(message).await.is_err() {
+                    return; // probably the WS was closed
+                }
+            }
+        })
+}
+
+async fn dashboard(State(state): State<DevServerState>) -> Response {
+    let path = state.output_directory.join(DASHBOARD_FILE);
+
+    match tokio::fs::read_to_string(path).await {
+        Ok(page) => Html(page).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// Rebuilds the blog, e.g: after a change that the watcher can't see
+async fn api_rebuild(State(state): State<DevServerState>, headers: HeaderMap) -> Response {
+    if !state.is_authorized(&headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    match state.watcher.send(WatcherRequest::Rebuild) {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewPost {
+    title: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+// Creates a draft in the posts directory, responding with where it can be
+// previewed once the blog is rebuilt
+async fn api_new_post(
+    State(state): State<DevServerState>,
+    headers: HeaderMap,
+    Json(new_post): Json<NewPost>,
+) -> Response {
+    if !state.is_authorized(&headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let slug = slug::slugify(&new_post.title);
+    if slug.is_empty() {
+        return (StatusCode::BAD_REQUEST, "the post needs a title").into_response();
+    }
+
+    let path = state
+        .blog_root
+        .join("posts")
+        .join(&slug)
+        .with_extension("md");
+    if path.exists() {
+        return (
+            StatusCode::CONFLICT,
+            "a post with this title already exists",
+        )
+            .into_response();
+    }
+
+    let post = new_post_markdown(&new_post.title, &new_post.tags, true, &new_post.content);
+
+    if let Err(e) = tokio::fs::write(&path, post).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    let _ = state.watcher.send(WatcherRequest::Rebuild);
+
+    let body = json!({
+        "path": path.display().to_string(),
+        "preview_url": format!("/{}/{}.html", DRAFT_PREVIEW_DIR, slug),
+    });
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+#[derive(Deserialize)]
+struct PreviewQuery {
+    /// Path of the post being edited, relative to the blog's root
+    path: Option<PathBuf>,
+}
+
+// Responds with the page of the markdown in the request's body, so that
+// editors can preview unsaved posts with the blog's styling
+async fn preview_post(
+    State(state): State<DevServerState>,
+    Query(query): Query<PreviewQuery>,
+    headers: HeaderMap,
+    content: String,
+) -> Response {
+    if !state.is_authorized(&headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let filepath = match preview_path(&state.blog_root, query.path.as_deref()) {
+        Some(filepath) => filepath,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "the post must be in the posts directory",
+            )
+                .into_response()
+        }
+    };
+
+    let (reply, response) = oneshot::channel();
+    let request = WatcherRequest::Preview {
+        content,
+        filepath,
+        reply,
+    };
+    if state.watcher.send(request).is_err() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    match response.await {
+        Ok(Ok(page)) => Html(page).into_response(),
+        Ok(Err(message)) => (StatusCode::UNPROCESSABLE_ENTITY, message).into_response(),
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+// Path of a post being previewed, which must be in the posts directory, so
+// that its images and defaults can't be read from anywhere else. The post
+// may not be saved yet, so only its directory has to exist
+fn preview_path(blog_root: &Path, path: Option<&Path>) -> Option<PathBuf> {
+    let posts_dir = blog_root.join("posts");
+    let path = match path {
+        Some(path) => blog_root.join(path),
+        None => return Some(posts_dir.join("preview.md")),
+    };
+
+    let canonical_posts_dir = fs::canonicalize(&posts_dir).ok()?;
+    let canonical = match fs::canonicalize(&path) {
+        Ok(canonical) => canonical,
+        Err(_) => fs::canonicalize(path.parent()?)
+            .ok()?
+            .join(path.file_name()?),
+    };
+
+    // Given relative to the posts directory as the posts that are built, so
+    // that their defaults are found
+    let relative = canonical.strip_prefix(&canonical_posts_dir).ok()?;
+    Some(posts_dir.join(relative))
+}
+
+async fn social_preview(
+    UrlPath(slug): UrlPath<String>,
+    State(state): State<DevServerState>,
+) -> Response {
+    let path = state
+        .output_directory
+        .join(SOCIAL_PREVIEW_DIR)
+        .join(slug)
+        .with_extension("html");
+
+    match tokio::fs::read_to_string(path).await {
+        Ok(page) => Html(page).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
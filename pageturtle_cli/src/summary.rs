@@ -0,0 +1,97 @@
+use std::{collections::BTreeMap, path::Path};
+
+use pageturtle_core::blog::BudgetConfiguration;
+use walkdir::WalkDir;
+
+/// Kind of the files in the output, which the output size is broken down by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum OutputKind {
+    Html,
+    Css,
+    JavaScript,
+    Image,
+    Audio,
+    Other,
+}
+
+impl OutputKind {
+    fn of(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+
+        match extension.as_deref() {
+            Some("html") => OutputKind::Html,
+            Some("css") => OutputKind::Css,
+            Some("js") => OutputKind::JavaScript,
+            Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg") => OutputKind::Image,
+            Some("mp3" | "m4a" | "ogg" | "opus" | "wav") => OutputKind::Audio,
+            _ => OutputKind::Other,
+        }
+    }
+
+    /// Budget of each file of this kind, in kilobytes
+    fn budget(self, budgets: &BudgetConfiguration) -> Option<u64> {
+        match self {
+            OutputKind::Html => budgets.page,
+            OutputKind::Css => budgets.stylesheet,
+            OutputKind::Image => budgets.image,
+            _ => None,
+        }
+    }
+}
+
+/// Prints the size of the output broken down by the kind of its files,
+/// warning about the files that are over their budget
+pub fn print_build_summary(output_dir: &Path, budgets: &BudgetConfiguration) {
+    let mut totals: BTreeMap<OutputKind, (u64, usize)> = BTreeMap::new();
+    let mut over_budget = Vec::new();
+
+    for entry in WalkDir::new(output_dir).into_iter().filter_map(Result::ok) {
+        let size = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => continue,
+        };
+
+        let kind = OutputKind::of(entry.path());
+        let total = totals.entry(kind).or_default();
+        total.0 += size;
+        total.1 += 1;
+
+        if let Some(budget) = kind.budget(budgets) {
+            if size > budget * 1000 {
+                over_budget.push((entry.into_path(), size, budget));
+            }
+        }
+    }
+
+    let size: u64 = totals.values().map(|(size, _)| size).sum();
+    println!("Output size: {}", format_size(size));
+    for (kind, (size, files)) in &totals {
+        println!(
+            "  {:<12} {:>10} ({} files)",
+            format!("{:?}", kind),
+            format_size(*size),
+            files
+        );
+    }
+
+    for (path, size, budget) in over_budget {
+        let path = path.strip_prefix(output_dir).unwrap_or(&path);
+        println!(
+            "Warning: {} is {}, over its budget of {} KB",
+            path.display(),
+            format_size(size),
+            budget
+        );
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{} B", bytes),
+        1_000..=999_999 => format!("{:.1} KB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    process::{Child, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::unbounded;
+use qrcode::{render::unicode, QrCode};
+
+use crate::shell;
+
+/// How long a tunnel command may go without printing anything before its
+/// public URL is given up on
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts the command opening a tunnel to the development server, waiting for
+/// the public URL that it prints unless it is already known
+pub fn open_tunnel(
+    command: Option<&str>,
+    url: Option<String>,
+    port: u32,
+) -> Result<(Child, String), String> {
+    // cloudflared also prints links to its documentation
+    let (command, url_host) = match command {
+        Some(command) => (command.replace("{port}", &port.to_string()), None),
+        None => (
+            format!("cloudflared tunnel --url http://localhost:{}", port),
+            Some(".trycloudflare.com"),
+        ),
+    };
+
+    let mut process = shell(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", command, e))?;
+
+    // Both outputs are read until the tunnel closes, so that it never blocks
+    // on writing to them
+    let (lines_tx, lines) = unbounded();
+    let stdout = process
+        .stdout
+        .take()
+        .map(|o| Box::new(o) as Box<dyn Read + Send>);
+    let stderr = process
+        .stderr
+        .take()
+        .map(|o| Box::new(o) as Box<dyn Read + Send>);
+    for output in stdout.into_iter().chain(stderr) {
+        let lines_tx = lines_tx.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines().map_while(Result::ok) {
+                let _ = lines_tx.send(line);
+            }
+        });
+    }
+    drop(lines_tx);
+
+    let url = match url {
+        Some(url) => url,
+        None => loop {
+            let line = match lines.recv_timeout(TUNNEL_TIMEOUT) {
+                Ok(line) => line,
+                Err(_) => {
+                    let _ = process.kill();
+                    return Err(format!("{} didn't print the URL of the tunnel", command));
+                }
+            };
+
+            let found = find_url(&line).filter(|u| url_host.is_none_or(|h| u.ends_with(h)));
+            if let Some(url) = found {
+                break url;
+            }
+        },
+    };
+
+    let url = url.trim_end_matches('/').to_owned();
+    println!("[tunnel] Sharing the development server at {}", url);
+    Ok((process, url))
+}
+
+// First HTTPS URL of a line of text
+fn find_url(line: &str) -> Option<String> {
+    let start = line.find("https://")?;
+    let url: String = line[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '|' | '"' | '\'' | '<' | '>'))
+        .collect();
+    Some(url.trim_end_matches(['/', '.', ',']).to_owned())
+}
+
+/// Prints a QR code of the URL, so that it can be opened from a phone
+pub fn print_qr_code(url: &str) {
+    let code = QrCode::new(url).unwrap();
+    // Inverted so that it can be scanned from terminals with dark backgrounds
+    let rendered = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+
+    println!("{}\nScan to open {} in your phone", rendered, url);
+}
@@ -0,0 +1,205 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{select, unbounded, Receiver, RecvTimeoutError};
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use pageturtle_core::{blog::BlogConfiguration, defaults::DEFAULTS_FILE};
+
+use crate::{
+    build::{build, is_hidden, render_preview, walk_content, BuildContext},
+    check_allowed_filetype,
+    server::WatcherRequest,
+    shell, BuildPostError, CONFIG_FILE, TEMPLATES_DIR, THEMES_DIR,
+};
+
+/// How long the watcher waits for more changes before rebuilding the blog
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Why the watcher stopped
+pub enum WatchEnd {
+    /// Nobody can send requests to the watcher anymore
+    Stopped,
+    /// The configuration file changed, so the blog has to be set up again
+    ConfigChanged,
+}
+
+/// Rebuilds the blog whenever one of its posts changes or a rebuild is
+/// requested, calling `on_rebuild` with the changed paths afterwards. Also
+/// renders the requested previews. Blocks the current thread until it stops.
+pub fn watch(
+    blog_root: &Path,
+    output_directory: &Path,
+    config: &BlogConfiguration,
+    context: &BuildContext,
+    requests: Receiver<WatcherRequest>,
+    mut on_rebuild: impl FnMut(&[PathBuf], &[BuildPostError]),
+) -> WatchEnd {
+    // Create a channel to receive the events.
+    let (event_tx, event_rx) = unbounded();
+    let mut watcher = RecommendedWatcher::new(event_tx, Config::default()).unwrap();
+
+    // Create a watcher object, delivering debounced events.
+    // The notification back-end is selected based on the platform.
+    let watched_root = fs::canonicalize(blog_root).unwrap();
+    watcher
+        .watch(&watched_root, RecursiveMode::Recursive)
+        .unwrap();
+
+    // The targets of symlinks aren't watched along with the root
+    if config.content.follow_symlinks {
+        let symlinks = walk_content(&watched_root, &config.content).filter(|e| e.path_is_symlink());
+        for entry in symlinks {
+            match fs::canonicalize(entry.path()) {
+                Ok(target) if !target.starts_with(&watched_root) => {
+                    if let Err(e) = watcher.watch(&target, RecursiveMode::Recursive) {
+                        println!("Failed to watch {}: {}", target.display(), e);
+                    }
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    // Files written by the build itself must not trigger another build
+    let output = fs::canonicalize(output_directory).unwrap();
+
+    let config_file = watched_root.join(CONFIG_FILE);
+
+    loop {
+        let mut paths = select! {
+            recv(event_rx) -> res => match res {
+                Ok(Ok(event)) if changes_file(&event, &config_file) => {
+                    return WatchEnd::ConfigChanged;
+                }
+                Ok(Ok(event)) if is_relevant_change(&event, &output, &watched_root, config) => {
+                    event.paths
+                }
+                Ok(Ok(_)) => continue,
+                Ok(Err(e)) => {
+                    println!("watch error: {:?}", e);
+                    continue;
+                }
+                Err(_) => return WatchEnd::Stopped,
+            },
+            recv(requests) -> request => match request {
+                Ok(WatcherRequest::Rebuild) => vec![blog_root.to_owned()],
+                Ok(WatcherRequest::Preview { content, filepath, reply }) => {
+                    // The server stops waiting when the client disconnects
+                    let _ = reply.send(render_preview(&content, &filepath, &blog_root.join("posts"), config, context));
+                    continue;
+                }
+                Err(_) => return WatchEnd::Stopped,
+            },
+        };
+
+        // Saving a file usually fires several events, e.g: when editors write
+        // it to a temporary file and then rename it, so the events that come
+        // shortly after each other are handled by a single build
+        loop {
+            match event_rx.recv_timeout(WATCH_DEBOUNCE) {
+                Ok(Ok(event)) if changes_file(&event, &config_file) => {
+                    return WatchEnd::ConfigChanged;
+                }
+                Ok(Ok(event)) if is_relevant_change(&event, &output, &watched_root, config) => {
+                    for path in event.paths {
+                        if !paths.contains(&path) {
+                            paths.push(path);
+                        }
+                    }
+                }
+                Ok(Ok(_)) => continue,
+                Ok(Err(e)) => println!("watch error: {:?}", e),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return WatchEnd::Stopped,
+            }
+        }
+
+        let start = Instant::now();
+        let report = build(blog_root, output_directory, config, context);
+        let duration = start.elapsed();
+        println!("[rebuilt] {:?}", duration);
+        on_rebuild(&paths, &report.failures);
+    }
+}
+
+// Whether a file system event changes the contents of the file, which
+// editors may do by replacing it
+fn changes_file(event: &Event, file: &Path) -> bool {
+    !matches!(event.kind, notify::EventKind::Access(_)) && event.paths.iter().any(|p| p == file)
+}
+
+// Whether a file system event should trigger a rebuild
+fn is_relevant_change(
+    event: &Event,
+    output: &Path,
+    root: &Path,
+    config: &BlogConfiguration,
+) -> bool {
+    match event.kind {
+        notify::EventKind::Modify(_) | notify::EventKind::Remove(_) => {
+            let path = event.paths.first().unwrap();
+            if path.starts_with(output) {
+                return false;
+            }
+
+            // e.g: the settings that editors keep next to the posts
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let hidden = relative.components().any(|c| match c {
+                Component::Normal(name) => is_hidden(name),
+                _ => false,
+            });
+            if hidden && !config.content.include_hidden {
+                return false;
+            }
+
+            // Static files are copied whatever their type is, the templates
+            // of the blog, of its sections and of themes are HTML and the
+            // frontmatter defaults are YAML
+            let mut section_templates = config
+                .sections
+                .values()
+                .filter_map(|section| section.templates.as_ref());
+            if path.starts_with(root.join(&config.static_directory))
+                || path.starts_with(root.join(TEMPLATES_DIR))
+                || path.starts_with(root.join(THEMES_DIR))
+                || section_templates.any(|dir| path.starts_with(root.join(dir)))
+                || path.file_name().is_some_and(|name| name == DEFAULTS_FILE)
+            {
+                return true;
+            }
+
+            match path.extension() {
+                Some(ext) => check_watched_filetype(ext.to_str().unwrap()),
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Runs a user command through the shell, passing it the changed paths
+pub fn run_command(command: &str, paths: &[PathBuf]) {
+    let mut process = shell(command);
+    // The first argument after the script becomes `$0` in `sh`
+    if !cfg!(windows) {
+        process.arg("sh");
+    }
+    process.args(paths);
+
+    let changed: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    process.env("PAGETURTLE_CHANGED_PATHS", changed.join("\n"));
+
+    match process.status() {
+        Ok(status) if status.success() => println!("[exec] {}", command),
+        Ok(status) => println!("[exec] {} failed with {}", command, status),
+        Err(e) => println!("[exec] failed to run {}: {}", command, e),
+    }
+}
+
+// Files that trigger a rebuild when changed, e.g: posts and data files
+fn check_watched_filetype(extension: &str) -> bool {
+    check_allowed_filetype(extension) || ["yaml", "json", "css", "js"].contains(&extension)
+}
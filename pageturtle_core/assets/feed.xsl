@@ -0,0 +1,71 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<!--
+  Renders the Atom feed as a human readable page when it is opened in a
+  browser. Feed readers ignore this stylesheet.
+-->
+<xsl:stylesheet version="1.0"
+  xmlns:xsl="http://www.w3.org/1999/XSL/Transform"
+  xmlns:atom="http://www.w3.org/2005/Atom">
+  <xsl:output method="html" encoding="UTF-8" indent="yes" />
+
+  <xsl:template match="/atom:feed">
+    <html>
+      <head>
+        <meta charset="UTF-8" />
+        <meta name="viewport" content="width=device-width, initial-scale=1" />
+        <title><xsl:value-of select="atom:title" /> - RSS feed</title>
+        <style>
+          body {
+            margin: 0 auto;
+            max-width: 40rem;
+            padding: 2rem 1rem;
+            font-family: ui-sans-serif, system-ui, sans-serif;
+            line-height: 1.5;
+            color: #111827;
+          }
+          .notice {
+            padding: 0.5rem 1rem;
+            border-radius: 0.375rem;
+            background: #e5e7eb;
+          }
+          code {
+            padding: 0 0.25rem;
+            border-radius: 0.25rem;
+            background: #d1d5db;
+          }
+          a { color: inherit; }
+          li { margin-bottom: 1rem; }
+          .date { font-size: 0.875rem; color: #4b5563; }
+        </style>
+      </head>
+      <body>
+        <div class="notice">
+          <p>
+            This is a web feed. Copy its URL into your feed reader to get
+            notified whenever a new post is published:
+            <code><xsl:value-of select="atom:id" />/atom.xml</code>
+          </p>
+          <p>
+            New to feeds? <a href="https://aboutfeeds.com">About Feeds</a>
+            explains what they are and how to start using them.
+          </p>
+        </div>
+
+        <h1>
+          <a href="{atom:id}/index.html"><xsl:value-of select="atom:title" /></a>
+        </h1>
+
+        <h2>Recent posts</h2>
+
+        <ul>
+          <xsl:for-each select="atom:entry">
+            <li>
+              <a href="{atom:link[@rel='alternate']/@href}"><xsl:value-of select="atom:title" /></a>
+              <div class="date"><xsl:value-of select="substring(atom:updated, 1, 10)" /></div>
+            </li>
+          </xsl:for-each>
+        </ul>
+      </body>
+    </html>
+  </xsl:template>
+</xsl:stylesheet>
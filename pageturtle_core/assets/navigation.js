@@ -0,0 +1,44 @@
+// Enhancements of the post pages, enabled in the `[navigation]` section of
+// the configuration file. The pages work the same without them.
+(function () {
+  var script = document.currentScript;
+
+  if (script.hasAttribute("data-keyboard-shortcuts")) {
+    // `j` and `k` go to the next and previous posts, as linked in the head
+    document.addEventListener("keydown", function (event) {
+      if (event.ctrlKey || event.metaKey || event.altKey || event.defaultPrevented) {
+        return;
+      }
+
+      var target = event.target;
+      if (target.isContentEditable || /^(INPUT|TEXTAREA|SELECT)$/.test(target.tagName)) {
+        return;
+      }
+
+      var rel = { j: "next", k: "prev" }[event.key];
+      var link = rel && document.querySelector('link[rel="' + rel + '"]');
+      if (link) {
+        window.location.href = link.href;
+      }
+    });
+  }
+
+  if (script.hasAttribute("data-reading-progress")) {
+    var article = document.querySelector("article");
+    var bar = document.createElement("div");
+    bar.className = "reading-progress";
+    bar.setAttribute("aria-hidden", "true");
+    document.body.append(bar);
+
+    var update = function () {
+      var rect = article.getBoundingClientRect();
+      var total = rect.height - window.innerHeight;
+      var progress = total > 0 ? Math.min(Math.max(-rect.top / total, 0), 1) : 1;
+      bar.style.width = progress * 100 + "%";
+    };
+
+    window.addEventListener("scroll", update, { passive: true });
+    window.addEventListener("resize", update);
+    update();
+  }
+})();
@@ -0,0 +1,121 @@
+// Searches the posts listed in `search-index.json` as the reader types, so
+// that the blog can be searched without a server. Posts must match every
+// word of the query, and are ranked by where the words are found.
+(function () {
+  var script = document.currentScript;
+  var form = document.getElementById("search-form");
+  var input = document.getElementById("search-input");
+  var status = document.getElementById("search-status");
+  var results = document.getElementById("search-results");
+
+  // Matches in the title count the most, and the ones in the text the least
+  var weights = { title: 10, tags: 5, description: 3, text: 1 };
+  var maxResults = 20;
+  var documents = null;
+
+  var normalize = function (text) {
+    return text.toLowerCase().normalize("NFD").replace(/[\u0300-\u036f]/g, "");
+  };
+
+  var score = function (doc, words) {
+    var total = 0;
+    for (var i = 0; i < words.length; i++) {
+      var found = 0;
+      for (var field in weights) {
+        if (doc.fields[field].indexOf(words[i]) !== -1) {
+          found += weights[field];
+        }
+      }
+      if (found === 0) {
+        return 0;
+      }
+      total += found;
+    }
+    return total;
+  };
+
+  var render = function (query) {
+    results.replaceChildren();
+
+    var words = normalize(query).split(/\s+/).filter(Boolean);
+    if (words.length === 0) {
+      status.textContent = "";
+      return;
+    }
+
+    var matches = documents
+      .map(function (doc) { return { doc: doc, score: score(doc, words) }; })
+      .filter(function (match) { return match.score > 0; })
+      .sort(function (a, b) { return b.score - a.score; })
+      .slice(0, maxResults);
+
+    status.textContent = matches.length === 0
+      ? "No posts found"
+      : matches.length + (matches.length === 1 ? " post found" : " posts found");
+
+    matches.forEach(function (match) {
+      var item = document.createElement("li");
+      item.className = "p-2";
+
+      var link = document.createElement("a");
+      link.className = "text-xl font-bold hover:underline";
+      link.href = match.doc.url;
+      link.textContent = match.doc.title;
+
+      var description = document.createElement("p");
+      description.className = "text-sm";
+      description.textContent = match.doc.description;
+
+      item.append(link, description);
+      results.append(item);
+    });
+  };
+
+  var search = function () {
+    var query = input.value;
+    var url = new URL(window.location.href);
+    if (query) {
+      url.searchParams.set("q", query);
+    } else {
+      url.searchParams.delete("q");
+    }
+    history.replaceState(null, "", url);
+
+    if (documents) {
+      render(query);
+    }
+  };
+
+  form.addEventListener("submit", function (event) {
+    event.preventDefault();
+    search();
+  });
+  input.addEventListener("input", search);
+
+  // Searches shared as links are run once the index is loaded
+  input.value = new URL(window.location.href).searchParams.get("q") || "";
+
+  status.textContent = "Loading...";
+  fetch(script.getAttribute("data-index"))
+    .then(function (response) { return response.json(); })
+    .then(function (index) {
+      documents = index.map(function (doc) {
+        return {
+          title: doc.title,
+          description: doc.description,
+          url: doc.url,
+          fields: {
+            title: normalize(doc.title),
+            tags: normalize(doc.tags.join(" ")),
+            description: normalize(doc.description),
+            text: normalize(doc.text),
+          },
+        };
+      });
+      status.textContent = "";
+      render(input.value);
+    })
+    .catch(function () {
+      status.textContent = "The search index could not be loaded";
+    });
+})();
@@ -0,0 +1,120 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{blog::BlogConfiguration, feed::Feed};
+
+const PUBLIC_AUDIENCE: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// Settings of the `[activitypub]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct ActivityPubConfiguration {
+    /// Name of the blog's account, e.g: `blog` for `@blog@example.com`
+    pub username: String,
+    pub summary: Option<String>,
+    /// PEM encoded public key of the account, required by servers that
+    /// verify signatures, such as Mastodon
+    pub public_key_pem: Option<String>,
+    /// Inbox receiving the activities sent to the blog. Static hosts can't
+    /// handle them, so this usually points to an external service
+    pub inbox: Option<String>,
+}
+
+/// Static documents that make the blog discoverable from the Fediverse
+pub struct ActivityPubDocuments {
+    /// Served at `/.well-known/webfinger`
+    pub webfinger: String,
+    /// Served at `/activitypub/actor.json`
+    pub actor: String,
+    /// Served at `/activitypub/outbox.json`
+    pub outbox: String,
+}
+
+pub fn build_documents(
+    feed: &Feed,
+    settings: &ActivityPubConfiguration,
+    config: &BlogConfiguration,
+) -> ActivityPubDocuments {
+    let actor_url = format!("{}/activitypub/actor.json", config.base_url);
+    let outbox_url = format!("{}/activitypub/outbox.json", config.base_url);
+    let followers_url = format!("{}/activitypub/followers.json", config.base_url);
+    let inbox_url = settings
+        .inbox
+        .clone()
+        .unwrap_or_else(|| format!("{}/activitypub/inbox", config.base_url));
+
+    let webfinger = json!({
+        "subject": format!("acct:{}@{}", settings.username, domain(&config.base_url)),
+        "links": [{
+            "rel": "self",
+            "type": "application/activity+json",
+            "href": actor_url,
+        }],
+    });
+
+    let mut actor = json!({
+        "@context": ["https://www.w3.org/ns/activitystreams", "https://w3id.org/security/v1"],
+        "id": actor_url,
+        "type": "Service",
+        "preferredUsername": settings.username,
+        "name": config.blog_title,
+        "summary": settings.summary.as_deref().unwrap_or_default(),
+        "url": config.base_url,
+        "inbox": inbox_url,
+        "outbox": outbox_url,
+        "followers": followers_url,
+    });
+
+    if let Some(ref key) = settings.public_key_pem {
+        actor["publicKey"] = json!({
+            "id": format!("{}#main-key", actor_url),
+            "owner": actor_url,
+            "publicKeyPem": key,
+        });
+    }
+
+    let activities: Vec<Value> = feed
+        .entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "id": format!("{}#create", entry.link),
+                "type": "Create",
+                "actor": actor_url,
+                "published": entry.updated,
+                "to": [PUBLIC_AUDIENCE],
+                "cc": [followers_url],
+                "object": {
+                    "id": entry.link,
+                    "type": "Article",
+                    "name": entry.title,
+                    "content": entry.content,
+                    "url": entry.link,
+                    "attributedTo": actor_url,
+                    "published": entry.updated,
+                    "to": [PUBLIC_AUDIENCE],
+                    "cc": [followers_url],
+                },
+            })
+        })
+        .collect();
+
+    let outbox = json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": outbox_url,
+        "type": "OrderedCollection",
+        "totalItems": activities.len(),
+        "orderedItems": activities,
+    });
+
+    ActivityPubDocuments {
+        webfinger: webfinger.to_string(),
+        actor: actor.to_string(),
+        outbox: outbox.to_string(),
+    }
+}
+
+/// Host of a URL, e.g: `example.com` for `https://example.com/blog`
+fn domain(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    without_scheme.split('/').next().unwrap_or(without_scheme)
+}
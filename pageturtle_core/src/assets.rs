@@ -0,0 +1,47 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+};
+
+/// Maps the assets of the blog, e.g: `styles.css`, to the fingerprinted paths
+/// they are published at, e.g: `styles.0123456789abcdef.css`, so that they
+/// can be cached forever
+#[derive(Debug, Default)]
+pub struct AssetManifest {
+    paths: BTreeMap<String, String>,
+}
+
+impl AssetManifest {
+    /// Adds an asset to the manifest, returning the path it should be
+    /// published at
+    pub fn insert(&mut self, name: &str, contents: &[u8]) -> String {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let hash = format!("{:016x}", hasher.finish());
+
+        let path = match name.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}.{}.{}", stem, hash, extension),
+            None => format!("{}.{}", name, hash),
+        };
+
+        self.paths.insert(name.to_owned(), path.clone());
+        path
+    }
+
+    /// Path that an asset is published at. Assets that aren't in the
+    /// manifest are published as they are named
+    pub fn path<'a>(&'a self, name: &'a str) -> &'a str {
+        self.paths.get(name).map_or(name, String::as_str)
+    }
+
+    /// Fingerprinted paths of the assets
+    pub fn published_paths(&self) -> Vec<&str> {
+        self.paths.values().map(String::as_str).collect()
+    }
+
+    /// The manifest as JSON, published so that tools outside of pageturtle
+    /// can find the assets
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.paths).unwrap()
+    }
+}
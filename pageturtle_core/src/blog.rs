@@ -1,18 +1,44 @@
 use core::panic;
 use std::{
     borrow::Borrow,
-    collections::VecDeque,
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    io::Cursor,
     path::{Path, PathBuf},
 };
 
-use crate::utils::{date, default_empty, default_true};
+use crate::{
+    activitypub::ActivityPubConfiguration,
+    assets::AssetManifest,
+    defaults::FrontmatterDefaults,
+    external::ExternalPost,
+    footnotes::{self, FootnoteConfiguration},
+    hashing,
+    highlighting::{CodeBlockRenderer, SyntaxHighlighter, SyntaxThemeConfiguration},
+    hosting::HostingConfiguration,
+    notify::NotifyConfiguration,
+    related::{RelatedPost, RelatedPostsConfiguration},
+    remote_images,
+    responsive_images::{self, ImageVariant, ResponsiveImagesConfiguration},
+    samples::SamplesConfiguration,
+    sanitizer::{self, SanitizerConfiguration},
+    search,
+    taxonomy::TagsConfiguration,
+    thumbnails::{PostThumbnail, ThumbnailConfiguration},
+    typography::{self, TypographyConfiguration},
+    urls,
+    user_templates::UserTemplates,
+    utils::{date, default_empty, default_true, escape_html, time, utc_offset},
+};
 use askama::filters::wordcount;
-use chrono::{Datelike, NaiveDate};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime, Utc};
 use comrak::{
     adapters::{HeadingAdapter, HeadingMeta},
     nodes::{AstNode, NodeValue},
-    Arena, ComrakOptions, ComrakPlugins,
+    Arena, ComrakExtensionOptions, ComrakOptions, ComrakPlugins, ComrakRenderOptions,
 };
+use image::ImageFormat;
 use serde::Deserialize;
 use slug::slugify;
 
@@ -33,7 +59,7 @@ impl<'a> TableOfContents {
     pub fn from_ast(ast: &'a AstNode<'a>) -> TableOfContents {
         let mut entries = VecDeque::new();
 
-        for node in ast.borrow().traverse() {
+        for node in ast.traverse() {
             match node {
                 comrak::arena_tree::NodeEdge::Start(nv) => match nv.data.borrow().value {
                     NodeValue::Heading(h) => {
@@ -75,10 +101,7 @@ impl<'a> TableOfContents {
             return None;
         }
 
-        let mut root = match entries.pop_front() {
-            Some(e) => e,
-            None => return None,
-        };
+        let mut root = entries.pop_front()?;
 
         while !entries.is_empty() {
             let mut node = entries.pop_front().unwrap();
@@ -149,6 +172,55 @@ impl HeadingAdapter for HeadingRenderer {
     }
 }
 
+/// Options of the markdown parser and renderer that the posts are compiled
+/// with
+pub fn markdown_options() -> ComrakOptions {
+    ComrakOptions {
+        extension: ComrakExtensionOptions {
+            front_matter_delimiter: Some("---".to_owned()),
+            table: true,
+            footnotes: true,
+            ..ComrakExtensionOptions::default()
+        },
+        render: ComrakRenderOptions {
+            // Makes the code block metadata (e.g: title, hl_lines) reach the
+            // codefence renderer
+            github_pre_lang: true,
+            full_info_string: true,
+            // Needed for the HTML generated when rewriting nodes, e.g:
+            // images. The HTML written in the posts is omitted or sanitized
+            // beforehand unless `raw_html` is set
+            unsafe_: true,
+            ..ComrakRenderOptions::default()
+        },
+        ..ComrakOptions::default()
+    }
+}
+
+/// Renderers of the posts' headings and code blocks, which the markdown
+/// renderer uses as plugins. Setting them up is expensive, so they are kept
+/// between builds
+pub struct PostRenderers {
+    heading_renderer: HeadingRenderer,
+    code_renderer: CodeBlockRenderer,
+}
+
+impl PostRenderers {
+    pub fn new(config: &BlogConfiguration) -> Self {
+        Self {
+            heading_renderer: HeadingRenderer::default(),
+            code_renderer: CodeBlockRenderer::new(config.syntax_highlighter, &config.syntax_theme),
+        }
+    }
+
+    pub fn plugins(&self) -> ComrakPlugins<'_> {
+        let mut plugins = ComrakPlugins::default();
+        plugins.render.heading_adapter = Some(&self.heading_renderer);
+        plugins.render.codefence_syntax_highlighter = Some(&self.code_renderer);
+        plugins
+    }
+}
+
 pub struct PostCompiler<'a> {
     arena: Arena<AstNode<'a>>,
     options: &'a ComrakOptions,
@@ -189,26 +261,460 @@ pub struct Link {
     pub href: String,
 }
 
+/// How the feed is published at its alias paths
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeedAliasMode {
+    /// Writes a copy of the feed to each alias
+    #[default]
+    Copy,
+    /// Redirects from the aliases to the feed, in a `_redirects` file as
+    /// supported by hosts such as Netlify and Cloudflare Pages, and in the
+    /// configuration of the other hosts in `[hosting]`
+    Redirect,
+}
+
+/// Order of the posts in the index
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PostOrder {
+    /// Newest first
+    #[default]
+    Date,
+    /// Alphabetically by their title
+    Title,
+    /// By their `weight`, lightest first. Posts without a weight come after
+    /// the others
+    Weight,
+}
+
+/// How the posts are presented in the index
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IndexLayout {
+    /// One post per row, with a thumbnail of their cover on the side
+    #[default]
+    List,
+    /// A grid of cards, with their cover on the top
+    Cards,
+    /// Only the date and title of the posts, one per line
+    Compact,
+}
+
+/// Where the table of contents of a post is shown
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TocPlacement {
+    /// At the top of the post
+    #[default]
+    Inline,
+    /// In a sidebar that follows the reader on wide screens, falling back to
+    /// the top of the post on smaller ones
+    Sidebar,
+}
+
+/// Which resource hints are added to the pages, so that browsers can fetch
+/// resources before they are needed
+#[derive(Deserialize)]
+pub struct ResourceHints {
+    /// Preloads the stylesheets
+    #[serde(default = "default_true")]
+    pub preload_styles: bool,
+
+    /// Prefetches the previous and next posts from post pages
+    #[serde(default = "default_true")]
+    pub prefetch_adjacent_posts: bool,
+}
+
+impl Default for ResourceHints {
+    fn default() -> Self {
+        ResourceHints {
+            preload_styles: true,
+            prefetch_adjacent_posts: true,
+        }
+    }
+}
+
+/// Settings of the `[navigation]` section of the configuration file, which
+/// enable the script that enhances the post pages
+#[derive(Debug, Default, Deserialize)]
+pub struct NavigationConfiguration {
+    /// Goes to the next and previous posts with `j` and `k`
+    #[serde(default)]
+    pub keyboard_shortcuts: bool,
+
+    /// Shows how much of the post was read in a bar at the top of the page
+    #[serde(default)]
+    pub reading_progress: bool,
+}
+
+impl NavigationConfiguration {
+    /// Whether the post pages include the navigation script
+    pub fn enabled(&self) -> bool {
+        self.keyboard_shortcuts || self.reading_progress
+    }
+}
+
+/// Settings of the `[budgets]` section of the configuration file, in
+/// kilobytes. Files bigger than the budget of their type are reported after
+/// building the blog
+#[derive(Debug, Default, Deserialize)]
+pub struct BudgetConfiguration {
+    pub page: Option<u64>,
+    pub stylesheet: Option<u64>,
+    pub image: Option<u64>,
+}
+
+/// Settings of the `[content]` section of the configuration file, which
+/// control how the posts are found
+#[derive(Debug, Default, Deserialize)]
+pub struct ContentConfiguration {
+    /// Follows the symlinks found in the content directories, e.g: into a
+    /// notes vault. Symlinks that loop back to their own directory are skipped
+    #[serde(default)]
+    pub follow_symlinks: bool,
+
+    /// Reads the files and directories whose names start with a dot, which
+    /// are skipped otherwise
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+/// Settings of a section of the blog, given in a `[sections.<name>]` table
+/// of the configuration file, where the name is the section's directory in
+/// `posts`, e.g: `[sections.notes]` for the posts of `posts/notes`
+#[derive(Debug, Default, Deserialize)]
+pub struct SectionConfiguration {
+    /// Title of the section, used in its feed. Defaults to its name
+    pub title: Option<String>,
+
+    /// Directory of the output that the section's posts are published in,
+    /// e.g: `notes` publishes `posts/notes/my-note.md` at
+    /// `/notes/my-note.html`. Posts are published at the root by default
+    pub output_directory: Option<String>,
+
+    /// Path of a feed with only the section's posts, relative to the
+    /// output, e.g: `notes/atom.xml`. They are still listed in the blog's
+    /// feed
+    pub feed: Option<String>,
+
+    /// Directory of the blog with the templates that replace the ones of the
+    /// blog for the section's posts, e.g: `templates/notes`. They may extend
+    /// the templates of the blog
+    pub templates: Option<String>,
+
+    /// Templates of the `templates` directory, loaded when building the blog
+    #[serde(skip)]
+    pub loaded_templates: UserTemplates,
+}
+
+/// How the tables in the posts are styled. Tables are always wrapped in a
+/// container that scrolls horizontally, so that they don't overflow the
+/// page on small screens
+#[derive(Default, Deserialize)]
+pub struct TableStyle {
+    /// Alternates the background of the table rows
+    #[serde(default)]
+    pub striped: bool,
+
+    /// Keeps the table header visible while scrolling through long tables
+    #[serde(default)]
+    pub sticky_header: bool,
+}
+
+impl TableStyle {
+    fn wrapper_classes(&self) -> String {
+        let mut classes = vec!["table-wrapper"];
+        if self.striped {
+            classes.push("table-striped");
+        }
+        if self.sticky_header {
+            classes.push("table-sticky-header");
+        }
+        classes.join(" ")
+    }
+}
+
+/// Profile of one of the blog's authors, declared in the `[authors.<id>]`
+/// table of the configuration file
+#[derive(Deserialize)]
+pub struct AuthorProfile {
+    pub name: String,
+    pub bio: Option<String>,
+    /// URL of the author's picture
+    pub avatar: Option<String>,
+
+    #[serde(default = "default_empty")]
+    pub links: Vec<Link>,
+}
+
+/// User and password that a server asks for
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
 #[derive(Deserialize)]
 pub struct BlogConfiguration {
     pub blog_title: String,
     pub author: String,
 
+    /// Description of the blog, shown in the previews of the links to its
+    /// pages besides posts
+    pub blog_description: Option<String>,
+
     #[serde(default)]
     pub base_url: String,
 
+    /// Whether the pages link to the blog's own pages and files with URLs
+    /// relative to them, e.g: `../img/a.webp`, so that the blog can be
+    /// browsed wherever it is published. Canonical URLs and feeds keep
+    /// absolute URLs
+    #[serde(default)]
+    pub relative_urls: bool,
+
     #[serde(default = "default_true")]
     pub enable_rss: bool,
 
+    /// Whether the posts are also published in a JSON Feed, `feed.json`
+    #[serde(default)]
+    pub enable_json_feed: bool,
+
+    /// Whether the blog has a search page, `search.html`, which searches the
+    /// posts in the browser using `search-index.json`
+    #[serde(default)]
+    pub enable_search: bool,
+
+    /// Other paths where the feed is published, e.g: `feed.xml`, so that
+    /// subscribers of blogs migrated from other generators aren't lost
+    #[serde(default = "default_empty")]
+    pub feed_aliases: Vec<String>,
+
+    #[serde(default)]
+    pub feed_alias_mode: FeedAliasMode,
+
+    /// Amount of entries in each page of the feed. The older entries are
+    /// published in pages linked from it, e.g: `atom-2.xml`, instead of
+    /// making the feed grow with every post. Not set by default
+    #[serde(default)]
+    pub feed_page_size: Option<usize>,
+
     #[serde(default = "default_empty")]
     pub extra_links_start: Vec<Link>,
 
     #[serde(default = "default_empty")]
     pub extra_links_end: Vec<Link>,
 
+    /// Profiles that identify the blog's author, linked with `rel="me"` so
+    /// that they can be verified, e.g: by Mastodon or IndieAuth
+    #[serde(default = "default_empty")]
+    pub identity_links: Vec<Link>,
+
+    /// Authors that can be referenced by their id in the posts' frontmatter
+    #[serde(default)]
+    pub authors: HashMap<String, AuthorProfile>,
+
+    #[serde(default)]
+    pub tags: TagsConfiguration,
+
+    #[serde(default)]
+    pub sections: HashMap<String, SectionConfiguration>,
+
+    #[serde(default)]
+    pub related_posts: RelatedPostsConfiguration,
+
+    #[serde(default)]
+    pub content: ContentConfiguration,
+
+    #[serde(default)]
+    pub budgets: BudgetConfiguration,
+
+    #[serde(default)]
+    pub syntax_highlighter: SyntaxHighlighter,
+
+    #[serde(default)]
+    pub syntax_theme: SyntaxThemeConfiguration,
+
+    #[serde(default = "default_true")]
+    pub image_placeholders: bool,
+
+    /// Publishes the raster images of the posts, e.g: PNG or JPEG, as WebP.
+    /// Animated and vector images are published as they are
+    #[serde(default = "default_true")]
+    pub webp_images: bool,
+
+    #[serde(default)]
+    pub thumbnails: ThumbnailConfiguration,
+
+    #[serde(default)]
+    pub responsive_images: ResponsiveImagesConfiguration,
+
+    #[serde(default)]
+    pub resource_hints: ResourceHints,
+
+    #[serde(default)]
+    pub navigation: NavigationConfiguration,
+
+    #[serde(default)]
+    pub tables: TableStyle,
+
+    #[serde(default)]
+    pub toc_placement: TocPlacement,
+
+    #[serde(default)]
+    pub index_layout: IndexLayout,
+
+    #[serde(default)]
+    pub sort_by: PostOrder,
+
+    /// Articles published somewhere else, which are listed in the index and
+    /// the feed along with the posts
+    #[serde(default = "default_empty")]
+    pub external_posts: Vec<ExternalPost>,
+
+    #[serde(default)]
+    pub typography: TypographyConfiguration,
+
+    #[serde(default)]
+    pub footnotes: FootnoteConfiguration,
+
+    #[serde(default)]
+    pub sanitizer: SanitizerConfiguration,
+
+    /// Whether the HTML written in the posts, including the one of expanded
+    /// shortcodes such as `youtube`, is published as it is. Otherwise, it is
+    /// omitted unless the sanitizer is enabled, which only keeps the allowed
+    /// elements
+    #[serde(default)]
+    pub raw_html: bool,
+
+    #[serde(default)]
+    pub samples: SamplesConfiguration,
+
+    /// Stylesheets smaller than this amount of bytes are inlined in the
+    /// pages instead of being linked
+    pub inline_styles_threshold: Option<usize>,
+
+    /// Stylesheets inlined in the pages, set when building the blog
+    #[serde(skip)]
+    pub inline_styles: Option<String>,
+
+    /// Fingerprinted paths of the blog's assets, set when building the blog
+    #[serde(skip)]
+    pub assets: AssetManifest,
+
+    /// Templates of the blog that replace the built-in ones, loaded when
+    /// building the blog
+    #[serde(skip)]
+    pub templates: UserTemplates,
+
+    /// When set, drafts are also published under paths derived from this
+    /// secret, so that they can be privately shared before being published
+    #[serde(default)]
+    pub draft_preview_secret: Option<String>,
+
+    /// Credentials that the development server asks for, so that the blog
+    /// can't be read by anyone reaching it, e.g: through a tunnel. The
+    /// `PAGETURTLE_DEV_USER` and `PAGETURTLE_DEV_PASSWORD` environment
+    /// variables take precedence over them
+    pub dev_server_auth: Option<Credentials>,
+
+    #[serde(default)]
+    pub notify: NotifyConfiguration,
+
+    #[serde(default)]
+    pub hosting: HostingConfiguration,
+
+    /// Makes the blog followable from the Fediverse when set
+    pub activitypub: Option<ActivityPubConfiguration>,
+
+    /// Whether the comment count of the posts' discussions should be fetched
+    /// when building the blog
+    #[serde(default)]
+    pub fetch_comment_counts: bool,
+
+    /// Whether links that are alone in a paragraph should be rendered as
+    /// cards with a preview of the linked page
+    #[serde(default)]
+    pub link_previews: bool,
+
+    /// Whether the images that posts link from other sites are downloaded
+    /// when building the blog and published along with it, so that its
+    /// pages don't depend on sites that may take them down. Otherwise, the
+    /// images are linked to their sites
+    #[serde(default = "default_true")]
+    pub localize_remote_images: bool,
+
+    /// Whether Hugo and Zola shortcodes, e.g: `{{< youtube id >}}`, should
+    /// be expanded, for blogs migrated from these generators
+    #[serde(default)]
+    pub shortcodes: bool,
+
+    /// Whether the generated pages should be consistently indented, with
+    /// their attributes sorted, so that the changes between builds can be
+    /// reviewed, e.g: when the output is kept in version control
+    #[serde(default)]
+    pub format_html: bool,
+
+    /// Shows the tags above the posts in the index, which filter the posts
+    /// when clicked
+    #[serde(default)]
+    pub tag_filter: bool,
+
+    /// Maximum amount of characters of the descriptions built from the
+    /// posts' content, used when a post doesn't have a description
+    #[serde(default = "default_description_length")]
+    pub description_length: usize,
+
+    /// Directory of the blog whose files are copied to the output as they
+    /// are, e.g: favicons, fonts or `robots.txt`
+    #[serde(default = "default_static_directory")]
+    pub static_directory: PathBuf,
+
+    /// Theme of the blog, found in `themes/<name>`. Themes may have
+    /// templates, a `styles.css` replacing the built-in stylesheet and a
+    /// `static` directory, all of which the blog's own files take precedence
+    /// over
+    #[serde(default)]
+    pub theme: Option<String>,
+
+    /// Format of the posts' dates, accepted along with the built-in ones,
+    /// e.g: `%d/%m/%Y` or `%d/%m/%Y %H:%M`
+    #[serde(default)]
+    pub date_format: Option<String>,
+
+    /// Offset from UTC of the posts' dates and times, e.g: `-03:00`
+    #[serde(
+        default = "default_utc_offset",
+        deserialize_with = "utc_offset::deserialize"
+    )]
+    pub utc_offset: FixedOffset,
+
     // Used for adding live reload support in the templates
     #[serde(default)]
     pub is_dev_server: bool,
+
+    /// Publishes the drafts along with the other posts, e.g: to preview them
+    #[serde(skip)]
+    pub include_drafts: bool,
+
+    /// Leaves out what changes between builds of the same posts, e.g: the
+    /// build time, so that the output can be kept in version control
+    #[serde(skip)]
+    pub reproducible: bool,
+}
+
+fn default_description_length() -> usize {
+    160
+}
+
+fn default_static_directory() -> PathBuf {
+    PathBuf::from("static")
+}
+
+fn default_utc_offset() -> FixedOffset {
+    FixedOffset::east_opt(0).unwrap()
 }
 
 impl BlogConfiguration {
@@ -216,37 +722,169 @@ impl BlogConfiguration {
         let config = toml::from_str(content)?;
         Ok(config)
     }
+
+    /// Absolute URL of one of the blog's assets, e.g: `styles.css`
+    pub fn asset(&self, name: &str) -> String {
+        format!("{}/{}", self.base_url, self.assets.path(name))
+    }
+
+    /// URL of a file of the blog's static directory, e.g: `demo/chart.js`,
+    /// which is published at the root of the blog
+    pub fn static_url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// Names of the authors of a post. Authors declared in the configuration
+    /// are referenced by their id, other ones are used as they are written.
+    /// Defaults to the blog's author.
+    pub fn post_authors<'a>(&'a self, metadata: &'a BlogPostMetadata) -> Vec<&'a str> {
+        match metadata.authors {
+            Some(ref authors) => authors
+                .iter()
+                .map(|a| match self.authors.get(a) {
+                    Some(profile) => profile.name.as_str(),
+                    None => a.as_str(),
+                })
+                .collect(),
+            None => vec![self.author.as_str()],
+        }
+    }
+
+    /// Profiles of the authors of a post that are declared in the
+    /// configuration
+    pub fn post_author_profiles(&self, metadata: &BlogPostMetadata) -> Vec<&AuthorProfile> {
+        metadata
+            .authors
+            .iter()
+            .flatten()
+            .filter_map(|a| self.authors.get(a))
+            .collect()
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct BlogPostMetadata {
     pub title: String,
+    /// Author names or ids of authors declared in the configuration
     pub authors: Option<Vec<String>>,
     pub slug: Option<String>,
+
+    /// Description used in the page's metadata, e.g: for search engines
     pub description: Option<String>,
 
+    /// Summary shown in the index and in the feed
+    pub summary: Option<String>,
+
+    /// Image used as the post's cover and social card, relative to the post
+    #[serde(alias = "cover")]
+    pub image: Option<String>,
+
+    /// Audio version of the post, relative to the post
+    pub audio_version: Option<String>,
+
+    /// Version that a changelog entry belongs to
+    pub version: Option<String>,
+
+    /// URL of a thread where the post is discussed, e.g: in Hacker News
+    pub discussion: Option<String>,
+
     #[serde(with = "date")]
     pub date: NaiveDate,
 
+    /// Time of the day when the post was published, e.g: `14:30`
+    #[serde(default, deserialize_with = "time::deserialize")]
+    pub time: Option<NaiveTime>,
+
     #[serde(default = "default_empty")]
     pub tags: Vec<String>,
 
     #[serde(default)]
     pub draft: bool,
 
+    /// Whether the post is listed in the feeds. Excluded posts are still
+    /// published in the blog
+    #[serde(default = "default_true")]
+    pub feed: bool,
+
     #[serde(default)]
     pub table_of_contents: bool,
+
+    /// Overrides the blog's placement of the table of contents
+    pub toc_placement: Option<TocPlacement>,
+
+    /// Position of the post when they are sorted by weight, e.g: for pages
+    /// that should always be listed first
+    #[serde(alias = "order")]
+    pub weight: Option<i64>,
+
+    /// Stylesheets of the static directory that are only included in the
+    /// post's page, e.g: for an interactive demo
+    #[serde(default = "default_empty")]
+    pub extra_css: Vec<String>,
+
+    /// Scripts of the static directory that are only included in the
+    /// post's page
+    #[serde(default = "default_empty")]
+    pub extra_js: Vec<String>,
+
+    /// Command that checks the code extracted from the post by `pageturtle
+    /// tangle --run`, e.g: `cargo test`. It runs in the directory the code
+    /// is extracted to
+    pub tangle: Option<String>,
 }
 
 impl BlogPostMetadata {
     pub fn format_date(&self) -> String {
-        let date = self.date;
-        let (_is_common_era, year) = date.year_ce();
+        format_date(self.date)
+    }
+
+    /// When the post was published. Posts without a time are considered as
+    /// published at midnight, in the blog's UTC offset
+    pub fn published_at(&self, config: &BlogConfiguration) -> DateTime<FixedOffset> {
+        let time = self.time.unwrap_or_default();
+        self.date
+            .and_time(time)
+            .and_local_timezone(config.utc_offset)
+            .unwrap()
+    }
+
+    pub fn status(&self, config: &BlogConfiguration) -> PostStatus {
+        if self.draft {
+            PostStatus::Draft
+        } else if self.published_at(config) > Utc::now() {
+            PostStatus::FutureDated
+        } else {
+            PostStatus::Published
+        }
+    }
+}
 
-        format!("{} {}, {}", format_month(date.month()), date.day(), year)
+/// Publication status of a post, as shown in the development server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostStatus {
+    Published,
+    /// Not included in the blog
+    Draft,
+    /// Published once its date and time come
+    FutureDated,
+}
+
+impl PostStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostStatus::Published => "published",
+            PostStatus::Draft => "draft",
+            PostStatus::FutureDated => "future-dated",
+        }
     }
 }
 
+pub(crate) fn format_date(date: NaiveDate) -> String {
+    let (_is_common_era, year) = date.year_ce();
+
+    format!("{} {}, {}", format_month(date.month()), date.day(), year)
+}
+
 fn format_month(month: u32) -> &'static str {
     match month {
         1 => "January",
@@ -261,19 +899,28 @@ fn format_month(month: u32) -> &'static str {
         10 => "October",
         11 => "November",
         12 => "December",
-        n => panic!("unknown month: {}", n)
+        n => panic!("unknown month: {}", n),
     }
 }
 
 #[derive(Debug)]
-pub struct BlogPost<'a> {
+pub struct BlogPost {
     pub metadata: BlogPostMetadata,
     pub raw_content: String,
-    pub ast: &'a AstNode<'a>,
     pub toc: TableOfContents,
+    pub word_count: usize,
     pub reading_time: u16,
 }
 
+/// A post along with the AST it was parsed into. The AST lives in the arena
+/// of the compiler that parsed it, so it can't leave the thread that parsed
+/// it, unlike the [`PublishableBlogPost`] it is prepared into
+#[derive(Debug)]
+pub struct ParsedBlogPost<'a> {
+    pub post: BlogPost,
+    pub ast: &'a AstNode<'a>,
+}
+
 #[derive(Debug)]
 /// Error that can happen when parsing a post and compiling it to HTML
 pub struct CompilePostError {
@@ -283,24 +930,140 @@ pub struct CompilePostError {
 }
 
 #[derive(Debug)]
-pub struct PublishableBlogPost<'a> {
-    pub post: &'a BlogPost<'a>,
+pub struct PublishableBlogPost {
+    pub post: BlogPost,
     pub output_filename: PathBuf,
-    pub filepath: &'a Path,
+    pub filepath: PathBuf,
+    /// Absolute URL that the post is published at, under the blog's
+    /// `base_url`. Pages and feeds point to it, so that search engines don't
+    /// index the post once for each hostname that serves the blog
+    pub canonical_url: String,
     pub description: String,
+    pub summary: String,
     pub rendered_html: String,
     pub images: Vec<PostImage>,
+    pub cover: Option<PostImage>,
+    /// Cropped version of the cover, shown in cards
+    pub thumbnail: Option<PostThumbnail>,
+    pub audio: Option<PostAudio>,
+    /// Amount of comments in the post's discussion, fetched at build time
+    pub comment_count: Option<u64>,
+    /// Plain text of the post, kept when the blog has a search page
+    pub search_text: Option<String>,
+    /// Posts most related to this one, listed after it
+    pub related_posts: Vec<RelatedPost>,
+}
+
+/// Sorts the posts in the given order, breaking ties by date, newest first
+pub fn sort_posts(posts: &mut [PublishableBlogPost], order: PostOrder) {
+    posts.sort_by(|a, b| {
+        let (a, b) = (&a.post.metadata, &b.post.metadata);
+        let newest_first = (b.date, b.time).cmp(&(a.date, a.time));
+
+        let ordering = match order {
+            PostOrder::Date => Ordering::Equal,
+            PostOrder::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            PostOrder::Weight => match (a.weight, b.weight) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        };
+
+        ordering.then(newest_first)
+    });
+}
+
+/// Posts of the same section published right before and after a post of
+/// the list, which its page links to. Posts are listed from newest to oldest
+/// by default, so the previous one comes after it in the list
+pub fn adjacent_posts<'a>(
+    post: &PublishableBlogPost,
+    posts: &'a [PublishableBlogPost],
+) -> (
+    Option<&'a PublishableBlogPost>,
+    Option<&'a PublishableBlogPost>,
+) {
+    let section: Vec<&PublishableBlogPost> = posts
+        .iter()
+        .filter(|p| p.section() == post.section())
+        .collect();
+    let i = match section.iter().position(|p| std::ptr::eq(*p, post)) {
+        Some(i) => i,
+        None => return (None, None),
+    };
+
+    (
+        section.get(i + 1).copied(),
+        i.checked_sub(1).map(|j| section[j]),
+    )
+}
+
+/// Takes the future-dated posts out of the posts, so that they are only
+/// published once their time comes
+pub fn withhold_scheduled(
+    posts: &mut Vec<PublishableBlogPost>,
+    config: &BlogConfiguration,
+) -> Vec<PublishableBlogPost> {
+    let now = Utc::now();
+    let (scheduled, published) = std::mem::take(posts)
+        .into_iter()
+        .partition(|p| p.post.metadata.published_at(config) > now);
+
+    *posts = published;
+    scheduled
+}
+
+/// The future-dated post that is published the soonest, if any, e.g: to
+/// schedule the next build for when it is published
+pub fn next_scheduled_post<'a>(
+    posts: &'a [PublishableBlogPost],
+    config: &BlogConfiguration,
+) -> Option<&'a PublishableBlogPost> {
+    let now = Utc::now();
+
+    posts
+        .iter()
+        .filter(|p| p.post.metadata.published_at(config) > now)
+        .min_by_key(|p| p.post.metadata.published_at(config))
 }
 
 pub fn prepare_for_publish<'a>(
-    p: &'a BlogPost<'a>,
-    filepath: &'a Path,
+    parsed: ParsedBlogPost<'a>,
+    filepath: PathBuf,
     compiler: &'a PostCompiler<'a>,
-) -> PublishableBlogPost<'a> {
-    let images = map_images(p.ast);
+    config: &BlogConfiguration,
+) -> PublishableBlogPost {
+    let ParsedBlogPost { post: p, ast } = parsed;
+    let post_directory = filepath.parent().unwrap();
+    // Only the HTML written in the post is sanitized, so it runs before the
+    // images are rendered as HTML
+    sanitizer::sanitize(ast, &config.sanitizer);
+    let images = map_images(ast, post_directory, config);
+    typography::refine(ast, &config.typography);
+    // Remote covers that weren't downloaded can't be processed as images of
+    // the blog
+    let cover = p
+        .metadata
+        .image
+        .as_ref()
+        .filter(|url| !remote_images::is_remote(url))
+        .map(|url| process_image(url, post_directory, config));
+    let thumbnail = cover
+        .as_ref()
+        .filter(|_| config.thumbnails.enabled)
+        .and_then(|cover| PostThumbnail::new(cover, &config.thumbnails));
+    let audio = p
+        .metadata
+        .audio_version
+        .as_ref()
+        .map(|url| process_audio(url, post_directory));
 
     // ^ Operations that mutate AST nodes should be done before converting to HTML
-    let rendered_html = compiler.ast_to_html(p.ast);
+    let rendered_html = wrap_tables(&compiler.ast_to_html(ast), &config.tables);
+    let rendered_html = footnotes::render(rendered_html, &config.footnotes);
+    let search_text = config.enable_search.then(|| search::searchable_text(ast));
 
     let metadata = &p.metadata;
     let filename = match metadata.slug {
@@ -308,53 +1071,183 @@ pub fn prepare_for_publish<'a>(
         None => slugify(&metadata.title),
     };
     let filename = Path::new(&filename).with_extension("html");
-
-    let description = match p.metadata.description {
-        Some(ref d) => d.to_owned(),
-        None => build_description(p.ast),
+    let filename = match section_name(&filepath)
+        .and_then(|name| config.sections.get(name))
+        .and_then(|section| section.output_directory.as_deref())
+    {
+        Some(directory) => Path::new(directory).join(filename),
+        None => filename,
+    };
+    let canonical_url = format!("{}/{}", config.base_url, urls::from_path(&filename));
+
+    // Both fall back to each other and to the excerpt, which is the content
+    // before the `<!-- more -->` marker
+    let excerpt = build_excerpt(ast);
+    let fallback = || {
+        excerpt
+            .clone()
+            .unwrap_or_else(|| build_description(ast, config.description_length))
     };
+    let description = metadata
+        .description
+        .clone()
+        .or_else(|| metadata.summary.clone())
+        .unwrap_or_else(fallback);
+    let summary = metadata
+        .summary
+        .clone()
+        .or_else(|| excerpt.clone())
+        .or_else(|| metadata.description.clone())
+        .unwrap_or_else(fallback);
 
     PublishableBlogPost {
         post: p,
         filepath,
         output_filename: filename,
+        canonical_url,
         description,
+        summary,
         rendered_html,
         images,
+        thumbnail,
+        cover,
+        audio,
+        comment_count: None,
+        search_text,
+        related_posts: vec![],
     }
 }
 
-fn build_description<'a>(ast: &'a AstNode<'a>) -> String {
-    use comrak::nodes::NodeValue::*;
+// Wraps the tables of the rendered post in a container that can be scrolled
+// horizontally and carries the classes of the configured table styles
+fn wrap_tables(html: &str, style: &TableStyle) -> String {
+    let opening = format!("<div class=\"{}\"><table>", style.wrapper_classes());
+    html.replace("<table>", &opening)
+        .replace("</table>", "</table></div>")
+}
 
-    for node in ast.traverse() {
-        match node {
-            comrak::arena_tree::NodeEdge::Start(nv) => {
-                if let Paragraph = nv.data.borrow().value {
-                    let mut buffer = String::new();
+impl PublishableBlogPost {
+    /// Absolute URL of the post's page
+    pub fn url(&self, config: &BlogConfiguration) -> String {
+        format!("{}/{}", config.base_url, self.url_path())
+    }
 
-                    for c in nv.children() {
-                        if let Text(ref t) = c.data.borrow().value {
-                            buffer.push_str(t);
-                            buffer.push(' ');
-                        }
-                    }
+    /// Moves a draft to the path it is published at when draft previews are
+    /// enabled, so that its page doesn't point to the path of its slug
+    pub fn move_to_preview(&mut self, secret: &str, config: &BlogConfiguration) {
+        self.output_filename = draft_preview_filename(&self.output_filename, secret);
+        self.canonical_url = self.url(config);
+    }
 
-                    let description = buffer.split(' ').take(25).collect::<Vec<&str>>().join(" ");
+    /// Path of the post's page relative to the root of the blog, e.g:
+    /// `my-post.html`
+    pub fn url_path(&self) -> String {
+        urls::from_path(&self.output_filename)
+    }
 
-                    return format!("{}...", description);
-                }
+    /// Image shown in the cards that link to the post, preferring its
+    /// thumbnail to its full cover
+    pub fn card_image_html(&self) -> Option<String> {
+        let alt = &self.post.metadata.title;
+        match (&self.thumbnail, &self.cover) {
+            (Some(thumbnail), _) => Some(thumbnail.to_html(alt)),
+            (None, Some(cover)) => Some(cover.to_html(alt)),
+            (None, None) => None,
+        }
+    }
+
+    /// Slugs of the post's tags, separated by spaces
+    pub fn tag_slugs(&self, config: &BlogConfiguration) -> String {
+        let slugs: Vec<String> = self
+            .post
+            .metadata
+            .tags
+            .iter()
+            .map(|tag| config.tags.slug(tag))
+            .collect();
+
+        slugs.join(" ")
+    }
+
+    /// Name of the directory inside `posts/` that the post is in, if any
+    pub fn section(&self) -> Option<&str> {
+        section_name(&self.filepath)
+    }
+}
+
+fn section_name(filepath: &Path) -> Option<&str> {
+    let name = filepath.parent()?.file_name()?.to_str()?;
+    (name != "posts").then_some(name)
+}
+
+/// Path where a draft is published when draft previews are enabled. It is
+/// derived from the secret so that it can't be guessed from the post's slug,
+/// and stays the same between builds so that shared links keep working
+fn draft_preview_filename(filename: &Path, secret: &str) -> PathBuf {
+    Path::new("drafts")
+        .join(hashing::keyed_hash(secret, urls::from_path(filename)))
+        .with_extension("html")
+}
+
+pub(crate) const EXCERPT_MARKER: &str = "<!-- more -->";
+
+// Plain text of the paragraphs before the excerpt marker, if the post has one
+fn build_excerpt<'a>(ast: &'a AstNode<'a>) -> Option<String> {
+    let mut paragraphs = Vec::new();
+
+    for node in ast.children() {
+        match node.data.borrow().value {
+            NodeValue::HtmlBlock(ref html) if html.literal.trim() == EXCERPT_MARKER => {
+                return Some(paragraphs.join(" "));
             }
-            comrak::arena_tree::NodeEdge::End(_nv) => continue,
+            NodeValue::Paragraph => paragraphs.push(plain_text(node)),
+            _ => continue,
         }
     }
 
-    "".to_owned()
+    None
 }
 
-fn reading_time<'a>(ast: &'a AstNode<'a>) -> u16 {
+// Builds a description from the first paragraphs of the post, cutting it at
+// the last word that fits in `max_length` characters
+fn build_description<'a>(ast: &'a AstNode<'a>, max_length: usize) -> String {
+    let mut words: Vec<String> = Vec::new();
+
+    let paragraphs = ast
+        .children()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Paragraph));
+
+    for paragraph in paragraphs {
+        words.extend(plain_text(paragraph).split_whitespace().map(str::to_owned));
+
+        if words.iter().map(|w| w.chars().count() + 1).sum::<usize>() > max_length {
+            break;
+        }
+    }
+
+    let mut description = String::new();
+
+    for word in &words {
+        let length = description.chars().count();
+        let separator = if description.is_empty() { 0 } else { 1 };
+
+        // Leaves room for the ellipsis
+        if length + separator + word.chars().count() + 1 > max_length {
+            let trimmed = description.trim_end_matches(|c: char| c.is_ascii_punctuation());
+            return format!("{}…", trimmed);
+        }
+
+        if separator == 1 {
+            description.push(' ');
+        }
+        description.push_str(word);
+    }
+
+    description
+}
+
+fn word_count<'a>(ast: &'a AstNode<'a>) -> usize {
     use comrak::nodes::NodeValue::*;
-    let avg_words_per_minute = 225.0;
     let mut words_count = 0;
 
     for node in ast.traverse() {
@@ -372,7 +1265,12 @@ fn reading_time<'a>(ast: &'a AstNode<'a>) -> u16 {
         }
     }
 
-    let average = (words_count as f64) / avg_words_per_minute;
+    words_count
+}
+
+fn reading_time(word_count: usize) -> u16 {
+    let avg_words_per_minute = 225.0;
+    let average = (word_count as f64) / avg_words_per_minute;
     average.ceil() as u16
 }
 
@@ -381,89 +1279,315 @@ pub struct PostImage {
     /// The path where an image can be found, relative to the blog's root
     pub original_path: String,
 
-    /// The final path where the processed image will be found in the blog
-    /// (e.g: /img/my-tour.png)
+    /// The final path where the processed image will be found in the blog,
+    /// relative to the images directory (e.g: 3f2a9c0d1b4e5a67-my-tour.webp)
     pub final_path: PathBuf,
+
+    /// Width and height of the image, if it could be read at build time
+    pub dimensions: Option<(u32, u32)>,
+
+    /// Tiny blurred version of the image as a data URI, shown while the
+    /// actual image is loading
+    pub placeholder: Option<String>,
+
+    /// Smaller versions of the image, listed in its `srcset`
+    pub variants: Vec<ImageVariant>,
 }
 
-// TODO: Support image resizing and optimization (webp, responsive images)
+impl PostImage {
+    /// URL of the image in the published blog, e.g: /img/my-tour.png
+    pub fn url(&self) -> String {
+        format!("/img/{}", urls::from_path(&self.final_path))
+    }
+
+    pub fn to_html(&self, alt: &str) -> String {
+        image_tag(self, alt, "", None)
+    }
+}
+
+/// Extensions of the images that are converted to WebP. GIFs are left out
+/// as they may be animated
+const WEBP_SOURCES: &[&str] = &["bmp", "jpeg", "jpg", "png", "tif", "tiff"];
 
 // Walks the markdown AST and maps the images referenced in a post to the path
 // they should have when publishing the blog
-// This mutates the image nodes in the AST, changing their URL to their final
-// path in the dist directory
-fn map_images<'a>(ast: &'a AstNode<'a>) -> Vec<PostImage> {
+// This replaces the image nodes in the AST with inline HTML pointing to their
+// final path in the dist directory, including their dimensions and lazy
+// loading attributes
+fn map_images<'a>(
+    ast: &'a AstNode<'a>,
+    post_directory: &Path,
+    config: &BlogConfiguration,
+) -> Vec<PostImage> {
     use comrak::nodes::NodeValue::*;
 
-    // TODO: deduplicate images with the same name
-
-    let mut post_images = Vec::new();
+    let mut image_nodes = Vec::new();
 
-    for node in ast.borrow().traverse() {
+    for node in ast.traverse() {
         match node {
-            comrak::arena_tree::NodeEdge::Start(nv) => match nv.data.borrow_mut().value {
-                Image(ref mut i) => {
-                    let path = Path::new(&i.url);
-                    let filename = path.file_name().unwrap();
-                    let final_path: PathBuf = filename.to_owned().into();
-
-                    post_images.push(PostImage {
-                        original_path: i.url.to_owned(),
-                        final_path: final_path.to_owned(),
-                    });
-
-                    i.url = Path::new("/")
-                        .join("img")
-                        .join(filename)
-                        .into_os_string()
-                        .into_string()
-                        .unwrap();
-                }
+            comrak::arena_tree::NodeEdge::Start(nv) => match nv.data.borrow().value {
+                Image(_) => image_nodes.push(nv),
                 _ => continue,
             },
             _ => continue,
         }
     }
 
+    let mut post_images = Vec::new();
+
+    for node in image_nodes {
+        let link = match node.data.borrow().value {
+            Image(ref i) => i.clone(),
+            _ => unreachable!(),
+        };
+        // Rendered as they are, linking to their site
+        if remote_images::is_remote(&link.url) {
+            continue;
+        }
+
+        let alt = plain_text(node);
+        for child in node.children().collect::<Vec<_>>() {
+            child.detach();
+        }
+
+        let image = process_image(&link.url, post_directory, config);
+        node.data.borrow_mut().value = HtmlInline(image_tag(
+            &image,
+            &alt,
+            &link.title,
+            Some(&config.responsive_images.sizes),
+        ));
+        post_images.push(image);
+    }
+
     post_images
 }
 
+/// Maps an image referenced by a post to its final path, reading the
+/// metadata needed to render it
+fn process_image(url: &str, post_directory: &Path, config: &BlogConfiguration) -> PostImage {
+    let path = Path::new(url);
+    let source = post_directory.join(path);
+    let mut final_path = content_hashed_filename(&source);
+    if config.webp_images && converts_to_webp(&source) {
+        final_path.set_extension("webp");
+    }
+    let dimensions = image::image_dimensions(&source).ok();
+    let placeholder = if config.image_placeholders {
+        image_placeholder(&source)
+    } else {
+        None
+    };
+    let variants = match dimensions {
+        Some((width, _)) => {
+            responsive_images::variants(&final_path, width, &config.responsive_images)
+        }
+        None => vec![],
+    };
+
+    PostImage {
+        original_path: url.to_owned(),
+        final_path,
+        dimensions,
+        placeholder,
+        variants,
+    }
+}
+
+/// Names a file after its content, so that it can be cached forever and so
+/// that different files with the same name don't overwrite each other
+fn content_hashed_filename(source: &Path) -> PathBuf {
+    // Paths such as `..` have no file name
+    let filename = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file");
+
+    match std::fs::read(source) {
+        Ok(content) => format!("{}-{}", hashing::content_hash(content), filename).into(),
+        Err(_) => filename.into(),
+    }
+}
+
+// Whether an image is published as WebP when the blog converts its images
+fn converts_to_webp(source: &Path) -> bool {
+    source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .is_some_and(|e| WEBP_SOURCES.contains(&e.as_str()))
+}
+
+#[derive(Debug)]
+pub struct PostAudio {
+    /// The path where the audio can be found, relative to the post
+    pub original_path: String,
+
+    /// The final path of the audio, relative to the audio directory
+    pub final_path: PathBuf,
+
+    /// Size of the file in bytes
+    pub length: u64,
+
+    pub mime_type: &'static str,
+}
+
+impl PostAudio {
+    /// URL of the audio in the published blog, e.g: /audio/my-tour.mp3
+    pub fn url(&self) -> String {
+        format!("/audio/{}", urls::from_path(&self.final_path))
+    }
+}
+
+fn process_audio(url: &str, post_directory: &Path) -> PostAudio {
+    let source = post_directory.join(url);
+    let length = std::fs::metadata(&source).map(|m| m.len()).unwrap_or(0);
+
+    let mime_type = match source.extension().and_then(|e| e.to_str()) {
+        Some("ogg") | Some("oga") => "audio/ogg",
+        Some("opus") => "audio/opus",
+        Some("m4a") => "audio/mp4",
+        Some("wav") => "audio/wav",
+        Some("flac") => "audio/flac",
+        _ => "audio/mpeg",
+    };
+
+    PostAudio {
+        original_path: url.to_owned(),
+        final_path: content_hashed_filename(&source),
+        length,
+        mime_type,
+    }
+}
+
+/// Builds a tiny blurred version of an image, inlined as a data URI
+fn image_placeholder(path: &Path) -> Option<String> {
+    let image = image::open(path).ok()?;
+    let thumbnail = image.thumbnail(16, 16).blur(1.0);
+
+    let mut buffer = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut buffer, ImageFormat::Png).ok()?;
+
+    Some(format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(buffer.into_inner())
+    ))
+}
+
+// Images are only given a `srcset` along with their `sizes`, as browsers
+// would assume that they take the whole width of the screen otherwise
+fn image_tag(image: &PostImage, alt: &str, title: &str, sizes: Option<&str>) -> String {
+    let mut tag = format!(
+        "<img src=\"{}\" alt=\"{}\"",
+        escape_html(&image.url()),
+        escape_html(alt)
+    );
+
+    // The original image is listed along with its smaller versions
+    let width = image.dimensions.filter(|_| !image.variants.is_empty());
+    if let (Some((width, _)), Some(sizes)) = (width, sizes) {
+        let mut candidates: Vec<String> = image
+            .variants
+            .iter()
+            .map(|v| format!("{} {}w", v.url(), v.width))
+            .collect();
+        candidates.push(format!("{} {}w", image.url(), width));
+
+        tag.push_str(&format!(
+            " srcset=\"{}\" sizes=\"{}\"",
+            escape_html(&candidates.join(", ")),
+            escape_html(sizes)
+        ));
+    }
+
+    if !title.is_empty() {
+        tag.push_str(&format!(" title=\"{}\"", escape_html(title)));
+    }
+
+    if let Some((width, height)) = image.dimensions {
+        tag.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+    }
+
+    if let Some(ref placeholder) = image.placeholder {
+        tag.push_str(&format!(
+            " class=\"bg-cover bg-center\" style=\"background-image:url({})\"",
+            placeholder
+        ));
+    }
+
+    tag.push_str(" loading=\"lazy\" decoding=\"async\" />");
+    tag
+}
+
+/// Concatenates the text found under the given node, ignoring formatting
+pub(crate) fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+    use comrak::nodes::NodeValue::*;
+
+    let mut buffer = String::new();
+
+    for child in node.descendants() {
+        match child.data.borrow().value {
+            Text(ref t) => buffer.push_str(t),
+            Code(ref c) => buffer.push_str(&c.literal),
+            SoftBreak | LineBreak => buffer.push(' '),
+            _ => continue,
+        }
+    }
+
+    buffer
+}
+
 pub fn build_blog_post<'a>(
     content: &str,
     compiler: &'a PostCompiler<'a>,
-) -> Result<BlogPost<'a>, CompilePostError> {
+    config: &BlogConfiguration,
+    defaults: &FrontmatterDefaults,
+) -> Result<ParsedBlogPost<'a>, CompilePostError> {
     let ast = compiler.to_ast(content);
+    // Only the HTML written in the post is omitted, so it runs before nodes
+    // are rewritten as HTML, e.g: images or link previews
+    if !config.raw_html && !config.sanitizer.enabled {
+        sanitizer::omit_raw_html(ast);
+    }
 
-    let metadata = match parse_frontmatter(ast) {
+    let metadata = match parse_frontmatter(ast, config, defaults) {
         Ok(settings) => settings,
         Err(msg) => {
-            // TODO: line and column error messages
+            // The frontmatter starts the post. Errors of its fields have
+            // their position in their message
             return Err(CompilePostError {
                 message: msg,
-                line: 10,
-                column: 20,
+                line: 1,
+                column: 1,
             });
         }
     };
 
     let toc = TableOfContents::from_ast(ast);
-    let reading_time = reading_time(ast);
+    let word_count = word_count(ast);
+    let reading_time = reading_time(word_count);
 
-    Ok(BlogPost {
-        ast, // TODO: figure out how to have this mutable AST reference
+    let post = BlogPost {
         raw_content: content.to_owned(),
         reading_time,
         toc,
+        word_count,
         metadata,
-    })
+    };
+
+    Ok(ParsedBlogPost { post, ast })
 }
 
-fn parse_frontmatter<'a>(ast: &'a AstNode<'a>) -> Result<BlogPostMetadata, String> {
+pub(crate) fn parse_frontmatter<'a>(
+    ast: &'a AstNode<'a>,
+    config: &BlogConfiguration,
+    defaults: &FrontmatterDefaults,
+) -> Result<BlogPostMetadata, String> {
     use comrak::nodes::NodeValue::*;
 
     let mut frontmatter: Option<String> = None;
 
-    for node in ast.borrow().traverse() {
+    for node in ast.traverse() {
         match node {
             comrak::arena_tree::NodeEdge::Start(nv) => {
                 if let FrontMatter(s) = &nv.borrow().data.borrow().value {
@@ -477,7 +1601,12 @@ fn parse_frontmatter<'a>(ast: &'a AstNode<'a>) -> Result<BlogPostMetadata, Strin
     match frontmatter {
         Some(s) => {
             let unquoted = s.replace("---", "");
-            match serde_yaml::from_str::<BlogPostMetadata>(&unquoted) {
+            let mut frontmatter: serde_yaml::Value =
+                serde_yaml::from_str(&unquoted).map_err(|e| e.to_string())?;
+            defaults.apply(&mut frontmatter);
+            normalize_date(&mut frontmatter, config)?;
+
+            match serde_yaml::from_value::<BlogPostMetadata>(frontmatter) {
                 Ok(settings) => Ok(settings),
                 Err(e) => Err(e.to_string()),
             }
@@ -485,3 +1614,157 @@ fn parse_frontmatter<'a>(ast: &'a AstNode<'a>) -> Result<BlogPostMetadata, Strin
         None => Err("could not find frontmatter section in file".to_owned()),
     }
 }
+
+// Rewrites the date of the frontmatter in the format that posts are
+// deserialized with, moving its time, if any, to the `time` field unless the
+// post already has one
+fn normalize_date(
+    frontmatter: &mut serde_yaml::Value,
+    config: &BlogConfiguration,
+) -> Result<(), String> {
+    let mapping = match frontmatter.as_mapping_mut() {
+        Some(mapping) => mapping,
+        None => return Ok(()),
+    };
+    let raw_date = match mapping.get("date").and_then(serde_yaml::Value::as_str) {
+        Some(raw_date) => raw_date,
+        None => return Ok(()),
+    };
+
+    let (date, time) = date::parse(raw_date, config.date_format.as_deref(), config.utc_offset)?;
+
+    mapping.insert("date".into(), date.format(date::FORMAT).to_string().into());
+    if let Some(time) = time {
+        if !mapping.contains_key("time") {
+            mapping.insert("time".into(), time.format("%H:%M:%S").to_string().into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn config(extra: &str) -> BlogConfiguration {
+        testing::config(&format!(
+            "blog_title = \"Dated blog\"\n\
+             author = \"Pageturtle\"\n\
+             base_url = \"https://example.com\"\n\
+             blog_description = \"Posts dated in several formats\"\n\
+             {}",
+            extra
+        ))
+    }
+
+    // Date and time of a frontmatter after normalizing its date
+    fn normalized(
+        frontmatter: &str,
+        config: &BlogConfiguration,
+    ) -> Result<(Option<String>, Option<String>), String> {
+        let mut frontmatter: serde_yaml::Value = serde_yaml::from_str(frontmatter).unwrap();
+        normalize_date(&mut frontmatter, config)?;
+
+        let field = |name: &str| frontmatter.get(name)?.as_str().map(str::to_owned);
+        Ok((field("date"), field("time")))
+    }
+
+    #[test]
+    fn normalizes_dates_in_the_accepted_formats() {
+        let cases = [
+            ("", "date: 2023-01-05", "2023-01-05", None),
+            ("", "date: 2023-01-05 14:30", "2023-01-05", Some("14:30:00")),
+            (
+                "",
+                "date: 2023-01-05 14:30:15",
+                "2023-01-05",
+                Some("14:30:15"),
+            ),
+            ("", "date: 2023-01-05T14:30", "2023-01-05", Some("14:30:00")),
+            (
+                "",
+                "date: 2023-01-05T14:30:15",
+                "2023-01-05",
+                Some("14:30:15"),
+            ),
+            (
+                "",
+                "date: 2023-01-05T14:30:00Z",
+                "2023-01-05",
+                Some("14:30:00"),
+            ),
+            (
+                "utc_offset = \"-03:00\"",
+                "date: 2023-01-06T01:00:00Z",
+                "2023-01-05",
+                Some("22:00:00"),
+            ),
+            (
+                "utc_offset = \"-03:00\"",
+                "date: 2023-01-05T14:30:00-03:00",
+                "2023-01-05",
+                Some("14:30:00"),
+            ),
+            (
+                "date_format = \"%d/%m/%Y\"",
+                "date: 05/01/2023",
+                "2023-01-05",
+                None,
+            ),
+            (
+                "date_format = \"%d/%m/%Y %H:%M\"",
+                "date: 05/01/2023 14:30",
+                "2023-01-05",
+                Some("14:30:00"),
+            ),
+            // The built-in formats are still accepted along with the blog's
+            (
+                "date_format = \"%d/%m/%Y\"",
+                "date: 2023-01-05",
+                "2023-01-05",
+                None,
+            ),
+            // The time of the post is kept over the one of its date
+            (
+                "",
+                "date: 2023-01-05 14:30\ntime: \"09:00\"",
+                "2023-01-05",
+                Some("09:00"),
+            ),
+        ];
+
+        for (extra, frontmatter, date, time) in cases {
+            assert_eq!(
+                normalized(frontmatter, &config(extra)),
+                Ok((Some(date.to_owned()), time.map(str::to_owned))),
+                "{:?}",
+                frontmatter
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_frontmatter_without_a_date_as_it_is() {
+        assert_eq!(normalized("title: Post", &config("")), Ok((None, None)));
+    }
+
+    #[test]
+    fn fails_on_invalid_dates() {
+        let cases = [
+            ("", "date: January 5th"),
+            ("", "date: 2023-13-05"),
+            ("", "date: 2023-01-05 25:00"),
+            ("date_format = \"%d/%m/%Y\"", "date: 2023/01/05"),
+        ];
+
+        for (extra, frontmatter) in cases {
+            let error = normalized(frontmatter, &config(extra)).unwrap_err();
+            assert!(error.starts_with("invalid date"), "{}", error);
+        }
+
+        let error = normalized("date: 5 Jan", &config("date_format = \"%d/%m/%Y\"")).unwrap_err();
+        assert!(error.ends_with("or in the format `%d/%m/%Y`"), "{}", error);
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::NaiveDate;
+use slug::slugify;
+
+use crate::blog::{format_date, PublishableBlogPost};
+
+/// Entries of the changelog that belong to the same version
+#[derive(Debug)]
+pub struct Release<'a> {
+    pub version: &'a str,
+    /// Id of the release's section in the changelog page
+    pub anchor: String,
+    /// Date of the release's latest entry
+    pub date: NaiveDate,
+    pub entries: Vec<&'a PublishableBlogPost>,
+}
+
+impl<'a> Release<'a> {
+    pub fn format_date(&self) -> String {
+        format_date(self.date)
+    }
+}
+
+/// Groups changelog entries by their version, sorting releases and their
+/// entries from newest to oldest. Entries without a version are skipped.
+pub fn group_releases<'a>(entries: &'a [PublishableBlogPost]) -> Vec<Release<'a>> {
+    let mut releases: Vec<Release<'a>> = Vec::new();
+
+    for entry in entries {
+        let version = match entry.post.metadata.version {
+            Some(ref v) => v.as_str(),
+            None => continue,
+        };
+        let date = entry.post.metadata.date;
+
+        match releases.iter_mut().find(|r| r.version == version) {
+            Some(release) => {
+                release.date = release.date.max(date);
+                release.entries.push(entry);
+            }
+            None => releases.push(Release {
+                version,
+                anchor: release_anchor(version),
+                date,
+                entries: vec![entry],
+            }),
+        }
+    }
+
+    for release in &mut releases {
+        release
+            .entries
+            .sort_by_key(|e| std::cmp::Reverse(e.post.metadata.date));
+    }
+    releases.sort_by_key(|r| std::cmp::Reverse(r.date));
+
+    releases
+}
+
+fn release_anchor(version: &str) -> String {
+    slugify(format!("v{}", version.trim_start_matches('v')))
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use crate::{
+    blog::{BlogConfiguration, PublishableBlogPost},
+    urls,
+};
+
+/// Pages where several posts are published, e.g: posts with the same slug,
+/// along with the paths of these posts
+pub fn shared_pages(posts: &[PublishableBlogPost]) -> Vec<(&Path, Vec<&Path>)> {
+    let mut posts_by_page: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+    for post in posts {
+        posts_by_page
+            .entry(post.output_filename.as_path())
+            .or_default()
+            .push(&post.filepath);
+    }
+
+    posts_by_page
+        .into_iter()
+        .filter(|(_, filepaths)| filepaths.len() > 1)
+        .collect()
+}
+
+/// Pages that a post links to which aren't among the `pages` of the blog,
+/// given relative to its root
+pub fn broken_links(
+    post: &PublishableBlogPost,
+    pages: &HashSet<String>,
+    config: &BlogConfiguration,
+) -> Vec<String> {
+    linked_pages(post, config)
+        .into_iter()
+        .filter(|page| !pages.contains(page))
+        .collect()
+}
+
+/// Pages of the blog that a post links to, relative to the root of the blog,
+/// e.g: `my-post.html`. Links to other sites and to files other than pages
+/// are left out
+pub fn linked_pages(post: &PublishableBlogPost, config: &BlogConfiguration) -> Vec<String> {
+    // e.g: `/blog` for blogs published at https://example.com/blog
+    let base_path = config
+        .base_url
+        .splitn(4, '/')
+        .nth(3)
+        .map(|path| format!("/{}", path.trim_end_matches('/')));
+    let page_dir = urls::from_path(post.output_filename.parent().unwrap_or(Path::new("")));
+
+    let mut pages = vec![];
+
+    for (start, _) in post.rendered_html.match_indices("href=\"") {
+        let href = match post.rendered_html[start + 6..].split_once('"') {
+            Some((href, _)) => href.split(['#', '?']).next().unwrap_or_default(),
+            None => continue,
+        };
+
+        let path = if !config.base_url.is_empty() && href.starts_with(&config.base_url) {
+            href[config.base_url.len()..].to_owned()
+        } else if href.contains(':') || href.starts_with("//") || href.is_empty() {
+            continue;
+        } else if let Some(absolute) = href.strip_prefix('/') {
+            match base_path {
+                Some(ref base_path) => match href.strip_prefix(base_path.as_str()) {
+                    Some(path) => path.to_owned(),
+                    None => continue,
+                },
+                None => absolute.to_owned(),
+            }
+        } else {
+            format!("{}/{}", page_dir, href)
+        };
+
+        let mut segments: Vec<&str> = vec![];
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop();
+                }
+                segment => segments.push(segment),
+            }
+        }
+        if path.is_empty() || path.ends_with('/') {
+            segments.push("index.html");
+        }
+
+        let page = segments.join("/");
+        if page.ends_with(".html") {
+            pages.push(page);
+        }
+    }
+
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{routes, testing};
+
+    const CONFIG: &str = r#"
+        blog_title = "Checked blog"
+        author = "Pageturtle"
+        base_url = "https://example.com/blog"
+        blog_description = "Posts with links between them"
+    "#;
+
+    // Post with the given frontmatter fields and content
+    fn post(fields: &str, content: &str) -> String {
+        format!("---\n{}\ndate: 2023-05-20\n---\n\n{}\n", fields, content)
+    }
+
+    #[test]
+    fn finds_the_posts_published_at_the_same_page() {
+        let config = testing::config(CONFIG);
+        let posts = testing::compile_posts(
+            &[
+                ("posts/first.md", &post("title: Same title", "First")),
+                (
+                    "posts/second.md",
+                    &post("title: Other\nslug: same title", "Second"),
+                ),
+                ("posts/third.md", &post("title: Unique", "Third")),
+            ],
+            &config,
+        );
+
+        assert_eq!(
+            shared_pages(&posts),
+            vec![(
+                Path::new("same-title.html"),
+                vec![Path::new("posts/first.md"), Path::new("posts/second.md")]
+            )]
+        );
+    }
+
+    #[test]
+    fn resolves_the_pages_that_posts_link_to() {
+        let config = testing::config(CONFIG);
+        let cases = [
+            ("[a](other.html)", vec!["other.html"]),
+            ("[a](./other.html#part)", vec!["other.html"]),
+            ("[a](/blog/other.html?ref=1)", vec!["other.html"]),
+            (
+                "[a](https://example.com/blog/tags/rust.html)",
+                vec!["tags/rust.html"],
+            ),
+            ("[a](/blog/)", vec!["index.html"]),
+            ("[a](/elsewhere.html)", vec![]),
+            ("[a](https://example.org/page.html)", vec![]),
+            ("[a](//example.org/page.html)", vec![]),
+            ("[a](mailto:someone@example.com)", vec![]),
+            ("[a](#section)", vec![]),
+            ("[a](image.png)", vec![]),
+        ];
+
+        for (content, expected) in cases {
+            let posts = testing::compile_posts(
+                &[("posts/post.md", &post("title: Post", content))],
+                &config,
+            );
+            assert_eq!(linked_pages(&posts[0], &config), expected, "{}", content);
+        }
+    }
+
+    #[test]
+    fn reports_links_to_pages_that_do_not_exist() {
+        let config = testing::config(CONFIG);
+        let posts = testing::compile_posts(
+            &[
+                (
+                    "posts/first.md",
+                    &post(
+                        "title: First",
+                        "[second](second.html), [index](/blog/) and [missing](missing.html)",
+                    ),
+                ),
+                (
+                    "posts/second.md",
+                    &post("title: Second", "[gone](../gone.html)"),
+                ),
+            ],
+            &config,
+        );
+        let pages: HashSet<String> = routes::collect_routes(&posts, &config)
+            .into_iter()
+            .map(|route| route.path)
+            .collect();
+
+        assert_eq!(
+            broken_links(&posts[0], &pages, &config),
+            vec!["missing.html"]
+        );
+        assert_eq!(broken_links(&posts[1], &pages, &config), vec!["gone.html"]);
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fs, io, path::Path};
+
+use serde_yaml::{Mapping, Value};
+
+/// File of a content directory with the default frontmatter of its posts,
+/// including the ones of its subdirectories
+pub const DEFAULTS_FILE: &str = "_defaults.yaml";
+
+/// Frontmatter that posts inherit from the `_defaults.yaml` files of their
+/// directory and of the ones above it, e.g: the tags or the authors shared by
+/// the posts of a section. Any field of the frontmatter can be given
+#[derive(Debug, Default)]
+pub struct FrontmatterDefaults {
+    fields: Mapping,
+}
+
+impl FrontmatterDefaults {
+    /// Loads the defaults of a post, from the content directory `root` down
+    /// to the post's own directory. Defaults of the directories closer to
+    /// the post take precedence
+    pub fn load(root: &Path, post: &Path) -> Result<FrontmatterDefaults, String> {
+        let post_dir = post.parent().unwrap_or(root);
+        let mut directories: Vec<&Path> = post_dir
+            .ancestors()
+            .take_while(|dir| dir.starts_with(root))
+            .collect();
+        directories.reverse();
+
+        let mut fields = Mapping::new();
+
+        for dir in directories {
+            let path = dir.join(DEFAULTS_FILE);
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            };
+
+            let defaults = match serde_yaml::from_str::<Value>(&content) {
+                Ok(Value::Null) => continue,
+                Ok(Value::Mapping(defaults)) => defaults,
+                Ok(_) => return Err(format!("{}: expected a mapping", path.display())),
+                Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            };
+            fields.extend(defaults);
+        }
+
+        Ok(FrontmatterDefaults { fields })
+    }
+
+    /// Adds the defaults that the post's frontmatter doesn't set
+    pub(crate) fn apply(&self, frontmatter: &mut Value) {
+        let frontmatter = match frontmatter.as_mapping_mut() {
+            Some(frontmatter) => frontmatter,
+            None => return,
+        };
+
+        for (key, value) in &self.fields {
+            if !frontmatter.contains_key(key) {
+                frontmatter.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
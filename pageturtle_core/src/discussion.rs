@@ -0,0 +1,67 @@
+use serde_json::Value;
+
+/// Site hosting the discussion of a post
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiscussionSite {
+    HackerNews,
+    Reddit,
+    Mastodon,
+}
+
+/// Where the comment count of a discussion thread can be fetched from
+#[derive(Debug, PartialEq)]
+pub struct CommentCountQuery {
+    site: DiscussionSite,
+    pub api_url: String,
+}
+
+impl CommentCountQuery {
+    /// Figures out the API that reports the comment count of a thread, for
+    /// the sites that are supported
+    pub fn from_discussion_url(url: &str) -> Option<CommentCountQuery> {
+        let url = url.trim_end_matches('/');
+        let (_scheme, rest) = url.split_once("://")?;
+        let (host, path) = rest.split_once('/')?;
+
+        if host.ends_with("news.ycombinator.com") {
+            let (_, id) = path.split_once("id=")?;
+            let id = id.split('&').next()?;
+
+            return Some(CommentCountQuery {
+                site: DiscussionSite::HackerNews,
+                api_url: format!("https://hacker-news.firebaseio.com/v0/item/{}.json", id),
+            });
+        }
+
+        if host.ends_with("reddit.com") && path.contains("comments/") {
+            return Some(CommentCountQuery {
+                site: DiscussionSite::Reddit,
+                api_url: format!("{}.json", url),
+            });
+        }
+
+        // Mastodon statuses look like https://instance/@user/<id>
+        let segments: Vec<&str> = path.split('/').collect();
+        if let [user, id] = segments[..] {
+            if user.starts_with('@') && id.chars().all(|c| c.is_ascii_digit()) {
+                return Some(CommentCountQuery {
+                    site: DiscussionSite::Mastodon,
+                    api_url: format!("https://{}/api/v1/statuses/{}", host, id),
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Reads the comment count from the API response
+    pub fn comment_count(&self, response: &Value) -> Option<u64> {
+        match self.site {
+            DiscussionSite::HackerNews => response["descendants"].as_u64(),
+            DiscussionSite::Reddit => {
+                response[0]["data"]["children"][0]["data"]["num_comments"].as_u64()
+            }
+            DiscussionSite::Mastodon => response["replies_count"].as_u64(),
+        }
+    }
+}
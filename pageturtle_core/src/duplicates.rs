@@ -0,0 +1,269 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use slug::slugify;
+
+use crate::blog::PublishableBlogPost;
+
+/// Words in each shingle, the overlapping runs of words that the posts are
+/// compared by
+const SHINGLE_WORDS: usize = 5;
+
+/// Hashes in the signature of each post. More hashes estimate the similarity
+/// of the posts more precisely
+const SIGNATURE_HASHES: u64 = 128;
+
+/// Estimated similarity from which the contents of two posts are considered
+/// the same, as imports often change the posts slightly, e.g: their markup
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// Reason for two posts to be considered duplicates of each other
+#[derive(Debug, PartialEq)]
+pub enum DuplicateReason {
+    SameTitle,
+    /// Share of the posts' contents that is the same, from 0 to 1
+    SimilarContent(f64),
+}
+
+/// Two posts that are likely the same, e.g: after importing posts from
+/// another blog
+#[derive(Debug)]
+pub struct Duplicate<'a> {
+    pub first: &'a PublishableBlogPost,
+    pub second: &'a PublishableBlogPost,
+    pub reason: DuplicateReason,
+}
+
+/// Finds the posts whose titles are the same, ignoring casing and
+/// punctuation, or whose contents are nearly identical. Contents are compared
+/// by MinHash signatures of their shingles, which estimate how many of the
+/// shingles two posts share without comparing all of them.
+pub fn find_duplicates(posts: &[PublishableBlogPost]) -> Vec<Duplicate> {
+    let titles: Vec<String> = posts
+        .iter()
+        .map(|p| slugify(&p.post.metadata.title))
+        .collect();
+    let signatures: Vec<Option<Vec<u64>>> =
+        posts.iter().map(|p| signature(&p.rendered_html)).collect();
+
+    let mut duplicates = Vec::new();
+
+    for i in 0..posts.len() {
+        for j in i + 1..posts.len() {
+            let reason = if titles[i] == titles[j] {
+                DuplicateReason::SameTitle
+            } else {
+                match (&signatures[i], &signatures[j]) {
+                    (Some(a), Some(b)) => match similarity(a, b) {
+                        s if s >= SIMILARITY_THRESHOLD => DuplicateReason::SimilarContent(s),
+                        _ => continue,
+                    },
+                    _ => continue,
+                }
+            };
+
+            duplicates.push(Duplicate {
+                first: &posts[i],
+                second: &posts[j],
+                reason,
+            });
+        }
+    }
+
+    duplicates
+}
+
+// Smallest hash of the post's shingles for each of the hash functions, or
+// `None` when the post has no text
+fn signature(html: &str) -> Option<Vec<u64>> {
+    let text = strip_tags(html).to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    // Posts shorter than a shingle are a single one
+    let shingles: Vec<u64> = words
+        .windows(SHINGLE_WORDS.min(words.len()))
+        .map(|shingle| hash(&shingle, 0))
+        .collect();
+
+    let signature = (0..SIGNATURE_HASHES)
+        .map(|seed| {
+            shingles
+                .iter()
+                .map(|shingle| hash(shingle, seed))
+                .min()
+                .unwrap()
+        })
+        .collect();
+
+    Some(signature)
+}
+
+// The share of hash functions whose smallest hash is the same for both
+// posts estimates the share of shingles that they have in common
+fn similarity(a: &[u64], b: &[u64]) -> f64 {
+    let same = a.iter().zip(b).filter(|(a, b)| a == b).count();
+    same as f64 / a.len() as f64
+}
+
+fn hash<T: Hash + ?Sized>(value: &T, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Text of the rendered HTML, with each tag replaced by a space
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing;
+
+    const TEXT: &str = "Shingles are the overlapping runs of words of a post, which are hashed \
+                        by several functions to keep the smallest hash of each one. Posts whose \
+                        smallest hashes are mostly the same share most of their shingles too. \
+                        Comparing these hashes is much faster than comparing every shingle of \
+                        both posts, and it is precise enough to tell apart the posts that were \
+                        imported twice from the ones that merely write about similar topics. \
+                        Changing a single word of a post changes only the few shingles that \
+                        contain it, so posts that were edited slightly after being imported, \
+                        e.g: to fix their links or their markup, are still found by the check \
+                        along with the ones that are exactly the same as each other.";
+
+    fn similarity_of(a: &str, b: &str) -> Option<f64> {
+        Some(similarity(&signature(a)?, &signature(b)?))
+    }
+
+    #[test]
+    fn strips_tags() {
+        let cases = [
+            ("<p>Some <em>text</em></p>", " Some  text  "),
+            ("a < b", "a "),
+            ("no tags", "no tags"),
+        ];
+
+        for (html, expected) in cases {
+            assert_eq!(strip_tags(html), expected, "{}", html);
+        }
+    }
+
+    #[test]
+    fn estimates_the_similarity_of_contents() {
+        let edited = TEXT.replace("several", "many");
+        let cases = [
+            (TEXT.to_owned(), Some(1.0)),
+            (format!("<p>{}</p>", TEXT.to_uppercase()), Some(1.0)),
+            (TEXT.replace(' ', "\n  "), Some(1.0)),
+            ("Something else entirely".to_owned(), Some(0.0)),
+            ("<p><img src=\"a.png\"></p>".to_owned(), None),
+        ];
+
+        for (html, expected) in cases {
+            let similarity = similarity_of(TEXT, &html);
+            match expected {
+                Some(expected) => {
+                    let similarity = similarity.unwrap();
+                    assert!(
+                        (similarity - expected).abs() < 0.05,
+                        "{} {}",
+                        similarity,
+                        html
+                    );
+                }
+                None => assert_eq!(similarity, None, "{}", html),
+            }
+        }
+
+        // A post changed slightly, e.g: when imported, is still the same
+        let similarity = similarity_of(TEXT, &edited).unwrap();
+        assert!(
+            (SIMILARITY_THRESHOLD..1.0).contains(&similarity),
+            "{}",
+            similarity
+        );
+    }
+
+    #[test]
+    fn finds_posts_with_the_same_title_or_content() {
+        let config = testing::config(
+            "blog_title = \"Imported blog\"\n\
+             author = \"Pageturtle\"\n\
+             base_url = \"https://example.com\"\n\
+             blog_description = \"Posts imported twice\"\n",
+        );
+        let post = |title: &str, content: &str| {
+            format!(
+                "---\ntitle: {}\ndate: 2023-05-20\n---\n\n{}\n",
+                title, content
+            )
+        };
+        let posts = testing::compile_posts(
+            &[
+                ("posts/first.md", &post("Hello, World!", TEXT)),
+                ("posts/second.md", &post("hello world", "Something else.")),
+                (
+                    "posts/imported.md",
+                    &post("Imported", &TEXT.replace("words", "*words*")),
+                ),
+                ("posts/other.md", &post("Other", "Unrelated text.")),
+                ("posts/empty.md", &post("Empty", "")),
+                ("posts/blank.md", &post("Blank", "")),
+            ],
+            &config,
+        );
+
+        let duplicates: Vec<(&Path, &Path, DuplicateReason)> = find_duplicates(&posts)
+            .into_iter()
+            .map(|d| {
+                (
+                    d.first.filepath.as_path(),
+                    d.second.filepath.as_path(),
+                    d.reason,
+                )
+            })
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![
+                (
+                    Path::new("posts/first.md"),
+                    Path::new("posts/second.md"),
+                    DuplicateReason::SameTitle
+                ),
+                (
+                    Path::new("posts/first.md"),
+                    Path::new("posts/imported.md"),
+                    DuplicateReason::SimilarContent(1.0)
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::Deserialize;
+
+use crate::{
+    blog::{format_date, BlogConfiguration, PostOrder, PublishableBlogPost},
+    utils::date,
+};
+
+/// Article published somewhere else, given in the `[[external_posts]]` tables
+/// of the configuration file, e.g:
+///
+/// ```toml
+/// [[external_posts]]
+/// title = "Writing a static site generator"
+/// url = "https://example.com/articles/static-site-generator"
+/// date = "2023-06-01"
+/// venue = "Example Magazine"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct ExternalPost {
+    pub title: String,
+    pub url: String,
+
+    #[serde(with = "date")]
+    pub date: NaiveDate,
+
+    /// Where the article was published
+    pub venue: Option<String>,
+
+    pub description: Option<String>,
+}
+
+impl ExternalPost {
+    pub fn format_date(&self) -> String {
+        format_date(self.date)
+    }
+
+    /// External posts are considered as published at midnight, in the
+    /// blog's UTC offset
+    pub fn published_at(&self, config: &BlogConfiguration) -> DateTime<FixedOffset> {
+        self.date
+            .and_time(Default::default())
+            .and_local_timezone(config.utc_offset)
+            .unwrap()
+    }
+}
+
+/// A post listed in the index, which is either published in the blog or
+/// somewhere else
+#[derive(Debug)]
+pub enum IndexEntry<'a> {
+    Post(&'a PublishableBlogPost),
+    External(&'a ExternalPost),
+}
+
+/// Merges the external posts into the posts of the index. They are placed by
+/// their date when the index is sorted by date, or after the posts otherwise
+pub fn index_entries<'a>(
+    posts: &'a [PublishableBlogPost],
+    config: &'a BlogConfiguration,
+) -> Vec<IndexEntry<'a>> {
+    let mut external: Vec<&ExternalPost> = config.external_posts.iter().collect();
+    external.sort_by_key(|e| std::cmp::Reverse(e.date));
+
+    let mut entries = Vec::with_capacity(posts.len() + external.len());
+    let mut external = external.into_iter().peekable();
+
+    for post in posts {
+        if config.sort_by == PostOrder::Date {
+            while let Some(e) = external.next_if(|e| e.date > post.post.metadata.date) {
+                entries.push(IndexEntry::External(e));
+            }
+        }
+
+        entries.push(IndexEntry::Post(post));
+    }
+
+    entries.extend(external.map(IndexEntry::External));
+    entries
+}
@@ -1,15 +1,44 @@
-use crate::blog::{BlogConfiguration, PublishableBlogPost};
-use chrono::{Datelike, NaiveDate, Utc};
+use crate::{
+    blog::{BlogConfiguration, PublishableBlogPost},
+    changelog::Release,
+    external::ExternalPost,
+    taxonomy::{self, TagEntry},
+};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+
+/// Version of the JSON Feed specification that JSON feeds follow
+const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
 
 #[derive(Debug)]
 pub struct FeedEntry<'a> {
     pub id: String,
     pub title: &'a str,
+    pub summary: &'a str,
     pub content: &'a str,
-    pub author: &'a str,
+    pub authors: Vec<&'a str>,
     /// RFC3339 formatted date
     pub updated: String,
     pub link: String,
+    pub discussion: Option<&'a str>,
+    pub comment_count: Option<u64>,
+    pub enclosure: Option<Enclosure>,
+    pub categories: Vec<Category<'a>>,
+}
+
+/// Tag of a feed entry
+#[derive(Debug)]
+pub struct Category<'a> {
+    pub term: String,
+    pub label: &'a str,
+}
+
+/// File attached to a feed entry, e.g: the audio version of a post
+#[derive(Debug)]
+pub struct Enclosure {
+    pub url: String,
+    pub mime_type: &'static str,
+    pub length: u64,
 }
 
 #[derive(Debug)]
@@ -20,45 +49,335 @@ pub struct Feed<'a> {
     /// RFC3339 formatted date
     pub updated: String,
     pub entries: Vec<FeedEntry<'a>>,
+    /// Links to the other pages of the feed, when it is split in pages
+    pub page: Option<FeedPage>,
+}
+
+/// Links of a page of a paged feed, as described by RFC 5005, so that
+/// readers can go through the entries that don't fit in the first page
+#[derive(Debug)]
+pub struct FeedPage {
+    pub url: String,
+    pub first: String,
+    pub last: String,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+pub fn build_feed<'a>(posts: &'a [PublishableBlogPost], config: &'a BlogConfiguration) -> Feed<'a> {
+    let mut entries: Vec<FeedEntry> = posts
+        .iter()
+        .filter(|p| p.post.metadata.feed)
+        .map(|p| to_entry(p, config))
+        .chain(
+            config
+                .external_posts
+                .iter()
+                .map(|e| external_entry(e, config)),
+        )
+        .collect();
+
+    // Feeds are always newest first, whatever the order of the index is. All
+    // entries share the blog's UTC offset, so their dates can be compared as
+    // text
+    entries.sort_by(|a, b| b.updated.cmp(&a.updated));
+
+    Feed {
+        author: &config.author,
+        title: &config.blog_title,
+        link: &config.base_url,
+        updated: last_updated(&entries),
+        entries,
+        page: None,
+    }
 }
 
-pub fn build_feed<'a>(
-    posts: &'a [PublishableBlogPost<'a>],
+/// Builds a feed with the posts of a section, e.g: for `notes/atom.xml`.
+/// Unlike the blog's feed, it doesn't list the external posts
+pub fn build_section_feed<'a>(
+    posts: &'a [PublishableBlogPost],
+    name: &'a str,
     config: &'a BlogConfiguration,
 ) -> Feed<'a> {
-    let entries = posts.iter().map(|p| to_entry(p, config)).collect();
+    let mut entries: Vec<FeedEntry> = posts
+        .iter()
+        .filter(|p| p.post.metadata.feed && p.section() == Some(name))
+        .map(|p| to_entry(p, config))
+        .collect();
+    entries.sort_by(|a, b| b.updated.cmp(&a.updated));
+
+    let title = config
+        .sections
+        .get(name)
+        .and_then(|section| section.title.as_deref())
+        .unwrap_or(name);
+
+    Feed {
+        author: &config.author,
+        title,
+        link: &config.base_url,
+        updated: last_updated(&entries),
+        entries,
+        page: None,
+    }
+}
+
+/// Builds a feed with the posts that carry a tag, e.g: for `tags/rust.xml`.
+/// Like the sections' feeds, it doesn't list the external posts
+pub fn build_tag_feed<'a>(
+    posts: &'a [PublishableBlogPost],
+    tag: &'a TagEntry<'a>,
+    config: &'a BlogConfiguration,
+) -> Feed<'a> {
+    let entries: Vec<FeedEntry> = taxonomy::tagged_posts(&tag.slug, posts, config)
+        .into_iter()
+        .filter(|p| p.post.metadata.feed)
+        .map(|p| to_entry(p, config))
+        .collect();
+
+    Feed {
+        author: &config.author,
+        title: tag.title,
+        link: &config.base_url,
+        updated: last_updated(&entries),
+        entries,
+        page: None,
+    }
+}
+
+/// Builds a feed with the changelog entries, linking to their release in the
+/// changelog page
+pub fn build_changelog_feed<'a>(
+    releases: &'a [Release<'a>],
+    config: &'a BlogConfiguration,
+) -> Feed<'a> {
+    let mut entries = Vec::new();
+
+    for release in releases {
+        for post in &release.entries {
+            let url = format!("{}/changelog.html#{}", config.base_url, release.anchor);
+
+            entries.push(FeedEntry {
+                id: format!("{}/{}", url, post.url_path()),
+                title: &post.post.metadata.title,
+                authors: config.post_authors(&post.post.metadata),
+                summary: &post.summary,
+                content: &post.rendered_html,
+                updated: published_at(post, config),
+                link: url,
+                discussion: post.post.metadata.discussion.as_deref(),
+                comment_count: post.comment_count,
+                enclosure: None,
+                categories: categories(post, config),
+            });
+        }
+    }
 
     Feed {
         author: &config.author,
         title: &config.blog_title,
         link: &config.base_url,
-        updated: rfc3339_date(Utc::now().naive_utc().date()),
+        updated: last_updated(&entries),
         entries,
+        page: None,
+    }
+}
+
+/// Splits a feed published at `path`, e.g: `atom.xml`, in pages of
+/// `page_size` entries. The first page, with the newest entries, keeps the
+/// path of the feed and the older ones are numbered after it, e.g:
+/// `atom-2.xml`. Returns the path of each page along with it
+pub fn paginate<'a>(
+    feed: Feed<'a>,
+    path: &str,
+    page_size: usize,
+    config: &BlogConfiguration,
+) -> Vec<(String, Feed<'a>)> {
+    let Feed {
+        title,
+        link,
+        author,
+        entries,
+        ..
+    } = feed;
+
+    let mut chunks: Vec<Vec<FeedEntry>> = vec![];
+    let mut entries = entries.into_iter().peekable();
+    while entries.peek().is_some() {
+        chunks.push(entries.by_ref().take(page_size.max(1)).collect());
+    }
+    if chunks.is_empty() {
+        chunks.push(vec![]);
+    }
+
+    let count = chunks.len();
+    let url = |n: usize| format!("{}/{}", config.base_url, page_path(path, n));
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, entries)| {
+            let n = i + 1;
+            // Feeds that fit in a single page are published as they are
+            let page = (count > 1).then(|| FeedPage {
+                url: url(n),
+                first: url(1),
+                last: url(count),
+                previous: (n > 1).then(|| url(n - 1)),
+                next: (n < count).then(|| url(n + 1)),
+            });
+
+            let feed = Feed {
+                title,
+                link,
+                author,
+                updated: last_updated(&entries),
+                entries,
+                page,
+            };
+            (page_path(path, n), feed)
+        })
+        .collect()
+}
+
+// Path of a page of a paged feed, e.g: `atom-2.xml` for the second page of
+// `atom.xml`
+fn page_path(path: &str, n: usize) -> String {
+    match (n, path.rsplit_once('.')) {
+        (1, _) => path.to_owned(),
+        (_, Some((stem, extension))) => format!("{}-{}.{}", stem, n, extension),
+        (_, None) => format!("{}-{}", path, n),
     }
 }
 
-fn to_entry<'a>(post: &'a PublishableBlogPost<'a>, config: &'a BlogConfiguration) -> FeedEntry<'a> {
-    let filename = post.output_filename.to_str().unwrap();
-    let url = format!("{}/{}", config.base_url, filename);
+/// Builds a JSON Feed, published as `feed.json`, with the same entries as
+/// the given feed
+pub fn build_json_feed(feed: &Feed, config: &BlogConfiguration) -> Value {
+    let items: Vec<Value> = feed
+        .entries
+        .iter()
+        .map(|entry| {
+            let mut item = Map::new();
+            item.insert("id".into(), json!(entry.id));
+            item.insert("url".into(), json!(entry.link));
+            item.insert("title".into(), json!(entry.title));
 
+            // External posts only have their description
+            match entry.content.is_empty() {
+                true => item.insert("content_text".into(), json!(entry.summary)),
+                false => item.insert("content_html".into(), json!(entry.content)),
+            };
+            if !entry.summary.is_empty() {
+                item.insert("summary".into(), json!(entry.summary));
+            }
+
+            item.insert("date_published".into(), json!(entry.updated));
+            item.insert("authors".into(), json_authors(&entry.authors));
+
+            if !entry.categories.is_empty() {
+                let tags: Vec<&str> = entry.categories.iter().map(|c| c.label).collect();
+                item.insert("tags".into(), json!(tags));
+            }
+
+            if let Some(ref enclosure) = entry.enclosure {
+                item.insert(
+                    "attachments".into(),
+                    json!([{
+                        "url": enclosure.url,
+                        "mime_type": enclosure.mime_type,
+                        "size_in_bytes": enclosure.length,
+                    }]),
+                );
+            }
+
+            Value::Object(item)
+        })
+        .collect();
+
+    json!({
+        "version": JSON_FEED_VERSION,
+        "title": feed.title,
+        "home_page_url": format!("{}/", feed.link),
+        "feed_url": format!("{}/feed.json", config.base_url),
+        "authors": json_authors(&[feed.author]),
+        "items": items,
+    })
+}
+
+fn json_authors(names: &[&str]) -> Value {
+    names.iter().map(|name| json!({ "name": name })).collect()
+}
+
+fn to_entry<'a>(post: &'a PublishableBlogPost, config: &'a BlogConfiguration) -> FeedEntry<'a> {
     FeedEntry {
-        id: url.to_owned(),
+        id: post.url(config),
         title: &post.post.metadata.title,
-        author: &config.author, // TODO: use post author if set
+        authors: config.post_authors(&post.post.metadata),
+        summary: &post.summary,
         content: &post.rendered_html,
-        updated: rfc3339_date(post.post.metadata.date),
-        link: url,
+        updated: published_at(post, config),
+        link: post.canonical_url.clone(),
+        discussion: post.post.metadata.discussion.as_deref(),
+        comment_count: post.comment_count,
+        enclosure: post.audio.as_ref().map(|audio| Enclosure {
+            url: format!("{}{}", config.base_url, audio.url()),
+            mime_type: audio.mime_type,
+            length: audio.length,
+        }),
+        categories: categories(post, config),
     }
 }
 
-fn rfc3339_date(date: NaiveDate) -> String {
-    format!(
-        "{}-{:02}-{:02}T{:02}:{:02}:{:02}+00:00",
-        date.year(),
-        date.month(),
-        date.day(),
-        0,
-        0,
-        0
-    )
+// External posts link to where they were published, as they have no content
+// in the blog
+fn external_entry<'a>(post: &'a ExternalPost, config: &'a BlogConfiguration) -> FeedEntry<'a> {
+    FeedEntry {
+        id: post.url.clone(),
+        title: &post.title,
+        authors: vec![&config.author],
+        summary: post.description.as_deref().unwrap_or_default(),
+        content: "",
+        updated: post
+            .published_at(config)
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        link: post.url.clone(),
+        discussion: None,
+        comment_count: None,
+        enclosure: None,
+        categories: vec![],
+    }
+}
+
+fn categories<'a>(
+    post: &'a PublishableBlogPost,
+    config: &'a BlogConfiguration,
+) -> Vec<Category<'a>> {
+    post.post
+        .metadata
+        .tags
+        .iter()
+        .map(|tag| Category {
+            term: config.tags.slug(tag),
+            label: config.tags.title(tag),
+        })
+        .collect()
+}
+
+fn published_at(post: &PublishableBlogPost, config: &BlogConfiguration) -> String {
+    post.post
+        .metadata
+        .published_at(config)
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// The feed is updated when its latest entry is published, so that it doesn't
+// change between builds when there are no new entries. All entries share the
+// blog's UTC offset, so their dates can be compared as text
+fn last_updated(entries: &[FeedEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| e.updated.as_str())
+        .max()
+        .map(str::to_owned)
+        .unwrap_or_else(|| Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true))
 }
@@ -0,0 +1,149 @@
+use serde::Deserialize;
+
+use crate::utils::escape_html;
+
+const DEFAULT_BACKREF: &str = "↩";
+const SECTION_START: &str = "<section class=\"footnotes\" data-footnotes>";
+const SECTION_END: &str = "</section>";
+const ITEM_START: &str = "<li id=\"fn-";
+
+/// Settings of the `[footnotes]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct FootnoteConfiguration {
+    /// Text of the links that go back from a footnote to its reference
+    #[serde(default = "default_backref")]
+    pub backref_symbol: String,
+
+    /// Shows the footnotes in popups when hovering their references
+    #[serde(default)]
+    pub popups: bool,
+
+    #[serde(default)]
+    pub placement: FootnotePlacement,
+}
+
+impl Default for FootnoteConfiguration {
+    fn default() -> Self {
+        FootnoteConfiguration {
+            backref_symbol: default_backref(),
+            popups: false,
+            placement: FootnotePlacement::default(),
+        }
+    }
+}
+
+fn default_backref() -> String {
+    DEFAULT_BACKREF.to_owned()
+}
+
+/// Where the footnotes are listed in the post
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FootnotePlacement {
+    #[default]
+    EndOfPost,
+    /// After the section, delimited by second level headings, where they
+    /// are referenced
+    EndOfSection,
+}
+
+/// Applies the footnote settings to a post rendered by comrak
+pub fn render(html: String, config: &FootnoteConfiguration) -> String {
+    let html = if config.backref_symbol != DEFAULT_BACKREF {
+        html.replace(
+            &format!(">{}</a>", DEFAULT_BACKREF),
+            &format!(">{}</a>", escape_html(&config.backref_symbol)),
+        )
+    } else {
+        html
+    };
+
+    match config.placement {
+        FootnotePlacement::EndOfPost => html,
+        FootnotePlacement::EndOfSection => place_at_end_of_sections(&html).unwrap_or(html),
+    }
+}
+
+// Splits the footnotes listed at the end of the post between the sections
+// that reference them
+fn place_at_end_of_sections(html: &str) -> Option<String> {
+    let start = html.rfind(SECTION_START)?;
+    let end = start + html[start..].find(SECTION_END)? + SECTION_END.len();
+    let body = format!("{}{}", &html[..start], &html[end..]);
+
+    let list = &html[start..end];
+    let list_end = list.rfind("</ol>")?;
+    let item_starts: Vec<usize> = list[..list_end]
+        .match_indices(ITEM_START)
+        .map(|(i, _)| i)
+        .collect();
+    let mut notes: Vec<(&str, &str)> = item_starts
+        .iter()
+        .enumerate()
+        .map(|(n, &i)| {
+            let item = &list[i..*item_starts.get(n + 1).unwrap_or(&list_end)];
+            let id = item[ITEM_START.len()..]
+                .split('"')
+                .next()
+                .unwrap_or_default();
+            (id, item)
+        })
+        .collect();
+
+    let mut output = String::with_capacity(html.len());
+    let mut number = 1;
+
+    for section in split_sections(&body) {
+        output.push_str(section);
+
+        let mut section_notes = Vec::new();
+        notes.retain(|(id, item)| {
+            let referenced = section.contains(&format!("href=\"#fn-{}\"", id));
+            if referenced {
+                section_notes.push(*item);
+            }
+            !referenced
+        });
+
+        push_footnotes(&mut output, number, &section_notes);
+        number += section_notes.len();
+    }
+
+    // Footnotes that are never referenced are still listed at the end
+    let unreferenced: Vec<&str> = notes.into_iter().map(|(_, item)| item).collect();
+    push_footnotes(&mut output, number, &unreferenced);
+
+    Some(output)
+}
+
+fn push_footnotes(output: &mut String, start: usize, items: &[&str]) {
+    if items.is_empty() {
+        return;
+    }
+
+    output.push_str(&format!(
+        "{}\n<ol start=\"{}\">\n{}</ol>\n{}\n",
+        SECTION_START,
+        start,
+        items.concat(),
+        SECTION_END
+    ));
+}
+
+// Splits the post right before each of its second level headings, including
+// the link wrapping them
+fn split_sections(html: &str) -> Vec<&str> {
+    let mut boundaries: Vec<usize> = html
+        .match_indices("<h2 ")
+        .map(|(i, _)| {
+            html[..i]
+                .rfind("<a class=\"no-underline\"")
+                .filter(|&a| !html[a..i].contains("</a>"))
+                .unwrap_or(i)
+        })
+        .collect();
+    boundaries.insert(0, 0);
+    boundaries.push(html.len());
+
+    boundaries.windows(2).map(|w| &html[w[0]..w[1]]).collect()
+}
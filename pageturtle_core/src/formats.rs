@@ -0,0 +1,617 @@
+use serde_yaml::{Mapping, Value};
+
+use crate::{blog::CompilePostError, notebooks};
+
+/// Markup languages that posts can be written in. Posts that aren't written
+/// in markdown are converted to it, so that they go through the same
+/// pipeline, e.g: syntax highlighting and footnotes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceFormat {
+    Markdown,
+    Org,
+    AsciiDoc,
+    /// Jupyter notebooks
+    Notebook,
+}
+
+impl SourceFormat {
+    /// Format of a post from the extension of its file, if it is supported
+    pub fn from_extension(extension: &str) -> Option<SourceFormat> {
+        match extension {
+            "md" | "markdown" => Some(SourceFormat::Markdown),
+            "org" => Some(SourceFormat::Org),
+            "adoc" | "asciidoc" => Some(SourceFormat::AsciiDoc),
+            "ipynb" => Some(SourceFormat::Notebook),
+            _ => None,
+        }
+    }
+
+    /// Converts a post to markdown, with its metadata as frontmatter
+    pub fn to_markdown(self, content: &str) -> Result<String, CompilePostError> {
+        match self {
+            SourceFormat::Markdown => Ok(content.to_owned()),
+            SourceFormat::Org => Ok(org_to_markdown(content)),
+            SourceFormat::AsciiDoc => Ok(asciidoc_to_markdown(content)),
+            SourceFormat::Notebook => {
+                notebooks::to_markdown(content).map_err(|e| CompilePostError {
+                    line: e.line() as u32,
+                    column: e.column() as u32,
+                    message: e.to_string(),
+                })
+            }
+        }
+    }
+}
+
+// Converts the subset of Org used in posts: `#+KEY: value` metadata,
+// headings, lists, source, example and quote blocks, emphasis and links
+fn org_to_markdown(content: &str) -> String {
+    let mut metadata = Vec::new();
+    let mut body = String::with_capacity(content.len());
+    let mut in_header = true;
+    let mut block: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let keyword = trimmed
+            .strip_prefix("#+")
+            .and_then(|k| k.split_once(':'))
+            .map(|(key, value)| (key.to_ascii_lowercase(), value.trim()));
+
+        if let Some(kind) = &block {
+            if trimmed.eq_ignore_ascii_case(&format!("#+end_{}", kind)) {
+                body.push_str(if kind == "quote" { "\n" } else { "```\n" });
+                block = None;
+            } else if kind == "quote" {
+                body.push_str(&format!("> {}\n", org_inline(trimmed)));
+            } else {
+                body.push_str(line);
+                body.push('\n');
+            }
+            continue;
+        }
+
+        let block_start = trimmed
+            .get(..8)
+            .filter(|start| start.eq_ignore_ascii_case("#+begin_"))
+            .map(|_| &trimmed[8..]);
+        if let Some(start) = block_start {
+            let (kind, arguments) = start.split_once(' ').unwrap_or((start, ""));
+            let kind = kind.to_ascii_lowercase();
+            match kind.as_str() {
+                "src" => {
+                    let language = arguments.split_whitespace().next().unwrap_or_default();
+                    body.push_str(&format!("```{}\n", language));
+                }
+                "quote" => {}
+                _ => body.push_str("```\n"),
+            }
+            block = Some(kind);
+            in_header = false;
+            continue;
+        }
+
+        match keyword {
+            Some((key, value)) if in_header => {
+                metadata.push((key, value.to_owned()));
+                continue;
+            }
+            // Other keywords, e.g: `#+OPTIONS`, only matter to Org's exporter
+            Some(_) => continue,
+            None => {}
+        }
+
+        // Comments
+        if trimmed == "#" || trimmed.starts_with("# ") {
+            continue;
+        }
+
+        if !trimmed.is_empty() {
+            in_header = false;
+        }
+
+        let level = line.chars().take_while(|&c| c == '*').count();
+        if level > 0 && line[level..].starts_with(' ') {
+            let title = line[level..].trim();
+            body.push_str(&format!("{} {}\n", "#".repeat(level), org_inline(title)));
+        } else if let Some(item) = line.trim_start().strip_prefix("+ ") {
+            let indent = line.len() - line.trim_start().len();
+            body.push_str(&format!("{}- {}\n", &line[..indent], org_inline(item)));
+        } else {
+            body.push_str(&org_inline(line));
+            body.push('\n');
+        }
+    }
+
+    // A block that is never closed runs until the end of the post
+    match block.as_deref() {
+        Some("quote") | None => {}
+        Some(_) => body.push_str("```\n"),
+    }
+
+    with_frontmatter(metadata, &body)
+}
+
+fn org_inline(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("[[") {
+        let end = match rest[start..].find("]]") {
+            Some(end) => start + end,
+            None => break,
+        };
+        output.push_str(&org_emphasis(&rest[..start]));
+
+        let link = &rest[start + 2..end];
+        let (target, description) = match link.split_once("][") {
+            Some((target, description)) => (target, Some(description)),
+            None => (link, None),
+        };
+        let target = target.strip_prefix("file:").unwrap_or(target);
+
+        output.push_str(&match description {
+            Some(description) => format!("[{}]({})", org_emphasis(description), target),
+            // Links without a description to images are shown inline
+            None if is_image(target) => format!("![]({})", target),
+            None => format!("<{}>", target),
+        });
+        rest = &rest[end + 2..];
+    }
+
+    output.push_str(&org_emphasis(rest));
+    output
+}
+
+fn org_emphasis(text: &str) -> String {
+    let text = convert_emphasis(text, '=', "`");
+    let text = convert_emphasis(&text, '~', "`");
+    let text = convert_emphasis(&text, '*', "**");
+    let text = convert_emphasis(&text, '/', "*");
+    convert_emphasis(&text, '+', "~~")
+}
+
+// Converts the subset of AsciiDoc used in posts: the document header and its
+// attributes, sections, lists, listing and quote blocks, emphasis, links and
+// images
+fn asciidoc_to_markdown(content: &str) -> String {
+    let mut metadata = Vec::new();
+    let mut body = String::with_capacity(content.len());
+    let mut lines = content.lines().peekable();
+
+    // The header is the title followed by attributes, e.g: `:date: 2023-01-01`,
+    // up to the first blank line
+    while lines.peek().is_some_and(|l| l.starts_with("//")) {
+        lines.next();
+    }
+    if let Some(title) = lines.peek().and_then(|l| l.strip_prefix("= ")) {
+        metadata.push(("title".to_owned(), title.trim().to_owned()));
+        lines.next();
+
+        while let Some(line) = lines.next_if(|l| !l.trim().is_empty()) {
+            let attribute = line
+                .strip_prefix(':')
+                .and_then(|l| l.split_once(':'))
+                .map(|(key, value)| (key.to_ascii_lowercase(), value.trim().to_owned()));
+
+            // The line after the title without a colon lists the authors
+            match attribute {
+                Some((key, value)) if key == "revdate" => metadata.push(("date".to_owned(), value)),
+                Some(attribute) => metadata.push(attribute),
+                None if !line.starts_with("//") => {
+                    metadata.push(("authors".to_owned(), line.trim().to_owned()))
+                }
+                None => {}
+            }
+        }
+    }
+
+    let mut language: Option<String> = None;
+    let mut delimiter: Option<&str> = None;
+
+    for line in lines {
+        let trimmed = line.trim_end();
+
+        if let Some(open) = delimiter {
+            if trimmed == open {
+                body.push_str(if open == "____" { "\n" } else { "```\n" });
+                delimiter = None;
+            } else if open == "____" {
+                body.push_str(&format!("> {}\n", asciidoc_inline(trimmed)));
+            } else {
+                body.push_str(line);
+                body.push('\n');
+            }
+            continue;
+        }
+
+        match trimmed {
+            "----" | "...." => {
+                body.push_str(&format!("```{}\n", language.take().unwrap_or_default()));
+                delimiter = Some(trimmed);
+                continue;
+            }
+            "____" => {
+                delimiter = Some("____");
+                continue;
+            }
+            _ => {}
+        }
+
+        // Block attributes, e.g: `[source,rust]`, apply to the next block
+        if trimmed.starts_with('[') && trimmed.ends_with(']') && !trimmed.contains("](") {
+            let attributes: Vec<&str> = trimmed[1..trimmed.len() - 1].split(',').collect();
+            if attributes[0] == "source" {
+                language = attributes.get(1).map(|l| l.trim().to_owned());
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("//") {
+            continue;
+        }
+
+        if let Some(image) = trimmed.strip_prefix("image::") {
+            body.push_str(&asciidoc_image(image));
+            body.push('\n');
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|&c| c == '=').count();
+        let bullets = trimmed.chars().take_while(|&c| c == '*').count();
+        let numbers = trimmed.chars().take_while(|&c| c == '.').count();
+
+        if level > 0 && trimmed[level..].starts_with(' ') {
+            let title = asciidoc_inline(trimmed[level..].trim());
+            body.push_str(&format!("{} {}\n", "#".repeat(level), title));
+        } else if bullets > 0 && trimmed[bullets..].starts_with(' ') {
+            let item = asciidoc_inline(trimmed[bullets..].trim());
+            body.push_str(&format!("{}- {}\n", "  ".repeat(bullets - 1), item));
+        } else if numbers > 0 && trimmed[numbers..].starts_with(' ') {
+            let item = asciidoc_inline(trimmed[numbers..].trim());
+            body.push_str(&format!("{}1. {}\n", "   ".repeat(numbers - 1), item));
+        } else {
+            body.push_str(&asciidoc_inline(line));
+            body.push('\n');
+        }
+    }
+
+    if delimiter.is_some_and(|d| d != "____") {
+        body.push_str("```\n");
+    }
+
+    with_frontmatter(metadata, &body)
+}
+
+fn asciidoc_inline(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    // Links are written as the URL followed by the text in brackets, e.g:
+    // `https://example.com[Example]` or `link:other-post.html[Other post]`
+    while let Some((start, end)) = find_asciidoc_macro(rest) {
+        output.push_str(&asciidoc_emphasis(&rest[..start]));
+
+        let link = &rest[start..end];
+        let (target, text) = link[..link.len() - 1].split_once('[').unwrap();
+        output.push_str(&match target.strip_prefix("image:") {
+            Some(image) => format!("![{}]({})", text, image),
+            None => {
+                let target = target.strip_prefix("link:").unwrap_or(target);
+                match text {
+                    "" => format!("<{}>", target),
+                    text => format!("[{}]({})", asciidoc_emphasis(text), target),
+                }
+            }
+        });
+        rest = &rest[end..];
+    }
+
+    output.push_str(&asciidoc_emphasis(rest));
+    output
+}
+
+// Start and end of the first link or inline image of the text
+fn find_asciidoc_macro(text: &str) -> Option<(usize, usize)> {
+    ["https://", "http://", "mailto:", "link:", "image:"]
+        .iter()
+        .filter_map(|prefix| {
+            // Bare URLs, e.g: `https://example.com`, are left as they are
+            text.match_indices(prefix).find_map(|(start, _)| {
+                let length = text[start..].find(|c: char| c.is_whitespace() || c == '[')?;
+                if !text[start + length..].starts_with('[') {
+                    return None;
+                }
+                let end = start + length + text[start + length..].find(']')? + 1;
+                Some((start, end))
+            })
+        })
+        .min()
+}
+
+fn asciidoc_image(image: &str) -> String {
+    match image
+        .trim_end()
+        .strip_suffix(']')
+        .and_then(|i| i.split_once('['))
+    {
+        Some((path, attributes)) => {
+            let alt = attributes.split(',').next().unwrap_or_default();
+            format!("![{}]({})", alt, path)
+        }
+        None => format!("![]({})", image.trim()),
+    }
+}
+
+fn asciidoc_emphasis(text: &str) -> String {
+    let text = convert_emphasis(text, '*', "**");
+    convert_emphasis(&text, '_', "*")
+}
+
+// Replaces the spans delimited by `marker`, e.g: `*bold*`, with the markdown
+// delimiter. Markers only count at the boundaries of words, so that e.g: the
+// slashes of URLs aren't taken as emphasis
+fn convert_emphasis(text: &str, marker: char, replacement: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let opens = chars[i] == marker
+            && (i == 0 || chars[i - 1].is_whitespace() || "([{\"'".contains(chars[i - 1]))
+            && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        let end = (i + 2..chars.len()).filter(|_| opens).find(|&j| {
+            chars[j] == marker
+                && !chars[j - 1].is_whitespace()
+                && chars
+                    .get(j + 1)
+                    .is_none_or(|&c| c.is_whitespace() || ")]}\"'.,;:!?".contains(c))
+        });
+
+        match end {
+            Some(end) => {
+                output.push_str(replacement);
+                output.extend(&chars[i + 1..end]);
+                output.push_str(replacement);
+                i = end + 1;
+            }
+            None => {
+                output.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+
+    output
+}
+
+fn is_image(path: &str) -> bool {
+    let extension = path.rsplit('.').next().unwrap_or_default();
+    ["png", "jpg", "jpeg", "gif", "webp", "svg", "avif"].contains(&extension)
+}
+
+// Writes the metadata of a post as the frontmatter of its markdown. Dates
+// are reduced to the day, e.g: Org's `<2023-01-01 Sun>`, and tags may be
+// separated by colons, commas or spaces
+fn with_frontmatter(metadata: Vec<(String, String)>, body: &str) -> String {
+    let mut frontmatter = Mapping::new();
+
+    for (key, value) in metadata {
+        let value = match key.as_str() {
+            "date" => {
+                let date = value.trim_matches(['<', '>', '[', ']']);
+                Value::from(date.split_whitespace().next().unwrap_or_default())
+            }
+            "draft" => Value::from(["t", "true", "yes"].contains(&value.as_str())),
+            "tags" | "filetags" | "keywords" | "author" | "authors" => {
+                let separators: &[char] = match key.as_str() {
+                    "author" | "authors" => &[';', ','],
+                    _ => &[':', ',', ' '],
+                };
+                Value::Sequence(
+                    value
+                        .split(separators)
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                        .map(Value::from)
+                        .collect(),
+                )
+            }
+            _ => Value::from(value),
+        };
+
+        let key = match key.as_str() {
+            "filetags" | "keywords" => "tags".to_owned(),
+            "author" => "authors".to_owned(),
+            _ => key,
+        };
+        frontmatter.insert(Value::from(key), value);
+    }
+
+    format!(
+        "---\n{}---\n\n{}",
+        serde_yaml::to_string(&frontmatter).unwrap(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(format: SourceFormat, content: &str) -> String {
+        format.to_markdown(content).unwrap()
+    }
+
+    #[test]
+    fn detects_the_format_from_the_extension() {
+        let cases = [
+            ("md", Some(SourceFormat::Markdown)),
+            ("markdown", Some(SourceFormat::Markdown)),
+            ("org", Some(SourceFormat::Org)),
+            ("adoc", Some(SourceFormat::AsciiDoc)),
+            ("asciidoc", Some(SourceFormat::AsciiDoc)),
+            ("ipynb", Some(SourceFormat::Notebook)),
+            ("txt", None),
+            ("MD", None),
+        ];
+
+        for (extension, expected) in cases {
+            assert_eq!(
+                SourceFormat::from_extension(extension),
+                expected,
+                "{}",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn converts_emphasis_at_word_boundaries() {
+        let cases = [
+            ("*bold*", '*', "**", "**bold**"),
+            ("a /word/ b", '/', "*", "a *word* b"),
+            ("(*bold*), *bold*.", '*', "**", "(**bold**), **bold**."),
+            (
+                "https://example.com/a/b",
+                '/',
+                "*",
+                "https://example.com/a/b",
+            ),
+            ("2 * 3 * 4", '*', "**", "2 * 3 * 4"),
+            ("snake_case_name", '_', "*", "snake_case_name"),
+            ("*never closed", '*', "**", "*never closed"),
+        ];
+
+        for (text, marker, replacement, expected) in cases {
+            assert_eq!(
+                convert_emphasis(text, marker, replacement),
+                expected,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn converts_org_markup() {
+        let cases = [
+            (
+                "[[https://example.com][The site]]",
+                "[The site](https://example.com)",
+            ),
+            ("[[https://example.com]]", "<https://example.com>"),
+            ("[[file:images/cat.png]]", "![](images/cat.png)"),
+            ("[[file:notes.pdf]]", "<notes.pdf>"),
+            ("=code= and ~verbatim~", "`code` and `verbatim`"),
+            (
+                "*bold*, /italic/ and +struck+",
+                "**bold**, *italic* and ~~struck~~",
+            ),
+            (
+                "[[https://example.com][/The/ site]] /after/",
+                "[*The* site](https://example.com) *after*",
+            ),
+            ("[[never closed", "[[never closed"),
+        ];
+
+        for (org, expected) in cases {
+            assert_eq!(org_inline(org), expected, "{}", org);
+        }
+    }
+
+    #[test]
+    fn converts_org_posts() {
+        let cases = [
+            (
+                "#+TITLE: A post\n#+DATE: <2023-01-05 Thu>\n#+FILETAGS: :rust:web:\n\n* Heading\n** Subheading\nText.\n",
+                "---\ntitle: A post\ndate: 2023-01-05\ntags:\n- rust\n- web\n---\n\n\n# Heading\n## Subheading\nText.\n",
+            ),
+            (
+                "#+title: Draft\n#+draft: t\n#+author: Jane Doe, John Roe\n",
+                "---\ntitle: Draft\ndraft: true\nauthors:\n- Jane Doe\n- John Roe\n---\n\n",
+            ),
+            // Keywords after the header, e.g: `#+OPTIONS`, and comments are dropped
+            (
+                "Text.\n#+OPTIONS: toc:nil\n# A comment\n+ item\n  + nested\n",
+                "---\n{}\n---\n\nText.\n- item\n  - nested\n",
+            ),
+            (
+                "#+BEGIN_SRC rust :results none\nlet a = *b*;\n#+END_SRC\n",
+                "---\n{}\n---\n\n```rust\nlet a = *b*;\n```\n",
+            ),
+            (
+                "#+begin_quote\nSome /wise/ words\n#+end_quote\n",
+                "---\n{}\n---\n\n> Some *wise* words\n\n",
+            ),
+            (
+                "#+BEGIN_EXAMPLE\nnever closed\n",
+                "---\n{}\n---\n\n```\nnever closed\n```\n",
+            ),
+        ];
+
+        for (org, expected) in cases {
+            assert_eq!(markdown(SourceFormat::Org, org), expected, "{}", org);
+        }
+    }
+
+    #[test]
+    fn converts_asciidoc_markup() {
+        let cases = [
+            (
+                "https://example.com[The site]",
+                "[The site](https://example.com)",
+            ),
+            (
+                "link:other.html[*Other* post]",
+                "[**Other** post](other.html)",
+            ),
+            (
+                "mailto:someone@example.com[]",
+                "<mailto:someone@example.com>",
+            ),
+            ("image:cat.png[A cat]", "![A cat](cat.png)"),
+            (
+                "See https://example.com for more",
+                "See https://example.com for more",
+            ),
+            ("*bold* and _italic_", "**bold** and *italic*"),
+        ];
+
+        for (asciidoc, expected) in cases {
+            assert_eq!(asciidoc_inline(asciidoc), expected, "{}", asciidoc);
+        }
+    }
+
+    #[test]
+    fn converts_asciidoc_posts() {
+        let cases = [
+            (
+                "= A post\nJane Doe; John Roe\n:revdate: 2023-01-05\n:tags: rust, web\n\n== Section\nText.\n",
+                "---\ntitle: A post\nauthors:\n- Jane Doe\n- John Roe\ndate: 2023-01-05\ntags:\n- rust\n- web\n---\n\n\n## Section\nText.\n",
+            ),
+            (
+                "// A comment\n= Titled\n\n[source,rust]\n----\nlet a = *b*;\n----\n",
+                "---\ntitle: Titled\n---\n\n\n```rust\nlet a = *b*;\n```\n",
+            ),
+            (
+                "* item\n** nested\n. first\n.. second\n",
+                "---\n{}\n---\n\n- item\n  - nested\n1. first\n   1. second\n",
+            ),
+            (
+                "image::images/cat.png[A cat,200]\n____\nSome _wise_ words\n____\n",
+                "---\n{}\n---\n\n![A cat](images/cat.png)\n> Some *wise* words\n\n",
+            ),
+            ("....\nnever closed\n", "---\n{}\n---\n\n```\nnever closed\n```\n"),
+        ];
+
+        for (asciidoc, expected) in cases {
+            assert_eq!(
+                markdown(SourceFormat::AsciiDoc, asciidoc),
+                expected,
+                "{}",
+                asciidoc
+            );
+        }
+    }
+}
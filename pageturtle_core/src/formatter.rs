@@ -0,0 +1,308 @@
+use crate::sanitizer::{parse_tag, Tag};
+
+const INDENT: &str = "  ";
+
+/// Elements that start on a line of their own, nesting their contents.
+/// Whitespace around them isn't rendered, so it can be changed freely
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "blockquote",
+    "body",
+    "details",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "noscript",
+    "ol",
+    "p",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// Elements that never have contents
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose contents are kept as they are, as their whitespace matters
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "script", "style", "textarea"];
+
+/// Formats a page so that it changes as little as possible between builds:
+/// block elements are put on lines of their own and indented by how nested
+/// they are, attributes are sorted by their names and quoted with double
+/// quotes, and runs of whitespace in text are collapsed. The contents of
+/// elements such as `pre` and `script` are kept as they are.
+pub fn format_html(html: &str) -> String {
+    let mut formatter = Formatter::default();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        formatter.push_text(&rest[..start]);
+        rest = &rest[start..];
+
+        // Comments and the doctype are kept as they are, on lines of their
+        // own
+        if rest.starts_with("<!") {
+            let end = match rest.starts_with("<!--") {
+                true => rest.find("-->").map_or(rest.len(), |i| i + 3),
+                false => rest.find('>').map_or(rest.len(), |i| i + 1),
+            };
+            formatter.push_line(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        let tag = match parse_tag(rest) {
+            Some(tag) => tag,
+            // e.g: `a < b`
+            None => {
+                formatter.push_text("<");
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        rest = &rest[tag.length..];
+
+        if !tag.closing && PREFORMATTED_ELEMENTS.contains(&tag.name.as_str()) {
+            let closing = format!("</{}", tag.name);
+            let end = rest
+                .to_ascii_lowercase()
+                .find(&closing)
+                .unwrap_or(rest.len());
+            let closing_end = rest[end..].find('>').map_or(rest.len(), |i| end + i + 1);
+
+            // The element may be inline, e.g: `pre` inside of a list item,
+            // so it continues the current line
+            formatter.push_inline(&format!(
+                "{}{}{}",
+                format_tag(&tag),
+                &rest[..end],
+                &rest[end..closing_end]
+            ));
+            rest = &rest[closing_end..];
+        } else {
+            formatter.push_tag(&tag);
+        }
+    }
+
+    formatter.push_text(rest);
+    formatter.finish()
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    /// Inline contents of the line being written
+    line: String,
+    depth: usize,
+}
+
+impl Formatter {
+    fn push_tag(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+        if !BLOCK_ELEMENTS.contains(&name) {
+            self.push_inline(&format_tag(tag));
+            return;
+        }
+
+        if tag.closing {
+            // The contents are written at the depth of the element
+            self.flush();
+            self.depth = self.depth.saturating_sub(1);
+            self.push_line(&format_tag(tag));
+        } else {
+            self.push_line(&format_tag(tag));
+            if !tag.self_closing && !VOID_ELEMENTS.contains(&name) {
+                self.depth += 1;
+            }
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        let ends_with_space = text.ends_with(char::is_whitespace);
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        if starts_with_space && !self.line.is_empty() && !self.line.ends_with(' ') {
+            self.line.push(' ');
+        }
+        self.line.push_str(&words.join(" "));
+        if ends_with_space && !words.is_empty() {
+            self.line.push(' ');
+        }
+    }
+
+    fn push_inline(&mut self, html: &str) {
+        self.line.push_str(html);
+    }
+
+    fn push_line(&mut self, html: &str) {
+        self.flush();
+        self.output.push_str(&INDENT.repeat(self.depth));
+        self.output.push_str(html);
+        self.output.push('\n');
+    }
+
+    // Writes the line being built, if it has anything besides whitespace
+    fn flush(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+
+        // Preformatted contents that span several lines are not indented,
+        // as that would change them
+        if !line.contains('\n') {
+            self.output.push_str(&INDENT.repeat(self.depth));
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.output
+    }
+}
+
+fn format_tag(tag: &Tag) -> String {
+    if tag.closing {
+        return format!("</{}>", tag.name);
+    }
+
+    let mut attributes: Vec<&(String, Option<String>)> = tag.attributes.iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut html = format!("<{}", tag.name);
+    for (name, value) in attributes {
+        match value {
+            Some(value) => {
+                html.push_str(&format!(" {}=\"{}\"", name, value.replace('"', "&quot;")))
+            }
+            None => html.push_str(&format!(" {}", name)),
+        }
+    }
+
+    html.push_str(if tag.self_closing { " />" } else { ">" });
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_pages() {
+        let cases = [
+            (
+                "<div><p>Some  <em>text</em>\n here</p></div>",
+                "<div>\n  <p>\n    Some <em>text</em> here\n  </p>\n</div>\n",
+            ),
+            (
+                "<!DOCTYPE html><html><head><title>A page</title></head><!-- a comment --></html>",
+                "<!DOCTYPE html>\n<html>\n  <head>\n    <title>\n      A page\n    </title>\n  </head>\n  <!-- a comment -->\n</html>\n",
+            ),
+            (
+                "<p>a<br>b</p><hr><p>c</p>",
+                "<p>\n  a<br>b\n</p>\n<hr>\n<p>\n  c\n</p>\n",
+            ),
+            ("<p>a < b</p>", "<p>\n  a < b\n</p>\n"),
+            ("<P>Text</P>", "<p>\n  Text\n</p>\n"),
+        ];
+
+        for (html, expected) in cases {
+            assert_eq!(format_html(html), expected, "{}", html);
+        }
+    }
+
+    #[test]
+    fn sorts_and_quotes_attributes() {
+        let cases = [
+            (
+                "<img src=a.png alt='A cat'>",
+                "<img alt=\"A cat\" src=\"a.png\">\n",
+            ),
+            (
+                "<a title='say \"hi\"' href=x>y</a>",
+                "<a href=\"x\" title=\"say &quot;hi&quot;\">y</a>\n",
+            ),
+            (
+                "<input type='checkbox' disabled/>",
+                "<input disabled type=\"checkbox\" />\n",
+            ),
+        ];
+
+        for (html, expected) in cases {
+            assert_eq!(format_html(html), expected, "{}", html);
+        }
+    }
+
+    #[test]
+    fn keeps_preformatted_contents() {
+        let cases = [
+            (
+                "<div><pre><code>fn main() {\n    println!();\n}</code></pre></div>",
+                "<div>\n<pre><code>fn main() {\n    println!();\n}</code></pre>\n</div>\n",
+            ),
+            (
+                "<ul><li>Run <pre>ls  -a</pre></li></ul>",
+                "<ul>\n  <li>\n    Run <pre>ls  -a</pre>\n  </li>\n</ul>\n",
+            ),
+            (
+                "<script>if (a < b) {\n  go();\n}</SCRIPT><p>x</p>",
+                "<script>if (a < b) {\n  go();\n}</SCRIPT>\n<p>\n  x\n</p>\n",
+            ),
+        ];
+
+        for (html, expected) in cases {
+            assert_eq!(format_html(html), expected, "{}", html);
+        }
+    }
+
+    #[test]
+    fn formatting_twice_changes_nothing() {
+        let html = "<!DOCTYPE html><html><body><main><h1 id=a class=b>Title</h1>\
+                    <p>Some <a href=x>link</a>.</p><pre>  kept\n  as is</pre></main></body></html>";
+        let formatted = format_html(html);
+        assert_eq!(format_html(&formatted), formatted);
+    }
+}
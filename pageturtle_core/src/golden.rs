@@ -0,0 +1,327 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    blog::{adjacent_posts, BlogConfiguration, PublishableBlogPost},
+    formatter::format_html,
+    rendering::{self, BuildInfo, RenderError},
+    routes::{self, Page},
+};
+
+/// Lines of context shown around the changed lines of a diff
+const CONTEXT_LINES: usize = 3;
+
+/// Page rendered from the posts, along with its path in the output
+#[derive(Debug)]
+pub struct RenderedPage {
+    pub path: PathBuf,
+    pub html: String,
+}
+
+/// Page that doesn't match its golden file
+#[derive(Debug)]
+pub struct Mismatch {
+    pub path: PathBuf,
+    /// Lines of the golden file that were removed (`-`) and added (`+`) in
+    /// the rendered page, or `None` when there is no golden file yet
+    pub diff: Option<String>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.diff {
+            Some(ref diff) => write!(f, "{} changed:\n{}", self.path.display(), diff),
+            None => write!(f, "{} has no golden file", self.path.display()),
+        }
+    }
+}
+
+/// Renders the pages of the blog's routes as they would be published, with
+/// either the built-in templates or the ones of the blog. The posts are
+/// expected in the order that they are listed, as when building. The build
+/// information is fixed and the pages are formatted, so that they only
+/// change along with their templates and posts, and their diffs only show
+/// the lines that changed.
+pub fn render_pages(
+    posts: &[PublishableBlogPost],
+    config: &BlogConfiguration,
+) -> Result<Vec<RenderedPage>, RenderError> {
+    let build = BuildInfo {
+        time: "1970-01-01T00:00:00Z".to_owned(),
+        version: "0.0.0",
+    };
+
+    let mut pages = Vec::new();
+
+    for route in routes::collect_routes(posts, config) {
+        let html = match route.page {
+            Page::Index => rendering::render_index(posts, config, &build)?,
+            Page::Tags => rendering::render_tags_page(posts, config)?,
+            Page::Tag(ref tag) => rendering::render_tag_page(tag, posts, config)?,
+            Page::Search => rendering::render_search_page(config)?,
+            Page::Post(post) => {
+                let (previous, next) = adjacent_posts(post, posts);
+                rendering::render_post_page(post, previous, next, config, &build)?
+            }
+        };
+
+        pages.push(RenderedPage {
+            path: route.path.into(),
+            html,
+        });
+    }
+
+    for page in pages.iter_mut() {
+        page.html = format_html(&page.html);
+    }
+
+    Ok(pages)
+}
+
+/// Compares the pages with the golden files of the same path in the
+/// directory. When `update` is set, the golden files are written with the
+/// pages instead, e.g: after an intended change to the templates.
+pub fn compare(
+    golden_dir: &Path,
+    pages: &[RenderedPage],
+    update: bool,
+) -> io::Result<Vec<Mismatch>> {
+    let mut mismatches = Vec::new();
+
+    for page in pages {
+        let golden_path = golden_dir.join(&page.path);
+
+        if update {
+            fs::create_dir_all(golden_path.parent().unwrap())?;
+            fs::write(&golden_path, &page.html)?;
+            continue;
+        }
+
+        match fs::read_to_string(&golden_path) {
+            Ok(golden) if golden == page.html => {}
+            Ok(golden) => mismatches.push(Mismatch {
+                path: page.path.clone(),
+                diff: Some(diff(&golden, &page.html)),
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => mismatches.push(Mismatch {
+                path: page.path.clone(),
+                diff: None,
+            }),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(mismatches)
+}
+
+/// Diff between two texts by lines, showing the changed lines along with a
+/// few lines around them, e.g:
+///
+/// ```text
+/// @@ line 12 @@
+///   <h1>My post</h1>
+/// - <p>Old</p>
+/// + <p>New</p>
+/// ```
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // Length of the longest common subsequence of the lines from `i` and
+    // `j` onwards
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = match expected[i] == actual[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    // Each line of the diff, with the line of the expected text it is at
+    let mut lines: Vec<(char, &str, usize)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push((' ', expected[i], i));
+            i += 1;
+            j += 1;
+        } else if i < expected.len()
+            && (j == actual.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            lines.push(('-', expected[i], i));
+            i += 1;
+        } else {
+            lines.push(('+', actual[j], i));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&n| lines[n].0 != ' ').collect();
+    let mut output = String::new();
+    let mut shown_until = 0;
+
+    for &n in &changed {
+        let end = (n + CONTEXT_LINES + 1).min(lines.len());
+        if end <= shown_until {
+            continue;
+        }
+        let start = n.saturating_sub(CONTEXT_LINES).max(shown_until);
+
+        if start > shown_until || output.is_empty() {
+            output.push_str(&format!("@@ line {} @@\n", lines[start].2 + 1));
+        }
+        for (marker, line, _) in &lines[start..end] {
+            output.push_str(&format!("{} {}\n", marker, line));
+        }
+        shown_until = end;
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{blog::sort_posts, testing};
+
+    const CONFIG: &str = r#"
+        blog_title = "Golden blog"
+        author = "Pageturtle"
+        base_url = "https://example.com"
+        blog_description = "Pages rendered with the built-in templates"
+    "#;
+
+    const POSTS: [(&str, &str); 3] = [
+        (
+            "posts/first-post.md",
+            "---\ntitle: First post\ndate: 2023-05-20\ntags:\n  - rust\n---\n\n\
+             # Introduction\n\nThe *first* post.\n\n<!-- more -->\n\n\
+             ```rust\nfn main() {}\n```\n",
+        ),
+        (
+            "posts/second-post.md",
+            "---\ntitle: Second post\ndescription: Follows the first one\n\
+             date: 2023-06-01\ntags:\n  - rust\n  - web\n---\n\n\
+             A [link](https://example.org) and a table:\n\n\
+             | a | b |\n|---|---|\n| 1 | 2 |\n",
+        ),
+        (
+            "posts/third-post.md",
+            "---\ntitle: Third post\ndate: 2023-06-10\ntime: \"14:30\"\n---\n\n\
+             With a footnote.[^1]\n\n[^1]: The footnote.\n",
+        ),
+    ];
+
+    // Compiles the posts as a build does, listed in the blog's order
+    fn golden_posts(config: &BlogConfiguration) -> Vec<PublishableBlogPost> {
+        let mut posts = testing::compile_posts(&POSTS, config);
+        sort_posts(&mut posts, config.sort_by);
+        posts
+    }
+
+    // Directory of a test, removed beforehand so that it starts empty
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("pageturtle-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn built_in_templates_match_their_golden_files() {
+        let config = testing::config(CONFIG);
+        let posts = golden_posts(&config);
+        let pages = render_pages(&posts, &config).unwrap();
+
+        // The golden files are only written when `UPDATE_GOLDEN` is set,
+        // e.g: after an intended change to the templates. Missing ones fail
+        // the test like the ones that changed
+        let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let update = env::var_os("UPDATE_GOLDEN").is_some();
+        let mismatches = compare(&golden_dir, &pages, update).unwrap();
+
+        let report: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+        assert!(
+            mismatches.is_empty(),
+            "{}\n\nSet UPDATE_GOLDEN=1 to record the golden files after an intended change",
+            report.join("\n")
+        );
+    }
+
+    #[test]
+    fn renders_a_page_for_each_route() {
+        let config = testing::config(CONFIG);
+        let posts = golden_posts(&config);
+        let pages = render_pages(&posts, &config).unwrap();
+
+        let paths: Vec<&Path> = pages.iter().map(|p| p.path.as_path()).collect();
+        for path in [
+            "index.html",
+            "tags.html",
+            "first-post.html",
+            "third-post.html",
+        ] {
+            assert!(paths.contains(&Path::new(path)), "{} is missing", path);
+        }
+    }
+
+    #[test]
+    fn compare_reports_missing_and_changed_golden_files() {
+        let golden_dir = scratch_dir("golden-compare");
+        let page = |html: &str| RenderedPage {
+            path: PathBuf::from("posts/page.html"),
+            html: html.to_owned(),
+        };
+
+        let mismatches = compare(&golden_dir, &[page("<p>a</p>\n")], false).unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].diff.is_none());
+
+        compare(&golden_dir, &[page("<p>a</p>\n")], true).unwrap();
+        assert!(compare(&golden_dir, &[page("<p>a</p>\n")], false)
+            .unwrap()
+            .is_empty());
+
+        let mismatches = compare(&golden_dir, &[page("<p>b</p>\n")], false).unwrap();
+        assert_eq!(
+            mismatches[0].diff.as_deref(),
+            Some("@@ line 1 @@\n- <p>a</p>\n+ <p>b</p>\n")
+        );
+
+        fs::remove_dir_all(&golden_dir).unwrap();
+    }
+
+    #[test]
+    fn diff_of_equal_texts_is_empty() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn diff_shows_the_changed_lines_with_their_context() {
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nx\nc\n"),
+            "@@ line 1 @@\n  a\n- b\n+ x\n  c\n"
+        );
+        assert_eq!(diff("a\n", "a\nb\n"), "@@ line 1 @@\n  a\n+ b\n");
+    }
+
+    #[test]
+    fn diff_splits_distant_changes_in_hunks() {
+        let expected: Vec<String> = (1..=10).map(|n| n.to_string()).collect();
+        let mut actual = expected.clone();
+        actual[0] = "one".to_owned();
+        actual[9] = "ten".to_owned();
+
+        assert_eq!(
+            diff(&expected.join("\n"), &actual.join("\n")),
+            "@@ line 1 @@\n- 1\n+ one\n  2\n  3\n  4\n\
+             @@ line 7 @@\n  7\n  8\n  9\n- 10\n+ ten\n"
+        );
+    }
+}
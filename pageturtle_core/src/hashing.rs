@@ -0,0 +1,35 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Hex digits kept of the hashes of contents, which are enough to tell the
+/// files of a blog apart
+const CONTENT_HASH_LENGTH: usize = 16;
+
+/// Hex digits kept of the keyed hashes, which are enough for them not to be
+/// guessed
+const KEYED_HASH_LENGTH: usize = 32;
+
+/// Hash of some contents, e.g: to fingerprint the files of the blog. Unlike
+/// the hashers of the standard library, it stays the same across platforms
+/// and versions of Rust, so that it can be compared between builds
+pub fn content_hash(contents: impl AsRef<[u8]>) -> String {
+    hex(&Sha256::digest(contents.as_ref()), CONTENT_HASH_LENGTH)
+}
+
+/// HMAC-SHA256 of a message, which can't be derived from the message
+/// without the key
+pub fn keyed_hash(key: &str, message: impl AsRef<[u8]>) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_ref());
+
+    hex(&mac.finalize().into_bytes(), KEYED_HASH_LENGTH)
+}
+
+fn hex(bytes: &[u8], length: usize) -> String {
+    bytes
+        .iter()
+        .take(length / 2)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,625 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, Write},
+    ops::RangeInclusive,
+};
+
+use comrak::{adapters::SyntaxHighlighterAdapter, html};
+use serde::Deserialize;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    html::{
+        css_for_theme_with_class_style, line_tokens_to_classed_spans,
+        styled_line_to_highlighted_html, ClassStyle, IncludeBackground,
+    },
+    parsing::{ParseState, Scope, ScopeStack, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+use self::treesitter::TreeSitterHighlighter;
+use crate::utils::escape_html;
+
+mod treesitter;
+
+/// Prefix of the classes given to the highlighted tokens, so that they don't
+/// clash with the blog's own styles
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "syn-" };
+
+/// Backend used for highlighting code blocks
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyntaxHighlighter {
+    #[default]
+    Syntect,
+    /// Uses tree-sitter grammars for the languages it supports, falling back
+    /// to syntect for the other ones
+    TreeSitter,
+}
+
+/// How the highlighted tokens are styled
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HighlightStyle {
+    /// Classes styled by the `syntax.css` stylesheet, which switches to the
+    /// dark theme when the reader prefers a dark color scheme
+    #[default]
+    Classes,
+    /// Styles of the light theme inlined in the tokens, e.g: for feed
+    /// readers, which don't load the blog's stylesheets
+    Inline,
+}
+
+/// Settings of the `[syntax_theme]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct SyntaxThemeConfiguration {
+    /// One of syntect's default themes, e.g: `InspiredGitHub` or
+    /// `Solarized (light)`
+    #[serde(default = "default_light_theme")]
+    pub light: String,
+
+    /// Theme used when the reader prefers a dark color scheme
+    #[serde(default = "default_dark_theme")]
+    pub dark: String,
+
+    #[serde(default)]
+    pub style: HighlightStyle,
+}
+
+impl Default for SyntaxThemeConfiguration {
+    fn default() -> Self {
+        SyntaxThemeConfiguration {
+            light: default_light_theme(),
+            dark: default_dark_theme(),
+            style: HighlightStyle::default(),
+        }
+    }
+}
+
+fn default_light_theme() -> String {
+    "InspiredGitHub".to_owned()
+}
+
+fn default_dark_theme() -> String {
+    "base16-ocean.dark".to_owned()
+}
+
+impl SyntaxThemeConfiguration {
+    /// Themes of the configuration that don't exist. These are replaced by
+    /// the default ones
+    pub fn unknown_themes(&self) -> Vec<&str> {
+        let theme_set = ThemeSet::load_defaults();
+
+        [self.light.as_str(), self.dark.as_str()]
+            .into_iter()
+            .filter(|name| !theme_set.themes.contains_key(*name))
+            .collect()
+    }
+}
+
+/// Names of the themes that code can be highlighted with
+pub fn theme_names() -> Vec<String> {
+    ThemeSet::load_defaults().themes.into_keys().collect()
+}
+
+fn load_theme(theme_set: &ThemeSet, name: &str, default: fn() -> String) -> Theme {
+    theme_set
+        .themes
+        .get(name)
+        .unwrap_or_else(|| &theme_set.themes[&default()])
+        .clone()
+}
+
+/// Metadata that can be given to a code block through its info string, e.g:
+/// `rust title="main.rs" hl_lines="3-5" diff`
+#[derive(Debug, Default, PartialEq)]
+pub struct CodeBlockMeta {
+    pub title: Option<String>,
+    pub highlighted_lines: Vec<RangeInclusive<usize>>,
+    /// Whether lines prefixed with `+` or `-` should be styled as added or
+    /// removed lines
+    pub diff: bool,
+    /// File that the code block is extracted to by `pageturtle tangle`
+    pub file: Option<String>,
+    /// Whether the code block is a sample run by `pageturtle check
+    /// --run-samples`
+    pub run: bool,
+}
+
+impl CodeBlockMeta {
+    /// Parses the `key="value"` pairs and flags found after the language in
+    /// a code block's info string. Unknown keys are ignored.
+    pub fn parse(info: &str) -> CodeBlockMeta {
+        let mut meta = CodeBlockMeta::default();
+
+        for (key, value) in parse_attributes(info) {
+            match key.as_str() {
+                "title" => meta.title = Some(value),
+                "hl_lines" => meta.highlighted_lines = parse_line_ranges(&value),
+                "diff" => meta.diff = true,
+                "file" => meta.file = Some(value),
+                "run" => meta.run = true,
+                _ => continue,
+            }
+        }
+
+        meta
+    }
+
+    pub fn is_highlighted(&self, line_number: usize) -> bool {
+        self.highlighted_lines
+            .iter()
+            .any(|range| range.contains(&line_number))
+    }
+}
+
+fn parse_attributes(info: &str) -> Vec<(String, String)> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_quotes = false;
+
+    for c in info.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => tokens.push(std::mem::take(&mut token)),
+            c => token.push(c),
+        }
+    }
+    tokens.push(token);
+
+    tokens
+        .iter()
+        .filter(|t| !t.is_empty())
+        .map(|t| match t.split_once('=') {
+            Some((key, value)) => (key.to_owned(), value.to_owned()),
+            None => (t.to_owned(), String::new()),
+        })
+        .collect()
+}
+
+/// Parses line ranges such as `"1 3-5"` or `"1,3-5"` into inclusive ranges.
+/// Reversed ranges, e.g: `5-3`, are read as if they were in order.
+fn parse_line_ranges(value: &str) -> Vec<RangeInclusive<usize>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|part| match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+                Some(start.min(end)..=start.max(end))
+            }
+            None => {
+                let line = part.parse().ok()?;
+                Some(line..=line)
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffMarker {
+    Added,
+    Removed,
+}
+
+impl DiffMarker {
+    fn from_line(line: &str) -> Option<DiffMarker> {
+        // File headers of unified diffs
+        if line.starts_with("+++") || line.starts_with("---") {
+            return None;
+        }
+
+        match line.chars().next() {
+            Some('+') => Some(DiffMarker::Added),
+            Some('-') => Some(DiffMarker::Removed),
+            _ => None,
+        }
+    }
+
+    fn class(&self) -> &'static str {
+        match self {
+            DiffMarker::Added => "code-line-added",
+            DiffMarker::Removed => "code-line-removed",
+        }
+    }
+}
+
+/// Codefence renderer that highlights code using the configured
+/// [`SyntaxHighlighter`] and understands the metadata described in
+/// [`CodeBlockMeta`].
+///
+/// comrak only hands the info string to `write_pre_tag`, so the parsed
+/// metadata is kept around until `write_highlighted` is called for the same
+/// block. It is kept per thread, so that posts can be rendered in parallel
+/// with the same renderer.
+pub struct CodeBlockRenderer {
+    syntax_set: SyntaxSet,
+    tree_sitter: Option<TreeSitterHighlighter>,
+    /// Theme whose styles are inlined in the tokens, if any
+    inline_theme: Option<Theme>,
+}
+
+thread_local! {
+    static CURRENT_META: RefCell<CodeBlockMeta> = RefCell::new(CodeBlockMeta::default());
+}
+
+impl CodeBlockRenderer {
+    pub fn new(highlighter: SyntaxHighlighter, theme: &SyntaxThemeConfiguration) -> Self {
+        let inline_theme = match theme.style {
+            HighlightStyle::Classes => None,
+            HighlightStyle::Inline => Some(load_theme(
+                &ThemeSet::load_defaults(),
+                &theme.light,
+                default_light_theme,
+            )),
+        };
+
+        let tree_sitter = match highlighter {
+            SyntaxHighlighter::Syntect => None,
+            SyntaxHighlighter::TreeSitter => {
+                Some(TreeSitterHighlighter::new(inline_theme.as_ref()))
+            }
+        };
+
+        CodeBlockRenderer {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            tree_sitter,
+            inline_theme,
+        }
+    }
+
+    /// Highlights the given code, returning the HTML of each line without
+    /// its line ending
+    fn highlight_lines(&self, lang: Option<&str>, code: &str) -> Vec<String> {
+        let tree_sitter_lines = self
+            .tree_sitter
+            .as_ref()
+            .zip(lang)
+            .and_then(|(highlighter, lang)| highlighter.highlight_lines(lang, code));
+
+        match tree_sitter_lines {
+            Some(lines) => lines,
+            None => self.syntect_highlight_lines(lang, code),
+        }
+    }
+
+    fn syntect_highlight_lines(&self, lang: Option<&str>, code: &str) -> Vec<String> {
+        let syntax = lang
+            .filter(|l| !l.is_empty())
+            .and_then(|l| self.syntax_set.find_syntax_by_token(l))
+            .or_else(|| self.syntax_set.find_syntax_by_first_line(code))
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+
+        if let Some(ref theme) = self.inline_theme {
+            let mut highlighter = HighlightLines::new(syntax, theme);
+
+            return LinesWithEndings::from(code)
+                .map(|line| {
+                    highlighter
+                        .highlight_line(line, &self.syntax_set)
+                        .and_then(|regions| {
+                            styled_line_to_highlighted_html(&regions, IncludeBackground::No)
+                        })
+                        .map(|html| html.replace(['\r', '\n'], ""))
+                        .unwrap_or_else(|_| escape_html(line.trim_end_matches(['\r', '\n'])))
+                })
+                .collect();
+        }
+
+        let mut parse_state = ParseState::new(syntax);
+        let mut scopes = ScopeStack::new();
+
+        LinesWithEndings::from(code)
+            .map(|line| {
+                // Spans can't cross line boundaries since each line may get
+                // wrapped, so the scopes still open from previous lines are
+                // reopened here and closed at the end of the line
+                let mut output: String = scopes.as_slice().iter().map(scope_span).collect();
+
+                let highlighted = parse_state
+                    .parse_line(line, &self.syntax_set)
+                    .map_err(syntect::Error::from)
+                    .and_then(|ops| {
+                        line_tokens_to_classed_spans(line, &ops, CLASS_STYLE, &mut scopes)
+                    });
+
+                match highlighted {
+                    Ok((html, _)) => {
+                        output.push_str(&html.replace(['\r', '\n'], ""));
+                        output.push_str(&"</span>".repeat(scopes.len()));
+                        output
+                    }
+                    Err(_) => escape_html(line.trim_end_matches(['\r', '\n'])),
+                }
+            })
+            .collect()
+    }
+}
+
+fn scope_span(scope: &Scope) -> String {
+    let classes: Vec<String> = scope
+        .build_string()
+        .split('.')
+        .map(|atom| format!("syn-{}", atom))
+        .collect();
+
+    format!("<span class=\"{}\">", classes.join(" "))
+}
+
+/// Builds the stylesheet for highlighted code blocks, using the dark theme
+/// when the reader prefers a dark color scheme. It is empty when the styles
+/// are inlined in the code blocks instead
+pub fn stylesheet(theme: &SyntaxThemeConfiguration) -> String {
+    if theme.style == HighlightStyle::Inline {
+        return String::new();
+    }
+
+    let theme_set = ThemeSet::load_defaults();
+
+    format!(
+        "{}\n@media (prefers-color-scheme: dark) {{\n{}}}\n",
+        theme_css(&load_theme(&theme_set, &theme.light, default_light_theme)),
+        theme_css(&load_theme(&theme_set, &theme.dark, default_dark_theme))
+    )
+}
+
+fn theme_css(theme: &Theme) -> String {
+    // Only fails when writing to the underlying string fails
+    let syntect_css = css_for_theme_with_class_style(theme, CLASS_STYLE).unwrap();
+    format!("{}{}", syntect_css, treesitter::theme_css(theme))
+}
+
+impl SyntaxHighlighterAdapter for CodeBlockRenderer {
+    fn write_highlighted(
+        &self,
+        output: &mut dyn Write,
+        lang: Option<&str>,
+        code: &str,
+    ) -> io::Result<()> {
+        let meta = CURRENT_META.with(|meta| meta.take());
+
+        let (lines, markers) = match lang {
+            // `diff` blocks are highlighted as diffs, the other ones have their
+            // markers stripped so that the code itself can be highlighted
+            Some("diff") => {
+                let markers = code.lines().map(DiffMarker::from_line).collect();
+                (self.highlight_lines(lang, code), markers)
+            }
+            _ if meta.diff => {
+                let markers: Vec<_> = code.lines().map(DiffMarker::from_line).collect();
+                let stripped: String = LinesWithEndings::from(code)
+                    .zip(&markers)
+                    .map(|(line, marker)| match marker {
+                        Some(_) => &line[1..],
+                        None => line,
+                    })
+                    .collect();
+
+                let lines = self
+                    .highlight_lines(lang, &stripped)
+                    .into_iter()
+                    .zip(&markers)
+                    .map(|(line, marker)| match marker {
+                        Some(DiffMarker::Added) => format!("+{}", line),
+                        Some(DiffMarker::Removed) => format!("-{}", line),
+                        None => line,
+                    })
+                    .collect();
+
+                (lines, markers)
+            }
+            _ => (self.highlight_lines(lang, code), vec![]),
+        };
+
+        for (i, line) in lines.iter().enumerate() {
+            let mut classes = Vec::new();
+
+            if meta.is_highlighted(i + 1) {
+                classes.push("code-line-highlighted");
+            }
+
+            if let Some(Some(marker)) = markers.get(i) {
+                classes.push(marker.class());
+            }
+
+            if classes.is_empty() {
+                writeln!(output, "{}", line)?;
+            } else {
+                writeln!(
+                    output,
+                    "<span class=\"{}\">{}</span>",
+                    classes.join(" "),
+                    line
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_pre_tag(
+        &self,
+        output: &mut dyn Write,
+        mut attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        let meta = attributes
+            .remove("data-meta")
+            .map(|info| CodeBlockMeta::parse(&info))
+            .unwrap_or_default();
+
+        if let Some(ref title) = meta.title {
+            write!(
+                output,
+                "<div class=\"code-title\">{}</div>",
+                escape_html(title)
+            )?;
+        }
+
+        attributes.insert("class".to_owned(), "syn-code".to_owned());
+
+        let background = self
+            .inline_theme
+            .as_ref()
+            .and_then(|theme| theme.settings.background);
+        if let Some(color) = background {
+            attributes.insert(
+                "style".to_owned(),
+                format!(
+                    "background-color: #{:02x}{:02x}{:02x};",
+                    color.r, color.g, color.b
+                ),
+            );
+        }
+
+        CURRENT_META.with(|current| current.replace(meta));
+        html::write_opening_tag(output, "pre", attributes)
+    }
+
+    fn write_code_tag(
+        &self,
+        output: &mut dyn Write,
+        attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        html::write_opening_tag(output, "code", attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_line_ranges() {
+        let cases: &[(&str, &[RangeInclusive<usize>])] = &[
+            ("1 3-5", &[1..=1, 3..=5]),
+            ("1,3-5", &[1..=1, 3..=5]),
+            ("5-3", &[3..=5]),
+            ("", &[]),
+            (" , ", &[]),
+            ("2,,4", &[2..=2, 4..=4]),
+            ("3- x 7", &[7..=7]),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(parse_line_ranges(value), *expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_code_block_metadata() {
+        let cases = [
+            ("rust", CodeBlockMeta::default()),
+            (
+                "rust title=\"My file.rs\" hl_lines=\"1 3-5\"",
+                CodeBlockMeta {
+                    title: Some("My file.rs".to_owned()),
+                    highlighted_lines: vec![1..=1, 3..=5],
+                    ..CodeBlockMeta::default()
+                },
+            ),
+            (
+                "sh file=\"scripts/run.sh\" run diff",
+                CodeBlockMeta {
+                    file: Some("scripts/run.sh".to_owned()),
+                    run: true,
+                    diff: true,
+                    ..CodeBlockMeta::default()
+                },
+            ),
+            (
+                "rust linenos unknown=\"a value\" title=main.rs",
+                CodeBlockMeta {
+                    title: Some("main.rs".to_owned()),
+                    ..CodeBlockMeta::default()
+                },
+            ),
+            ("rust hl_lines=\"\"", CodeBlockMeta::default()),
+        ];
+
+        for (info, expected) in cases {
+            assert_eq!(CodeBlockMeta::parse(info), expected, "{:?}", info);
+        }
+    }
+
+    #[test]
+    fn reversed_ranges_are_highlighted() {
+        let meta = CodeBlockMeta::parse("rust hl_lines=\"4-2\"");
+        assert!((2..=4).all(|line| meta.is_highlighted(line)));
+        assert!(!meta.is_highlighted(1) && !meta.is_highlighted(5));
+    }
+
+    fn render(markdown: &str) -> String {
+        let renderer = CodeBlockRenderer::new(
+            SyntaxHighlighter::Syntect,
+            &SyntaxThemeConfiguration::default(),
+        );
+        let mut plugins = comrak::ComrakPlugins::default();
+        plugins.render.codefence_syntax_highlighter = Some(&renderer);
+        comrak::markdown_to_html_with_plugins(markdown, &crate::blog::markdown_options(), &plugins)
+    }
+
+    // Checks the class of each line of the rendered code, given by the text
+    // that it contains, and that the spans are closed on the line they're
+    // opened, as lines may get wrapped
+    fn assert_line_classes(html: &str, expected: &[(&str, Option<&str>)]) {
+        for line in html.lines() {
+            assert_eq!(
+                line.matches("<span").count(),
+                line.matches("</span>").count(),
+                "unbalanced spans in {:?}",
+                line
+            );
+        }
+
+        for (text, class) in expected {
+            let line = html.lines().find(|l| l.contains(text)).unwrap();
+            for marker in [DiffMarker::Added, DiffMarker::Removed] {
+                assert_eq!(
+                    line.contains(marker.class()),
+                    *class == Some(marker.class()),
+                    "{:?}",
+                    line
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn marks_the_changed_lines_of_diffs() {
+        let html = render(
+            "```diff\n--- a/main.rs\n+++ b/main.rs\n fn main() {\n-    old();\n+    new();\n }\n```\n",
+        );
+
+        assert_line_classes(
+            &html,
+            &[
+                ("a/main.rs", None),
+                ("b/main.rs", None),
+                ("fn main", None),
+                ("old", Some("code-line-removed")),
+                ("new", Some("code-line-added")),
+            ],
+        );
+    }
+
+    #[test]
+    fn marks_the_changed_lines_of_highlighted_code() {
+        let html =
+            render("```rust diff\nfn main() {\n-    old(\"a\");\n+    new(\"b\");\n}\n```\n");
+
+        assert_line_classes(
+            &html,
+            &[
+                ("fn", None),
+                ("old", Some("code-line-removed")),
+                ("new", Some("code-line-added")),
+            ],
+        );
+        // The markers are kept in front of the highlighted code
+        assert!(
+            html.contains("<span class=\"code-line-added\">+"),
+            "{}",
+            html
+        );
+    }
+}
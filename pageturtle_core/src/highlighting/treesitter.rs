@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use syntect::{
+    highlighting::{FontStyle, Highlighter, Theme},
+    parsing::Scope,
+};
+use tree_sitter_highlight::{HighlightConfiguration, HtmlRenderer};
+
+/// Highlight names recognized in the tree-sitter queries, along with the
+/// TextMate scope used to pick their colors from the syntect theme
+const HIGHLIGHT_NAMES: &[(&str, &str)] = &[
+    ("attribute", "entity.other.attribute-name"),
+    ("comment", "comment"),
+    ("constant", "constant"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.type"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.builtin", "support.function"),
+    ("function.macro", "entity.name.function"),
+    ("function.method", "entity.name.function"),
+    ("keyword", "keyword"),
+    ("label", "entity.name.label"),
+    ("number", "constant.numeric"),
+    ("operator", "keyword.operator"),
+    ("property", "variable.other.member"),
+    ("punctuation", "punctuation"),
+    ("string", "string"),
+    ("string.special", "string.regexp"),
+    ("tag", "entity.name.tag"),
+    ("type", "entity.name.type"),
+    ("type.builtin", "storage.type"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter"),
+];
+
+/// Highlighting backend using tree-sitter grammars. Only a handful of
+/// languages are bundled, [`TreeSitterHighlighter::highlight_lines`] returns
+/// `None` for the other ones so that callers can fall back to syntect.
+pub struct TreeSitterHighlighter {
+    languages: HashMap<&'static str, HighlightConfiguration>,
+    attributes: Vec<String>,
+}
+
+impl TreeSitterHighlighter {
+    /// Highlights the tokens with classes, or with the styles of the given
+    /// theme inlined in them
+    pub fn new(inline_theme: Option<&Theme>) -> Self {
+        let mut languages = HashMap::new();
+
+        let rust = HighlightConfiguration::new(
+            tree_sitter_rust::language(),
+            tree_sitter_rust::HIGHLIGHT_QUERY,
+            tree_sitter_rust::INJECTIONS_QUERY,
+            "",
+        );
+        let python = HighlightConfiguration::new(
+            tree_sitter_python::language(),
+            tree_sitter_python::HIGHLIGHT_QUERY,
+            "",
+            "",
+        );
+        let javascript = HighlightConfiguration::new(
+            tree_sitter_javascript::language(),
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+            tree_sitter_javascript::INJECTION_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        );
+        let go = HighlightConfiguration::new(
+            tree_sitter_go::language(),
+            tree_sitter_go::HIGHLIGHT_QUERY,
+            "",
+            "",
+        );
+
+        let names: Vec<&str> = HIGHLIGHT_NAMES.iter().map(|(name, _)| *name).collect();
+
+        for (name, config) in [
+            ("rust", rust),
+            ("python", python),
+            ("javascript", javascript),
+            ("go", go),
+        ] {
+            // The bundled queries are known to be valid
+            let mut config = config.unwrap();
+            config.configure(&names);
+            languages.insert(name, config);
+        }
+
+        let attributes = HIGHLIGHT_NAMES
+            .iter()
+            .map(|(name, scope)| match inline_theme {
+                Some(theme) => format!("style=\"{}\"", scope_css(theme, scope)),
+                None => format!("class=\"{}\"", class_name(name)),
+            })
+            .collect();
+
+        TreeSitterHighlighter {
+            languages,
+            attributes,
+        }
+    }
+
+    /// Highlights the given code, returning the HTML of each line without its
+    /// line ending, or `None` if there is no grammar for the given language
+    pub fn highlight_lines(&self, lang: &str, code: &str) -> Option<Vec<String>> {
+        let config = self.languages.get(language_name(lang))?;
+
+        let mut highlighter = tree_sitter_highlight::Highlighter::new();
+        let events = highlighter
+            .highlight(config, code.as_bytes(), None, |injected| {
+                self.languages.get(language_name(injected))
+            })
+            .ok()?;
+
+        let mut renderer = HtmlRenderer::new();
+        renderer
+            .render(events, code.as_bytes(), &|h| {
+                self.attributes[h.0].as_bytes()
+            })
+            .ok()?;
+
+        let lines = renderer
+            .lines()
+            .map(|line| line.trim_end_matches('\n').to_owned())
+            .collect();
+
+        Some(lines)
+    }
+}
+
+fn language_name(lang: &str) -> &str {
+    match lang {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "golang" => "go",
+        lang => lang,
+    }
+}
+
+fn class_name(highlight_name: &str) -> String {
+    format!("ts-{}", highlight_name.replace('.', "-"))
+}
+
+/// Builds the CSS rules of each highlight class from the theme colors
+pub fn theme_css(theme: &Theme) -> String {
+    HIGHLIGHT_NAMES
+        .iter()
+        .map(|(name, scope)| format!(".{} {{ {} }}\n", class_name(name), scope_css(theme, scope)))
+        .collect()
+}
+
+// Declarations styling the tokens of a scope with the theme colors
+fn scope_css(theme: &Theme, scope: &str) -> String {
+    let style = Highlighter::new(theme).style_for_stack(&[Scope::new(scope).unwrap()]);
+    let color = style.foreground;
+    let mut css = format!("color: #{:02x}{:02x}{:02x};", color.r, color.g, color.b);
+
+    if style.font_style.contains(FontStyle::BOLD) {
+        css.push_str(" font-weight: bold;");
+    }
+
+    if style.font_style.contains(FontStyle::ITALIC) {
+        css.push_str(" font-style: italic;");
+    }
+
+    css
+}
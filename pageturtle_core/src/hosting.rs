@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{blog::BlogConfiguration, utils::default_empty};
+
+/// Cache policy of the fingerprinted assets, whose paths change along with
+/// their contents
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Settings of the `[hosting]` section of the configuration file
+#[derive(Debug, Default, Deserialize)]
+pub struct HostingConfiguration {
+    /// Hosts whose configuration files are published along with the blog
+    #[serde(default = "default_empty")]
+    pub hosts: Vec<Host>,
+
+    /// Domain of the blog published in GitHub Pages' `CNAME` file. Defaults
+    /// to the domain of the blog's `base_url`, unless it is a `github.io`
+    /// one
+    pub domain: Option<String>,
+}
+
+/// Static hosting service that the blog is published to
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Host {
+    /// Publishes `CNAME` and `.nojekyll`
+    GithubPages,
+    /// Publishes `_headers`. Redirects are always published in `_redirects`
+    Netlify,
+    /// Publishes `vercel.json`
+    Vercel,
+}
+
+/// Permanent redirect from a path of the blog to another
+#[derive(Debug)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+}
+
+/// Configuration file of a host, relative to the output directory
+#[derive(Debug)]
+pub struct HostFile {
+    pub path: &'static str,
+    pub contents: String,
+}
+
+impl HostingConfiguration {
+    fn targets(&self, host: Host) -> bool {
+        self.hosts.contains(&host)
+    }
+
+    /// Domain that GitHub Pages serves the blog at, if it is a custom one
+    pub fn custom_domain(&self, config: &BlogConfiguration) -> Option<String> {
+        if let Some(ref domain) = self.domain {
+            return Some(domain.clone());
+        }
+
+        let domain = config
+            .base_url
+            .split_once("://")
+            .map_or(config.base_url.as_str(), |(_, rest)| rest)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default();
+
+        match domain.is_empty() || domain.ends_with("github.io") {
+            true => None,
+            false => Some(domain.to_owned()),
+        }
+    }
+}
+
+/// Configuration files of the hosts that the blog targets, with the blog's
+/// redirects and with the fingerprinted assets cached forever.
+pub fn host_files(
+    config: &BlogConfiguration,
+    redirects: &[Redirect],
+    immutable_paths: &[&str],
+) -> Vec<HostFile> {
+    let hosting = &config.hosting;
+    let mut files = Vec::new();
+
+    // Understood by several hosts besides Netlify, e.g: Cloudflare Pages
+    if !redirects.is_empty() {
+        let lines: Vec<String> = redirects
+            .iter()
+            .map(|r| format!("{} {} 301", r.from, r.to))
+            .collect();
+        files.push(HostFile {
+            path: "_redirects",
+            contents: lines.join("\n"),
+        });
+    }
+
+    if hosting.targets(Host::GithubPages) {
+        // Otherwise GitHub Pages ignores the files starting with `_`
+        files.push(HostFile {
+            path: ".nojekyll",
+            contents: String::new(),
+        });
+
+        if let Some(domain) = hosting.custom_domain(config) {
+            files.push(HostFile {
+                path: "CNAME",
+                contents: format!("{}\n", domain),
+            });
+        }
+    }
+
+    if hosting.targets(Host::Netlify) && !immutable_paths.is_empty() {
+        let rules: Vec<String> = immutable_paths
+            .iter()
+            .map(|path| format!("/{}\n  Cache-Control: {}", path, IMMUTABLE_CACHE_CONTROL))
+            .collect();
+        files.push(HostFile {
+            path: "_headers",
+            contents: rules.join("\n"),
+        });
+    }
+
+    if hosting.targets(Host::Vercel) {
+        let redirects: Vec<_> = redirects
+            .iter()
+            .map(|r| json!({ "source": r.from, "destination": r.to, "permanent": true }))
+            .collect();
+        let headers: Vec<_> = immutable_paths
+            .iter()
+            .map(|path| {
+                json!({
+                    "source": format!("/{}", path),
+                    "headers": [{ "key": "Cache-Control", "value": IMMUTABLE_CACHE_CONTROL }],
+                })
+            })
+            .collect();
+
+        let vercel = json!({ "redirects": redirects, "headers": headers });
+        files.push(HostFile {
+            path: "vercel.json",
+            contents: serde_json::to_string_pretty(&vercel).unwrap(),
+        });
+    }
+
+    files
+}
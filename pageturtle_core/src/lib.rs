@@ -1,4 +1,42 @@
+pub mod activitypub;
+pub mod assets;
 pub mod blog;
+pub mod changelog;
+pub mod checks;
+pub mod defaults;
+pub mod discussion;
+pub mod duplicates;
+pub mod external;
 pub mod feed;
+pub mod footnotes;
+pub mod formats;
+pub mod formatter;
+pub mod golden;
+pub mod hashing;
+pub mod highlighting;
+pub mod hosting;
+pub mod link_preview;
+pub mod notebooks;
+pub mod notify;
+pub mod related;
+pub mod remote_images;
 pub mod rendering;
+pub mod responsive_images;
+pub mod resume;
+pub mod routes;
+pub mod samples;
+pub mod sanitizer;
+pub mod search;
+pub mod shortcodes;
+pub mod sitemap;
+pub mod tag_suggestions;
+pub mod talks;
+pub mod tangle;
+pub mod taxonomy;
+#[cfg(test)]
+mod testing;
+pub mod thumbnails;
+pub mod typography;
+pub mod urls;
+pub mod user_templates;
 mod utils;
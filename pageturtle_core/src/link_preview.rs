@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use comrak::nodes::{AstNode, NodeValue};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::escape_html;
+
+/// OpenGraph metadata of a linked page, rendered as a card in place of the
+/// bare link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+impl LinkPreview {
+    /// Reads the preview of a page from its OpenGraph tags, falling back to
+    /// its `<title>`. Returns `None` when the page has no title at all.
+    pub fn from_html(url: &str, html: &str) -> Option<LinkPreview> {
+        let meta = meta_properties(html);
+        let title = meta
+            .get("og:title")
+            .cloned()
+            .or_else(|| document_title(html))?;
+
+        Some(LinkPreview {
+            url: url.to_owned(),
+            title,
+            description: meta
+                .get("og:description")
+                .or_else(|| meta.get("description"))
+                .cloned(),
+            image: meta.get("og:image").cloned(),
+        })
+    }
+
+    pub fn to_html(&self) -> String {
+        let image = match self.image {
+            Some(ref src) => format!(
+                "<img class=\"link-preview-image\" src=\"{}\" alt=\"\" loading=\"lazy\" />",
+                escape_html(src)
+            ),
+            None => String::new(),
+        };
+        let description = match self.description {
+            Some(ref d) => format!(
+                "<span class=\"link-preview-description\">{}</span>",
+                escape_html(d)
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            "<a class=\"link-preview\" href=\"{url}\">{image}<span class=\"link-preview-body\">\
+             <span class=\"link-preview-title\">{title}</span>{description}\
+             <span class=\"link-preview-url\">{url}</span></span></a>",
+            url = escape_html(&self.url),
+            title = escape_html(&self.title),
+        )
+    }
+}
+
+/// URLs that are the only content of a paragraph, which are the ones
+/// rendered as preview cards
+pub fn bare_urls<'a>(ast: &'a AstNode<'a>) -> Vec<String> {
+    ast.descendants()
+        .filter_map(|node| bare_url(node).map(|(url, _)| url))
+        .collect()
+}
+
+/// Replaces the bare URLs that have a preview with their card. URLs without
+/// a preview, e.g: when the page couldn't be fetched, are left as they are.
+pub fn render_previews<'a>(ast: &'a AstNode<'a>, previews: &HashMap<String, LinkPreview>) {
+    for node in ast.descendants() {
+        let (url, child) = match bare_url(node) {
+            Some(found) => found,
+            None => continue,
+        };
+
+        if let Some(preview) = previews.get(&url) {
+            for grandchild in child.children().collect::<Vec<_>>() {
+                grandchild.detach();
+            }
+            child.data.borrow_mut().value = NodeValue::HtmlInline(preview.to_html());
+        }
+    }
+}
+
+// Finds the URL of a paragraph that has nothing but a link, either written as
+// plain text or as an autolink, e.g: `<https://example.com>`
+fn bare_url<'a>(node: &'a AstNode<'a>) -> Option<(String, &'a AstNode<'a>)> {
+    if !matches!(node.data.borrow().value, NodeValue::Paragraph) {
+        return None;
+    }
+
+    let child = node.first_child()?;
+    if child.next_sibling().is_some() {
+        return None;
+    }
+
+    let url = match child.data.borrow().value {
+        NodeValue::Text(ref text) => text.trim().to_owned(),
+        NodeValue::Link(ref link) => link.url.clone(),
+        _ => return None,
+    };
+
+    let is_url = (url.starts_with("http://") || url.starts_with("https://"))
+        && !url.contains(char::is_whitespace);
+
+    // Links with custom text are kept as they are
+    let is_bare = match child.data.borrow().value {
+        NodeValue::Link(_) => {
+            let text: String = child
+                .descendants()
+                .filter_map(|n| match n.data.borrow().value {
+                    NodeValue::Text(ref t) => Some(t.clone()),
+                    _ => None,
+                })
+                .collect();
+            text == url
+        }
+        _ => true,
+    };
+
+    (is_url && is_bare).then_some((url, child))
+}
+
+// Collects the `property` or `name` and `content` attributes of the page's
+// `<meta>` tags
+fn meta_properties(html: &str) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+
+    for tag in html.split("<meta").skip(1) {
+        let tag = match tag.split_once('>') {
+            Some((tag, _)) => tag,
+            None => continue,
+        };
+
+        let key = attribute(tag, "property").or_else(|| attribute(tag, "name"));
+        if let (Some(key), Some(content)) = (key, attribute(tag, "content")) {
+            properties.entry(key.to_lowercase()).or_insert(content);
+        }
+    }
+
+    properties
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let start = format!("{}={}", name, quote);
+        if let Some((_, rest)) = tag.split_once(&start) {
+            let (value, _) = rest.split_once(quote)?;
+            return Some(unescape_html(value));
+        }
+    }
+
+    None
+}
+
+fn document_title(html: &str) -> Option<String> {
+    let (_, rest) = html.split_once("<title")?;
+    let (_, rest) = rest.split_once('>')?;
+    let (title, _) = rest.split_once("</title>")?;
+    let title = unescape_html(title.trim());
+
+    (!title.is_empty()).then_some(title)
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A Jupyter notebook, as saved in `.ipynb` files
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: NotebookMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NotebookMetadata {
+    kernelspec: Option<Kernelspec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Kernelspec {
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageInfo {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cell {
+    cell_type: String,
+    source: Text,
+    #[serde(default)]
+    outputs: Vec<Output>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream { text: Text },
+    ExecuteResult { data: BTreeMap<String, Value> },
+    DisplayData { data: BTreeMap<String, Value> },
+    Error { traceback: Vec<String> },
+}
+
+/// Notebooks store text either as a string or as a list of lines
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Text {
+    Single(String),
+    Lines(Vec<String>),
+}
+
+impl Text {
+    fn into_string(self) -> String {
+        match self {
+            Text::Single(text) => text,
+            Text::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Converts a notebook to markdown. Markdown cells are kept as they are,
+/// code cells become code blocks followed by their outputs, and the first
+/// raw cell holds the frontmatter of the post.
+pub fn to_markdown(content: &str) -> Result<String, serde_json::Error> {
+    let notebook: Notebook = serde_json::from_str(content)?;
+    let language = notebook
+        .metadata
+        .language_info
+        .map(|l| l.name)
+        .or_else(|| notebook.metadata.kernelspec.and_then(|k| k.language))
+        .unwrap_or_else(|| "python".to_owned());
+
+    let mut frontmatter = None;
+    let mut body = String::with_capacity(content.len());
+
+    for cell in notebook.cells {
+        let source = cell.source.into_string();
+
+        match cell.cell_type.as_str() {
+            "raw" if frontmatter.is_none() => {
+                let source = source.trim();
+                frontmatter = Some(match source.starts_with("---") {
+                    true => source.to_owned(),
+                    false => format!("---\n{}\n---", source),
+                });
+            }
+            "markdown" => push_block(&mut body, &source),
+            "code" if !source.trim().is_empty() => {
+                push_block(&mut body, &code_block(&language, &source));
+
+                for output in cell.outputs {
+                    if let Some(output) = render_output(output) {
+                        push_block(&mut body, &output);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(format!("{}\n\n{}", frontmatter.unwrap_or_default(), body))
+}
+
+fn push_block(body: &mut String, block: &str) {
+    body.push_str(block.trim_end());
+    body.push_str("\n\n");
+}
+
+// Renders the richest format of an output that can be shown in a post
+fn render_output(output: Output) -> Option<String> {
+    let data = match output {
+        Output::Stream { text } => return Some(code_block("text", &text.into_string())),
+        Output::Error { traceback } => {
+            return Some(code_block("text", &strip_ansi(&traceback.join("\n"))))
+        }
+        Output::ExecuteResult { data } | Output::DisplayData { data } => data,
+    };
+
+    for mime_type in ["image/png", "image/jpeg", "image/gif"] {
+        if let Some(image) = data.get(mime_type).and_then(text) {
+            let image: String = image.split_whitespace().collect();
+            return Some(image_tag(mime_type, &image));
+        }
+    }
+
+    if let Some(svg) = data.get("image/svg+xml").and_then(text) {
+        return Some(image_tag("image/svg+xml", &STANDARD.encode(svg)));
+    }
+
+    // e.g: pandas' tables. Blank lines would end the HTML block
+    if let Some(html) = data.get("text/html").and_then(text) {
+        let html: Vec<&str> = html.lines().filter(|l| !l.trim().is_empty()).collect();
+        return Some(format!(
+            "<div class=\"notebook-output\">\n{}\n</div>",
+            html.join("\n")
+        ));
+    }
+
+    data.get("text/plain")
+        .and_then(text)
+        .map(|plain| code_block("text", &plain))
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.to_owned()),
+        Value::Array(lines) => lines.iter().map(Value::as_str).collect(),
+        _ => None,
+    }
+}
+
+// Images are inlined, as notebooks don't keep them in files
+fn image_tag(mime_type: &str, base64: &str) -> String {
+    format!(
+        "<img class=\"notebook-output\" src=\"data:{};base64,{}\" \
+         alt=\"Output of the code above\" loading=\"lazy\" />",
+        mime_type, base64
+    )
+}
+
+// The fence is longer than any run of backticks in the code, so that the
+// code can't end the block
+fn code_block(language: &str, code: &str) -> String {
+    let longest_run = code
+        .split(|c: char| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+
+    format!("{}{}\n{}\n{}", fence, language, code.trim_end(), fence)
+}
+
+// Tracebacks are colored with ANSI escape sequences, e.g: `\x1b[0;31m`
+fn strip_ansi(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    blog::{BlogConfiguration, PublishableBlogPost},
+    utils::default_empty,
+};
+
+/// Settings of the `[notify]` section of the configuration file
+#[derive(Debug, Default, Deserialize)]
+pub struct NotifyConfiguration {
+    #[serde(default = "default_empty")]
+    pub webhooks: Vec<Webhook>,
+}
+
+/// Webhook pinged with the newly published posts after a build
+#[derive(Debug, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+
+    #[serde(default)]
+    pub format: WebhookFormat,
+
+    /// Sent as a bearer token, e.g: the access token of a Mastodon account
+    pub token: Option<String>,
+}
+
+/// Shape of the JSON body sent to a webhook
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookFormat {
+    /// Blog title and the list of new posts
+    #[default]
+    Json,
+    Discord,
+    Slack,
+    /// Posts a status using Mastodon's statuses API
+    Mastodon,
+}
+
+impl Webhook {
+    /// Builds the JSON body announcing the given posts
+    pub fn payload(&self, posts: &[&PublishableBlogPost], config: &BlogConfiguration) -> Value {
+        match self.format {
+            WebhookFormat::Json => {
+                let posts: Vec<Value> = posts
+                    .iter()
+                    .map(|p| {
+                        json!({
+                            "title": p.post.metadata.title,
+                            "description": p.description,
+                            "url": p.url(config),
+                        })
+                    })
+                    .collect();
+
+                json!({ "blog": config.blog_title, "posts": posts })
+            }
+            WebhookFormat::Discord => json!({ "content": announcement(posts, config) }),
+            WebhookFormat::Slack => json!({ "text": announcement(posts, config) }),
+            WebhookFormat::Mastodon => json!({ "status": announcement(posts, config) }),
+        }
+    }
+}
+
+fn announcement(posts: &[&PublishableBlogPost], config: &BlogConfiguration) -> String {
+    posts
+        .iter()
+        .map(|p| format!("New post: {} {}", p.post.metadata.title, p.url(config)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
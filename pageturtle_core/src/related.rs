@@ -0,0 +1,129 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blog::{BlogConfiguration, PublishableBlogPost};
+
+/// Shortest word of the titles taken into account
+const MIN_WORD_LENGTH: usize = 3;
+
+/// Settings of the `[related_posts]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct RelatedPostsConfiguration {
+    /// Amount of related posts listed after each post, `0` to list none
+    #[serde(default = "default_count")]
+    pub count: usize,
+
+    /// Relates the posts whose titles share words, besides the ones that
+    /// share tags
+    #[serde(default)]
+    pub title_overlap: bool,
+}
+
+impl Default for RelatedPostsConfiguration {
+    fn default() -> Self {
+        RelatedPostsConfiguration {
+            count: default_count(),
+            title_overlap: false,
+        }
+    }
+}
+
+fn default_count() -> usize {
+    3
+}
+
+/// Post listed after another one that it is related to
+#[derive(Debug, Serialize)]
+pub struct RelatedPost {
+    pub title: String,
+    /// Absolute URL of the post's page
+    pub url: String,
+    pub description: String,
+}
+
+/// Lists the posts most related to each post in its `related_posts`. Posts
+/// are related by the tags they share, the tags that fewer posts have
+/// counting more, and optionally by the words their titles share. Posts
+/// that are as related keep the order they are listed in.
+pub fn link_related_posts(posts: &mut [PublishableBlogPost], config: &BlogConfiguration) {
+    let settings = &config.related_posts;
+    if settings.count == 0 {
+        return;
+    }
+
+    let tags: Vec<HashSet<String>> = posts
+        .iter()
+        .map(|p| {
+            p.post
+                .metadata
+                .tags
+                .iter()
+                .map(|tag| config.tags.slug(tag))
+                .collect()
+        })
+        .collect();
+    let titles: Vec<HashSet<String>> = posts
+        .iter()
+        .map(|p| title_words(&p.post.metadata.title))
+        .collect();
+
+    let mut frequencies: HashMap<&str, usize> = HashMap::new();
+    for tag in tags.iter().flatten() {
+        *frequencies.entry(tag).or_insert(0) += 1;
+    }
+    let total = posts.len() as f64;
+
+    let related: Vec<Vec<RelatedPost>> = (0..posts.len())
+        .map(|i| {
+            let mut scores: Vec<(usize, f64)> = (0..posts.len())
+                .filter(|&j| j != i)
+                .map(|j| {
+                    let mut score: f64 = tags[i]
+                        .intersection(&tags[j])
+                        .map(|tag| 1.0 + (total / frequencies[tag.as_str()] as f64).ln())
+                        .sum();
+                    if settings.title_overlap {
+                        score += overlap(&titles[i], &titles[j]);
+                    }
+                    (j, score)
+                })
+                .filter(|(_, score)| *score > 0.0)
+                .collect();
+            scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+            scores
+                .into_iter()
+                .take(settings.count)
+                .map(|(j, _)| RelatedPost {
+                    title: posts[j].post.metadata.title.clone(),
+                    url: posts[j].url(config),
+                    description: posts[j].description.clone(),
+                })
+                .collect()
+        })
+        .collect();
+
+    for (post, related) in posts.iter_mut().zip(related) {
+        post.related_posts = related;
+    }
+}
+
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_WORD_LENGTH)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Share of the words of both titles that they have in common
+fn overlap(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    match a.union(b).count() {
+        0 => 0.0,
+        total => a.intersection(b).count() as f64 / total as f64,
+    }
+}
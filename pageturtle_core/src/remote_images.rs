@@ -0,0 +1,32 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use comrak::nodes::{AstNode, NodeValue};
+
+/// Whether an image is hosted by another site, rather than being a file of
+/// the blog
+pub fn is_remote(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// URLs of the images of a post that are hosted by other sites
+pub fn remote_urls<'a>(ast: &'a AstNode<'a>) -> Vec<String> {
+    ast.descendants()
+        .filter_map(|node| match node.data.borrow().value {
+            NodeValue::Image(ref link) if is_remote(&link.url) => Some(link.url.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Points the remote images of a post to the files they were downloaded to,
+/// so that they are published along with the blog like its own images.
+/// Images that weren't downloaded are left linking to their site.
+pub fn localize<'a>(ast: &'a AstNode<'a>, files: &HashMap<String, PathBuf>) {
+    for node in ast.descendants() {
+        if let NodeValue::Image(ref mut link) = node.data.borrow_mut().value {
+            if let Some(file) = files.get(&link.url) {
+                link.url = file.display().to_string();
+            }
+        }
+    }
+}
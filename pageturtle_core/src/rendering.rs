@@ -1,37 +1,308 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
 use askama::Template;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
 
 use crate::{
     blog::{
-        BlogConfiguration, PublishableBlogPost, TableOfContents, TableOfContentsEntry,
+        AuthorProfile, BlogConfiguration, IndexLayout, PostStatus, PublishableBlogPost,
+        TableOfContents, TableOfContentsEntry, TocPlacement,
     },
+    changelog::Release,
+    external::{self, IndexEntry},
     feed::Feed,
+    resume::Resume,
+    routes,
+    sitemap::Sitemap,
+    talks::TalksByYear,
+    taxonomy::{self, TagEntry},
+    user_templates::{ExternalPostContext, PostContext, SiteContext, TagContext, UserTemplates},
 };
 
+/// Error that happened while rendering a page, along with the template and
+/// the post that were being rendered
+#[derive(Debug)]
+pub struct RenderError {
+    pub template: &'static str,
+    pub post: Option<PathBuf>,
+    pub source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to render {}", self.template)?;
+        if let Some(ref post) = self.post {
+            write!(f, " for {}", post.display())?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+fn render<T: Template>(
+    template: T,
+    name: &'static str,
+    post: Option<&Path>,
+) -> Result<String, RenderError> {
+    template.render().map_err(|source| RenderError {
+        template: name,
+        post: post.map(Path::to_path_buf),
+        source: Box::new(source),
+    })
+}
+
+// Renders a page with the template of the blog that replaces the built-in
+// one. Besides the given context, templates can reach the blog as `site`
+fn render_user_template(
+    mut context: tera::Context,
+    name: &'static str,
+    post: Option<&Path>,
+    templates: &UserTemplates,
+    config: &BlogConfiguration,
+) -> Result<String, RenderError> {
+    context.insert("site", &SiteContext::new(config));
+
+    templates
+        .render(name, &context)
+        .map_err(|source| RenderError {
+            template: name,
+            post: post.map(Path::to_path_buf),
+            source: Box::new(source),
+        })
+}
+
+/// Information about the build that is available to the templates
+#[derive(Serialize)]
+pub struct BuildInfo {
+    /// When the blog was built, in the RFC 3339 format
+    pub time: String,
+    /// Version of pageturtle that built the blog
+    pub version: &'static str,
+}
+
+impl BuildInfo {
+    pub fn now() -> Self {
+        BuildInfo {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+
+    /// Build dated at the publication of the latest post instead of the
+    /// current time, so that the same posts always render the same pages
+    pub fn latest_post(posts: &[PublishableBlogPost], config: &BlogConfiguration) -> Self {
+        let time = posts
+            .iter()
+            .map(|p| p.post.metadata.published_at(config))
+            .max()
+            .map_or_else(
+                || "1970-01-01T00:00:00Z".to_owned(),
+                |t| t.to_rfc3339_opts(SecondsFormat::Secs, true),
+            );
+
+        BuildInfo {
+            time,
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
+/// Open Graph and Twitter Card metadata of a page, shown in the previews of
+/// its links when they are shared
+#[derive(Serialize)]
+pub struct SocialMeta {
+    pub title: String,
+    pub description: Option<String>,
+    /// Absolute URL of the page
+    pub url: String,
+    /// `article` for posts and `website` for the other pages
+    pub kind: &'static str,
+    /// When the post was published, in the RFC 3339 format
+    pub published_time: Option<String>,
+    /// Absolute URL of the image shown in the preview
+    pub image: Option<String>,
+    pub twitter_card: &'static str,
+}
+
+impl SocialMeta {
+    pub fn post(post: &PublishableBlogPost, config: &BlogConfiguration) -> Self {
+        let image = post
+            .cover
+            .as_ref()
+            .map(|cover| format!("{}{}", config.base_url, cover.url()));
+
+        SocialMeta {
+            title: post.post.metadata.title.clone(),
+            description: Some(post.description.clone()),
+            url: post.canonical_url.clone(),
+            kind: "article",
+            published_time: Some(
+                post.post
+                    .metadata
+                    .published_at(config)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            twitter_card: twitter_card(&image),
+            image,
+        }
+    }
+
+    /// Metadata of the pages besides posts, described by the blog's
+    /// description
+    pub fn page(title: String, url: String, config: &BlogConfiguration) -> Self {
+        SocialMeta {
+            title,
+            description: config.blog_description.clone(),
+            url,
+            kind: "website",
+            published_time: None,
+            image: None,
+            twitter_card: twitter_card(&None),
+        }
+    }
+}
+
+// Pages with an image are previewed with it in full
+fn twitter_card(image: &Option<String>) -> &'static str {
+    match image {
+        Some(_) => "summary_large_image",
+        None => "summary",
+    }
+}
+
 #[derive(Template)]
 #[template(path = "tags.html")]
 struct TagsTemplate<'a> {
     config: &'a BlogConfiguration,
-    tags: Vec<&'a String>,
+    tags: Vec<TagEntry<'a>>,
+    social: SocialMeta,
+}
+
+#[derive(Template)]
+#[template(path = "tag.html")]
+struct TagTemplate<'a> {
+    config: &'a BlogConfiguration,
+    tag: &'a TagEntry<'a>,
+    posts: Vec<&'a PublishableBlogPost>,
+    social: SocialMeta,
 }
 
 #[derive(Template)]
-#[template(path = "post.html", escape = "none")]
+#[template(path = "post.html")]
 struct PostTemplate<'a> {
     toc: Option<TocTemplate>,
+    toc_in_sidebar: bool,
     authors: String,
+    author_profiles: Vec<&'a AuthorProfile>,
+    config: &'a BlogConfiguration,
+    post: &'a PublishableBlogPost,
+    previous: Option<&'a PublishableBlogPost>,
+    next: Option<&'a PublishableBlogPost>,
+    social: SocialMeta,
+    section: Option<&'a str>,
+    build: &'a BuildInfo,
+}
+
+#[derive(Template)]
+#[template(path = "social-preview.html")]
+struct SocialPreviewTemplate<'a> {
+    config: &'a BlogConfiguration,
+    post: &'a PublishableBlogPost,
+    url: String,
+}
+
+#[derive(Template)]
+#[template(path = "changelog.html")]
+struct ChangelogTemplate<'a> {
     config: &'a BlogConfiguration,
-    post: &'a PublishableBlogPost<'a>,
+    releases: &'a [Release<'a>],
 }
 
 #[derive(Template)]
-#[template(path = "index.html")]
-struct IndexTemplate<'a> {
+#[template(path = "search.html")]
+struct SearchTemplate<'a> {
     config: &'a BlogConfiguration,
-    posts: &'a Vec<PublishableBlogPost<'a>>,
+    social: SocialMeta,
 }
 
 #[derive(Template)]
-#[template(path = "toc-entry.html", escape = "none")]
+#[template(path = "talks.html")]
+struct TalksTemplate<'a> {
+    config: &'a BlogConfiguration,
+    years: &'a [TalksByYear<'a>],
+}
+
+#[derive(Template)]
+#[template(path = "resume.html")]
+struct ResumeTemplate<'a> {
+    config: &'a BlogConfiguration,
+    resume: &'a Resume,
+}
+
+/// A post listed in the development server's dashboard
+pub struct DashboardEntry<'a> {
+    pub post: &'a PublishableBlogPost,
+    pub status: PostStatus,
+    /// Where the post can be previewed in the development server
+    pub preview_url: String,
+}
+
+#[derive(Template)]
+#[template(path = "dashboard.html")]
+struct DashboardTemplate<'a> {
+    config: &'a BlogConfiguration,
+    entries: &'a [DashboardEntry<'a>],
+}
+
+// Each index layout is an alternate template extending `index.html`
+
+#[derive(Template)]
+#[template(path = "index-list.html")]
+struct IndexListTemplate<'a> {
+    config: &'a BlogConfiguration,
+    entries: Vec<IndexEntry<'a>>,
+    /// Tags shown in the tag filter, if it is enabled
+    tags: Vec<TagEntry<'a>>,
+    canonical_url: String,
+    social: SocialMeta,
+    build: &'a BuildInfo,
+}
+
+#[derive(Template)]
+#[template(path = "index-cards.html")]
+struct IndexCardsTemplate<'a> {
+    config: &'a BlogConfiguration,
+    entries: Vec<IndexEntry<'a>>,
+    /// Tags shown in the tag filter, if it is enabled
+    tags: Vec<TagEntry<'a>>,
+    canonical_url: String,
+    social: SocialMeta,
+    build: &'a BuildInfo,
+}
+
+#[derive(Template)]
+#[template(path = "index-compact.html")]
+struct IndexCompactTemplate<'a> {
+    config: &'a BlogConfiguration,
+    entries: Vec<IndexEntry<'a>>,
+    /// Tags shown in the tag filter, if it is enabled
+    tags: Vec<TagEntry<'a>>,
+    canonical_url: String,
+    social: SocialMeta,
+    build: &'a BuildInfo,
+}
+
+#[derive(Template)]
+#[template(path = "toc-entry.html")]
 struct TocEntryTemplate {
     title: String,
     anchor: String,
@@ -55,7 +326,7 @@ impl TocEntryTemplate {
 }
 
 #[derive(Template)]
-#[template(path = "toc.html", escape = "none")]
+#[template(path = "toc.html")]
 struct TocTemplate {
     entries: Vec<TocEntryTemplate>,
 }
@@ -78,35 +349,115 @@ struct FeedTemplate<'a> {
     feed: &'a Feed<'a>,
 }
 
+#[derive(Template)]
+#[template(path = "sitemap.xml")]
+struct SitemapTemplate<'a> {
+    sitemap: &'a Sitemap,
+}
+
 pub fn render_tags_page(
-    posts: &Vec<PublishableBlogPost<'_>>,
+    posts: &[PublishableBlogPost],
     config: &BlogConfiguration,
-) -> String {
-    let mut all_tags = Vec::new();
+) -> Result<String, RenderError> {
+    let tags = taxonomy::collect_tags(posts, config);
+    let social = SocialMeta::page(
+        format!("Tags - {}", config.blog_title),
+        format!("{}/{}", config.base_url, routes::TAGS_PAGE),
+        config,
+    );
 
-    for post in posts {
-        all_tags.extend(&post.post.metadata.tags);
+    if config.templates.overrides("tags.html") {
+        let mut context = tera::Context::new();
+        let tags: Vec<TagContext> = tags.iter().map(|t| TagContext::new(t, config)).collect();
+        context.insert("tags", &tags);
+        context.insert("social", &social);
+        return render_user_template(context, "tags.html", None, &config.templates, config);
     }
 
-    TagsTemplate {
+    render(
+        TagsTemplate {
+            config,
+            tags,
+            social,
+        },
+        "tags.html",
+        None,
+    )
+}
+
+/// Renders the page listing the posts that carry a tag
+pub fn render_tag_page<'a>(
+    tag: &'a TagEntry<'a>,
+    posts: &'a [PublishableBlogPost],
+    config: &'a BlogConfiguration,
+) -> Result<String, RenderError> {
+    let posts = taxonomy::tagged_posts(&tag.slug, posts, config);
+    let social = SocialMeta::page(
+        format!("{} - {}", tag.title, config.blog_title),
+        tag.url(config),
         config,
-        tags: all_tags,
+    );
+
+    if config.templates.overrides("tag.html") {
+        let mut context = tera::Context::new();
+        context.insert("tag", &TagContext::new(tag, config));
+        context.insert("posts", &summaries(&posts, config));
+        context.insert("social", &social);
+        return render_user_template(context, "tag.html", None, &config.templates, config);
     }
-    .render()
-    .unwrap()
+
+    render(
+        TagTemplate {
+            config,
+            tag,
+            posts,
+            social,
+        },
+        "tag.html",
+        None,
+    )
 }
 
+/// Renders a post page. `previous` and `next` are the posts published right
+/// before and after it, which are linked in the page's head
 pub fn render_post_page<'a>(
-    post: &'a PublishableBlogPost<'a>,
+    post: &'a PublishableBlogPost,
+    previous: Option<&'a PublishableBlogPost>,
+    next: Option<&'a PublishableBlogPost>,
     config: &'a BlogConfiguration,
-) -> String {
-    let authors = post
-        .post
-        .metadata
-        .authors
-        .as_ref()
-        .map(|v| v.join(", "))
-        .unwrap_or(config.author.clone());
+    build: &'a BuildInfo,
+) -> Result<String, RenderError> {
+    let authors = config.post_authors(&post.post.metadata).join(", ");
+
+    // Posts of sections with templates of their own are rendered with them
+    let templates = post
+        .section()
+        .and_then(|name| config.sections.get(name))
+        .map(|section| &section.loaded_templates)
+        .filter(|templates| templates.overrides("post.html"))
+        .unwrap_or(&config.templates);
+
+    if templates.overrides("post.html") {
+        let mut context = tera::Context::new();
+        context.insert("post", &PostContext::full(post, config));
+        context.insert("authors", &authors);
+        context.insert(
+            "previous",
+            &previous.map(|p| PostContext::summary(p, config)),
+        );
+        context.insert("next", &next.map(|p| PostContext::summary(p, config)));
+        context.insert("social", &SocialMeta::post(post, config));
+        context.insert("build", build);
+        return render_user_template(
+            context,
+            "post.html",
+            Some(&post.filepath),
+            templates,
+            config,
+        );
+    }
+
+    let author_profiles = config.post_author_profiles(&post.post.metadata);
 
     let toc = if post.post.metadata.table_of_contents {
         Some(TocTemplate::from_toc(&post.post.toc))
@@ -114,21 +465,163 @@ pub fn render_post_page<'a>(
         None
     };
 
-    PostTemplate {
+    let toc_placement = post
+        .post
+        .metadata
+        .toc_placement
+        .unwrap_or(config.toc_placement);
+
+    let template = PostTemplate {
+        toc_in_sidebar: toc_placement == TocPlacement::Sidebar,
         authors,
+        author_profiles,
         post,
         config,
         toc,
-    }
-    .render()
-    .unwrap()
+        previous,
+        next,
+        social: SocialMeta::post(post, config),
+        section: post.section(),
+        build,
+    };
+
+    render(template, "post.html", Some(&post.filepath))
+}
+
+/// Renders a page showing how a post will look when shared in social media,
+/// used by the development server
+pub fn render_social_preview<'a>(
+    post: &'a PublishableBlogPost,
+    config: &'a BlogConfiguration,
+) -> Result<String, RenderError> {
+    let url = post.url(config);
+
+    render(
+        SocialPreviewTemplate { config, post, url },
+        "social-preview.html",
+        Some(&post.filepath),
+    )
+}
+
+/// Renders a page listing all posts along with their status, used by the
+/// development server
+pub fn render_dashboard<'a>(
+    entries: &'a [DashboardEntry<'a>],
+    config: &'a BlogConfiguration,
+) -> Result<String, RenderError> {
+    render(
+        DashboardTemplate { config, entries },
+        "dashboard.html",
+        None,
+    )
 }
 
 pub fn render_index<'a>(
-    posts: &'a Vec<PublishableBlogPost<'a>>,
+    posts: &'a [PublishableBlogPost],
+    config: &'a BlogConfiguration,
+    build: &'a BuildInfo,
+) -> Result<String, RenderError> {
+    let page_url = routes::index_url(config);
+    let social = SocialMeta::page(config.blog_title.clone(), page_url.clone(), config);
+
+    if config.templates.overrides("index.html") {
+        let mut context = tera::Context::new();
+        let listed: Vec<&PublishableBlogPost> = posts.iter().collect();
+        context.insert("posts", &summaries(&listed, config));
+        context.insert("external_posts", &ExternalPostContext::list(config));
+        context.insert("social", &social);
+        context.insert("build", build);
+
+        // The tags that the index can be filtered by, if any
+        let tags = match config.tag_filter {
+            true => taxonomy::collect_tags(posts, config),
+            false => vec![],
+        };
+        let tags: Vec<TagContext> = tags.iter().map(|t| TagContext::new(t, config)).collect();
+        context.insert("tags", &tags);
+
+        return render_user_template(context, "index.html", None, &config.templates, config);
+    }
+
+    let canonical_url = page_url;
+    let entries = external::index_entries(posts, config);
+    let tags = match config.tag_filter {
+        true => taxonomy::collect_tags(posts, config),
+        false => vec![],
+    };
+
+    match config.index_layout {
+        IndexLayout::List => render(
+            IndexListTemplate {
+                entries,
+                config,
+                tags,
+                canonical_url,
+                social,
+                build,
+            },
+            "index-list.html",
+            None,
+        ),
+        IndexLayout::Cards => render(
+            IndexCardsTemplate {
+                entries,
+                config,
+                tags,
+                canonical_url,
+                social,
+                build,
+            },
+            "index-cards.html",
+            None,
+        ),
+        IndexLayout::Compact => render(
+            IndexCompactTemplate {
+                entries,
+                config,
+                tags,
+                canonical_url,
+                social,
+                build,
+            },
+            "index-compact.html",
+            None,
+        ),
+    }
+}
+
+pub fn render_changelog<'a>(
+    releases: &'a [Release<'a>],
+    config: &'a BlogConfiguration,
+) -> Result<String, RenderError> {
+    render(
+        ChangelogTemplate { config, releases },
+        "changelog.html",
+        None,
+    )
+}
+
+pub fn render_talks<'a>(
+    years: &'a [TalksByYear<'a>],
     config: &'a BlogConfiguration,
-) -> String {
-    IndexTemplate { posts, config }.render().unwrap()
+) -> Result<String, RenderError> {
+    render(TalksTemplate { config, years }, "talks.html", None)
+}
+
+/// Renders the search page, which searches the posts of `search-index.json`
+/// in the browser
+pub fn render_search_page(config: &BlogConfiguration) -> Result<String, RenderError> {
+    let social = SocialMeta::page(
+        format!("Search - {}", config.blog_title),
+        format!("{}/{}", config.base_url, routes::SEARCH_PAGE),
+        config,
+    );
+
+    render(SearchTemplate { config, social }, "search.html", None)
+}
+
+pub fn render_resume(resume: &Resume, config: &BlogConfiguration) -> Result<String, RenderError> {
+    render(ResumeTemplate { config, resume }, "resume.html", None)
 }
 
 pub fn stylesheet() -> String {
@@ -136,6 +629,37 @@ pub fn stylesheet() -> String {
     String::from_utf8(styles_bytes.to_vec()).unwrap()
 }
 
-pub fn render_feed<'a>(feed: &'a Feed<'a>) -> String {
-    FeedTemplate { feed }.render().unwrap()
+/// Script enhancing the post pages, see [`NavigationConfiguration`]
+///
+/// [`NavigationConfiguration`]: crate::blog::NavigationConfiguration
+pub fn navigation_script() -> &'static str {
+    include_str!("../assets/navigation.js")
+}
+
+/// Script of the search page
+pub fn search_script() -> &'static str {
+    include_str!("../assets/search.js")
+}
+
+/// XSL stylesheet that makes the feed readable when opened in a browser
+pub fn feed_stylesheet() -> &'static str {
+    include_str!("../assets/feed.xsl")
+}
+
+pub fn render_feed<'a>(feed: &'a Feed<'a>) -> Result<String, RenderError> {
+    render(FeedTemplate { feed }, "atom.xml", None)
+}
+
+pub fn render_sitemap(sitemap: &Sitemap) -> Result<String, RenderError> {
+    render(SitemapTemplate { sitemap }, "sitemap.xml", None)
+}
+
+fn summaries<'a>(
+    posts: &[&'a PublishableBlogPost],
+    config: &BlogConfiguration,
+) -> Vec<PostContext<'a>> {
+    posts
+        .iter()
+        .map(|p| PostContext::summary(p, config))
+        .collect()
 }
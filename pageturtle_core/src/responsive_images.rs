@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use image::{imageops::FilterType, ImageResult};
+use serde::Deserialize;
+
+use crate::{urls, utils::default_true};
+
+/// Settings of the `[responsive_images]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct ResponsiveImagesConfiguration {
+    /// Generates smaller versions of the images of the posts, which browsers
+    /// pick from depending on the size of the screen
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Widths of the generated versions. Images are never enlarged, so only
+    /// the widths smaller than the image's own width are generated
+    #[serde(default = "default_widths")]
+    pub widths: Vec<u32>,
+
+    /// How wide images are shown in the page, given to browsers so that they
+    /// can pick a version before the page is laid out
+    #[serde(default = "default_sizes")]
+    pub sizes: String,
+}
+
+impl Default for ResponsiveImagesConfiguration {
+    fn default() -> Self {
+        ResponsiveImagesConfiguration {
+            enabled: true,
+            widths: default_widths(),
+            sizes: default_sizes(),
+        }
+    }
+}
+
+fn default_widths() -> Vec<u32> {
+    vec![480, 960, 1440]
+}
+
+fn default_sizes() -> String {
+    "(min-width: 768px) 65ch, 100vw".to_owned()
+}
+
+/// Smaller version of an image, generated when building the blog
+#[derive(Debug)]
+pub struct ImageVariant {
+    /// The final path of the version, relative to the images directory
+    /// (e.g: 480w-3f2a9c0d1b4e5a67-my-tour.png). As the image's path, it is
+    /// named after the image's content, so it is only generated once
+    pub final_path: PathBuf,
+    pub width: u32,
+}
+
+impl ImageVariant {
+    /// URL of the version in the published blog
+    pub fn url(&self) -> String {
+        format!("/img/{}", urls::from_path(&self.final_path))
+    }
+}
+
+/// Versions of an image, given its final path and width, that should be
+/// generated
+pub fn variants(
+    final_path: &Path,
+    width: u32,
+    config: &ResponsiveImagesConfiguration,
+) -> Vec<ImageVariant> {
+    if !config.enabled {
+        return vec![];
+    }
+
+    let mut widths: Vec<u32> = config
+        .widths
+        .iter()
+        .copied()
+        .filter(|w| *w < width)
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+
+    widths
+        .into_iter()
+        .map(|w| ImageVariant {
+            final_path: format!("{}w-{}", w, final_path.display()).into(),
+            width: w,
+        })
+        .collect()
+}
+
+/// Writes an image in the format of the destination's extension, e.g: WebP
+pub fn convert(source: &Path, destination: &Path) -> ImageResult<()> {
+    image::open(source)?.save(destination)
+}
+
+/// Resizes an image to the given width, keeping its aspect ratio and writing
+/// it in the format of the destination's extension
+pub fn generate(source: &Path, destination: &Path, width: u32) -> ImageResult<()> {
+    image::open(source)?
+        .resize(width, u32::MAX, FilterType::Lanczos3)
+        .save(destination)
+}
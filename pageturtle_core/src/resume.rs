@@ -0,0 +1,76 @@
+use serde::Deserialize;
+
+use crate::utils::default_empty;
+
+/// Resume following the JSON Resume schema (https://jsonresume.org/schema),
+/// read from `data/resume.yaml` or `data/resume.json`. Only the sections
+/// rendered in the resume page are parsed.
+#[derive(Debug, Deserialize)]
+pub struct Resume {
+    pub basics: Basics,
+
+    #[serde(default = "default_empty")]
+    pub work: Vec<Work>,
+
+    #[serde(default = "default_empty")]
+    pub education: Vec<Education>,
+
+    #[serde(default = "default_empty")]
+    pub skills: Vec<Skill>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Basics {
+    pub name: String,
+    pub label: Option<String>,
+    pub email: Option<String>,
+    pub url: Option<String>,
+    pub summary: Option<String>,
+
+    #[serde(default = "default_empty")]
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Profile {
+    pub network: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Work {
+    pub name: String,
+    pub position: String,
+    pub url: Option<String>,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    pub summary: Option<String>,
+
+    #[serde(default = "default_empty")]
+    pub highlights: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Education {
+    pub institution: String,
+    pub area: Option<String>,
+    pub study_type: Option<String>,
+    pub start_date: String,
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Skill {
+    pub name: String,
+
+    #[serde(default = "default_empty")]
+    pub keywords: Vec<String>,
+}
+
+/// Parses a resume written in YAML or JSON
+pub fn parse_resume(content: &str) -> Result<Resume, serde_yaml::Error> {
+    // JSON documents are also valid YAML
+    serde_yaml::from_str(content)
+}
@@ -0,0 +1,121 @@
+use chrono::SecondsFormat;
+
+use crate::{
+    blog::{BlogConfiguration, PublishableBlogPost},
+    taxonomy::{self, TagEntry},
+};
+
+pub const INDEX_PAGE: &str = "index.html";
+pub const TAGS_PAGE: &str = "tags.html";
+pub const SEARCH_PAGE: &str = "search.html";
+/// Feed of the blog's posts, along with the aliases it may have
+pub const FEED_FILE: &str = "atom.xml";
+
+/// What a page of the blog is rendered from
+#[derive(Debug)]
+pub enum Page<'a> {
+    Index,
+    Post(&'a PublishableBlogPost),
+    Tags,
+    Tag(TagEntry<'a>),
+    Search,
+}
+
+/// Page that the blog publishes. The pages that are built, listed in the
+/// sitemap and that posts may link to all come from the blog's routes
+#[derive(Debug)]
+pub struct Route<'a> {
+    /// Path of the page relative to the output directory, e.g:
+    /// `tags/rust.html`
+    pub path: String,
+    pub url: String,
+    /// Path of the feed with the page's posts relative to the output
+    /// directory, if it has one
+    pub feed: Option<String>,
+    /// W3C formatted date of the page's last modification, if known
+    pub lastmod: Option<String>,
+    pub page: Page<'a>,
+}
+
+impl<'a> Route<'a> {
+    /// Whether search engines are told about the page. The search page has
+    /// nothing to index by itself
+    pub fn in_sitemap(&self) -> bool {
+        !matches!(self.page, Page::Search)
+    }
+}
+
+/// Canonical URL of the index, which is the root of the blog rather than
+/// its `index.html`
+pub fn index_url(config: &BlogConfiguration) -> String {
+    format!("{}/", config.base_url)
+}
+
+/// Routes of the pages generated from the posts: the index, the posts, the
+/// tags page along with a page for each tag and the search page, when the
+/// blog has one
+pub fn collect_routes<'a>(
+    posts: &'a [PublishableBlogPost],
+    config: &'a BlogConfiguration,
+) -> Vec<Route<'a>> {
+    let post_routes: Vec<Route> = posts
+        .iter()
+        .map(|post| Route {
+            path: post.url_path(),
+            url: post.canonical_url.clone(),
+            feed: None,
+            lastmod: Some(
+                post.post
+                    .metadata
+                    .published_at(config)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            page: Page::Post(post),
+        })
+        .collect();
+
+    // The index changes whenever a post is published
+    let latest = post_routes.iter().filter_map(|r| r.lastmod.clone()).max();
+
+    let mut routes = vec![
+        Route {
+            path: INDEX_PAGE.to_owned(),
+            url: index_url(config),
+            feed: config.enable_rss.then(|| FEED_FILE.to_owned()),
+            lastmod: latest,
+            page: Page::Index,
+        },
+        Route {
+            path: TAGS_PAGE.to_owned(),
+            url: format!("{}/{}", config.base_url, TAGS_PAGE),
+            feed: None,
+            lastmod: None,
+            page: Page::Tags,
+        },
+    ];
+
+    routes.extend(
+        taxonomy::collect_tags(posts, config)
+            .into_iter()
+            .map(|tag| Route {
+                path: tag.path(),
+                url: tag.url(config),
+                feed: tag.feed_path(config),
+                lastmod: None,
+                page: Page::Tag(tag),
+            }),
+    );
+    routes.extend(post_routes);
+
+    if config.enable_search {
+        routes.push(Route {
+            path: SEARCH_PAGE.to_owned(),
+            url: format!("{}/{}", config.base_url, SEARCH_PAGE),
+            feed: None,
+            lastmod: None,
+            page: Page::Search,
+        });
+    }
+
+    routes
+}
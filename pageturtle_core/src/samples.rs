@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use comrak::{
+    nodes::{AstNode, NodeValue},
+    parse_document, Arena, ComrakOptions,
+};
+use serde::Deserialize;
+
+use crate::{
+    blog::{parse_frontmatter, BlogConfiguration},
+    defaults::FrontmatterDefaults,
+    highlighting::CodeBlockMeta,
+};
+
+/// Commands that run the samples of each language by default. Samples are
+/// given to them on their standard input
+const DEFAULT_RUNNERS: &[(&str, &str)] = &[
+    ("bash", "bash -s"),
+    ("javascript", "node -"),
+    ("js", "node -"),
+    ("python", "python3 -"),
+    ("ruby", "ruby -"),
+    ("rust", "rustc --edition 2021 -o sample - && ./sample"),
+    ("sh", "sh -s"),
+];
+
+/// Language of the code blocks holding the output expected from the sample
+/// right before them
+const OUTPUT_LANGUAGE: &str = "output";
+
+/// Settings of the `[samples]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct SamplesConfiguration {
+    /// Commands that run the samples of each language, e.g:
+    /// `python = "python3.11 -"`, given the sample on their standard input.
+    /// They take precedence over the default ones
+    #[serde(default)]
+    pub runners: HashMap<String, String>,
+
+    /// Seconds that a sample may run for before it is stopped
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for SamplesConfiguration {
+    fn default() -> Self {
+        SamplesConfiguration {
+            runners: HashMap::new(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl SamplesConfiguration {
+    /// Command that runs the samples of a language, if there is one
+    pub fn runner(&self, language: &str) -> Option<&str> {
+        self.runners.get(language).map(String::as_str).or_else(|| {
+            DEFAULT_RUNNERS
+                .iter()
+                .find(|(l, _)| *l == language)
+                .map(|(_, runner)| *runner)
+        })
+    }
+}
+
+/// Code block of a post flagged with `run`, e.g: ```` ```python run ````
+#[derive(Debug)]
+pub struct CodeSample {
+    pub language: String,
+    pub code: String,
+    /// Contents of the `output` code block right after the sample, if any
+    pub expected_output: Option<String>,
+    /// Line of the post where the sample starts
+    pub line: usize,
+}
+
+impl CodeSample {
+    /// Whether the sample printed what it was expected to. Trailing
+    /// whitespace is ignored, as it can't be seen in the post
+    pub fn matches(&self, output: &str) -> bool {
+        let lines = |text: &str| -> Vec<String> {
+            text.trim_end()
+                .lines()
+                .map(|l| l.trim_end().to_owned())
+                .collect()
+        };
+
+        self.expected_output
+            .as_deref()
+            .is_none_or(|expected| lines(expected) == lines(output))
+    }
+}
+
+/// Extracts the code samples of a post, in the order they appear
+pub fn extract_samples(
+    content: &str,
+    config: &BlogConfiguration,
+    defaults: &FrontmatterDefaults,
+) -> Result<Vec<CodeSample>, String> {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_owned());
+
+    let arena = Arena::new();
+    let ast = parse_document(&arena, content, &options);
+    parse_frontmatter(ast, config, defaults)?;
+
+    let mut samples = Vec::new();
+
+    for node in ast.descendants() {
+        let (info, code) = match code_block(node) {
+            Some(block) => block,
+            None => continue,
+        };
+        if !CodeBlockMeta::parse(&info).run {
+            continue;
+        }
+
+        let expected_output = node
+            .next_sibling()
+            .and_then(code_block)
+            .filter(|(info, _)| language(info) == OUTPUT_LANGUAGE)
+            .map(|(_, output)| output);
+
+        samples.push(CodeSample {
+            language: language(&info).to_owned(),
+            code,
+            expected_output,
+            line: node.data.borrow().sourcepos.start.line,
+        });
+    }
+
+    Ok(samples)
+}
+
+// Info string and contents of a code block
+fn code_block<'a>(node: &'a AstNode<'a>) -> Option<(String, String)> {
+    match node.data.borrow().value {
+        NodeValue::CodeBlock(ref block) => Some((block.info.clone(), block.literal.clone())),
+        _ => None,
+    }
+}
+
+fn language(info: &str) -> &str {
+    info.split_whitespace().next().unwrap_or_default()
+}
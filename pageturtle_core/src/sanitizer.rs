@@ -0,0 +1,457 @@
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Deserialize;
+
+use crate::blog::EXCERPT_MARKER;
+
+/// Elements that are always allowed, as markdown renders to them anyway
+const DEFAULT_ELEMENTS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "del",
+    "div",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "s",
+    "span",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+const DEFAULT_ATTRIBUTES: &[&str] = &[
+    "alt", "class", "decoding", "height", "href", "id", "loading", "src", "title", "width",
+];
+
+/// Attributes whose values are URLs, which can run scripts
+const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "formaction",
+    "href",
+    "poster",
+    "src",
+    "xlink:href",
+];
+
+/// What markdown renders the HTML of posts to when it isn't allowed
+const RAW_HTML_OMITTED: &str = "<!-- raw HTML omitted -->";
+
+const SAFE_SCHEMES: &[&str] = &["http:", "https:", "mailto:", "tel:", "data:image/"];
+
+/// Elements whose contents are dropped along with them, instead of being
+/// kept as text
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// Settings of the `[sanitizer]` section of the configuration file
+#[derive(Debug, Default, Deserialize)]
+pub struct SanitizerConfiguration {
+    /// Strips the HTML of the posts that isn't allowed, e.g: for blogs with
+    /// several authors
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Elements allowed besides the ones that markdown renders to, e.g:
+    /// `video`, `details`, web components such as `my-chart` or `iframe` for
+    /// the embeds of shortcodes
+    #[serde(default)]
+    pub allowed_elements: Vec<String>,
+
+    /// Attributes allowed besides the basic ones, e.g: `href` and `src`.
+    /// Event handlers, e.g: `onclick`, are never allowed
+    #[serde(default)]
+    pub allowed_attributes: Vec<String>,
+}
+
+impl SanitizerConfiguration {
+    fn allows_element(&self, name: &str) -> bool {
+        DEFAULT_ELEMENTS.contains(&name)
+            || self
+                .allowed_elements
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(name))
+    }
+
+    fn allows_attribute(&self, name: &str) -> bool {
+        !name.starts_with("on")
+            && (DEFAULT_ATTRIBUTES.contains(&name)
+                || self
+                    .allowed_attributes
+                    .iter()
+                    .any(|a| a.eq_ignore_ascii_case(name)))
+    }
+}
+
+/// Strips the elements and attributes that aren't allowed from the HTML
+/// written in the post, along with links that would run scripts. Does
+/// nothing unless the sanitizer is enabled.
+pub fn sanitize<'a>(ast: &'a AstNode<'a>, config: &SanitizerConfiguration) {
+    if !config.enabled {
+        return;
+    }
+
+    for node in ast.descendants() {
+        match node.data.borrow_mut().value {
+            NodeValue::HtmlBlock(ref mut block) => {
+                block.literal = sanitize_html(&block.literal, config);
+            }
+            NodeValue::HtmlInline(ref mut html) => *html = sanitize_html(html, config),
+            NodeValue::Link(ref mut link) | NodeValue::Image(ref mut link) => {
+                if is_dangerous_url(&link.url) {
+                    link.url.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the HTML written in the post with a comment, keeping the excerpt
+/// marker, and clears the links that would run scripts. Used when neither
+/// `raw_html` nor the sanitizer are enabled.
+pub fn omit_raw_html<'a>(ast: &'a AstNode<'a>) {
+    for node in ast.descendants() {
+        match node.data.borrow_mut().value {
+            NodeValue::HtmlBlock(ref mut block) if block.literal.trim() != EXCERPT_MARKER => {
+                block.literal = format!("{}\n", RAW_HTML_OMITTED);
+            }
+            NodeValue::HtmlInline(ref mut html) => *html = RAW_HTML_OMITTED.to_owned(),
+            NodeValue::Link(ref mut link) | NodeValue::Image(ref mut link) => {
+                if is_dangerous_url(&link.url) {
+                    link.url.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn sanitize_html(html: &str, config: &SanitizerConfiguration) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // Only the excerpt marker is kept of the comments. Others are
+        // dropped, as browsers end them in more ways than `-->`
+        if rest.starts_with(EXCERPT_MARKER) {
+            output.push_str(EXCERPT_MARKER);
+            rest = &rest[EXCERPT_MARKER.len()..];
+            continue;
+        }
+        if rest.starts_with("<!--") {
+            rest = &rest[comment_length(rest)..];
+            continue;
+        }
+
+        let tag = match parse_tag(rest) {
+            Some(tag) => tag,
+            // e.g: `a < b`, or a tag that is never closed
+            None => {
+                output.push_str("&lt;");
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        rest = &rest[tag.length..];
+
+        if config.allows_element(&tag.name) {
+            output.push_str(&tag.to_html(config));
+        } else if !tag.closing && RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) {
+            let closing = format!("</{}", tag.name);
+            rest = match rest.to_ascii_lowercase().find(&closing) {
+                Some(i) => rest[i..].split_once('>').map_or("", |(_, after)| after),
+                None => "",
+            };
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Length of the comment at the start of the text, up to the first of the
+// ways that browsers end it: `-->`, `--!>`, or right away with `<!-->` and
+// `<!--->`
+fn comment_length(text: &str) -> usize {
+    if text.starts_with("<!-->") {
+        return 5;
+    } else if text.starts_with("<!--->") {
+        return 6;
+    }
+
+    ["-->", "--!>"]
+        .iter()
+        .filter_map(|end| text[4..].find(end).map(|i| 4 + i + end.len()))
+        .min()
+        .unwrap_or(text.len())
+}
+
+pub(crate) struct Tag {
+    /// Lowercase name of the element
+    pub name: String,
+    pub closing: bool,
+    pub self_closing: bool,
+    pub attributes: Vec<(String, Option<String>)>,
+    /// Length of the tag in the text it was parsed from
+    pub length: usize,
+}
+
+impl Tag {
+    // Writes the tag back, with the attributes that are allowed
+    fn to_html(&self, config: &SanitizerConfiguration) -> String {
+        if self.closing {
+            return format!("</{}>", self.name);
+        }
+
+        let mut html = format!("<{}", self.name);
+
+        for (name, value) in &self.attributes {
+            if !config.allows_attribute(name) {
+                continue;
+            }
+
+            match value {
+                Some(v) if URL_ATTRIBUTES.contains(&name.as_str()) && is_dangerous_url(v) => {}
+                Some(v) => html.push_str(&format!(" {}=\"{}\"", name, v.replace('"', "&quot;"))),
+                None => html.push_str(&format!(" {}", name)),
+            }
+        }
+
+        html.push_str(if self.self_closing { " />" } else { ">" });
+        html
+    }
+}
+
+// Parses the tag at the start of the text, e.g: `<video controls src="a.mp4">`
+pub(crate) fn parse_tag(text: &str) -> Option<Tag> {
+    let mut i = 1;
+    let closing = text[i..].starts_with('/');
+    if closing {
+        i += 1;
+    }
+
+    if !text[i..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name_length = text[i..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(text.len() - i);
+    let name = text[i..i + name_length].to_ascii_lowercase();
+    i += name_length;
+
+    let mut self_closing = false;
+    let mut attributes = Vec::new();
+
+    loop {
+        i += whitespace_length(&text[i..]);
+        let rest = &text[i..];
+
+        if rest.is_empty() {
+            return None;
+        } else if rest.starts_with('>') {
+            i += 1;
+            break;
+        } else if rest.starts_with("/>") {
+            self_closing = true;
+            i += 2;
+            break;
+        }
+
+        let name_length = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        if name_length == 0 {
+            // e.g: a stray `/` or `=`
+            i += 1;
+            continue;
+        }
+        let attribute = rest[..name_length].to_ascii_lowercase();
+        i += name_length;
+
+        let after_name = whitespace_length(&text[i..]);
+        if !text[i + after_name..].starts_with('=') {
+            attributes.push((attribute, None));
+            continue;
+        }
+        i += after_name + 1;
+        i += whitespace_length(&text[i..]);
+
+        let rest = &text[i..];
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let end = rest[1..].find(quote)?;
+                i += end + 2;
+                &rest[1..end + 1]
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                i += end;
+                &rest[..end]
+            }
+        };
+        attributes.push((attribute, Some(value.to_owned())));
+    }
+
+    Some(Tag {
+        name,
+        closing,
+        self_closing,
+        attributes,
+        length: i,
+    })
+}
+
+fn whitespace_length(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+// Whether following the URL could run a script, e.g: `javascript:alert(1)`.
+// URLs without a scheme are relative, so they are safe.
+fn is_dangerous_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    // Browsers decode entities in attributes, e.g: `javascript&#58;`
+    let scheme_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+    let has_scheme = url[..scheme_end].contains([':', '&']);
+
+    has_scheme && !SAFE_SCHEMES.iter().any(|s| url.starts_with(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize(html: &str) -> String {
+        let config = SanitizerConfiguration {
+            enabled: true,
+            ..Default::default()
+        };
+        sanitize_html(html, &config)
+    }
+
+    #[test]
+    fn keeps_the_excerpt_marker() {
+        assert_eq!(sanitize("<!-- more -->"), "<!-- more -->");
+    }
+
+    #[test]
+    fn drops_comments() {
+        assert_eq!(sanitize("a<!-- note -->b"), "ab");
+        assert_eq!(sanitize("<!-- never closed"), "");
+    }
+
+    #[test]
+    fn drops_comments_ended_like_browsers_do() {
+        let html = "<!-- --!><img src=x onerror=alert(1)>-->";
+        assert_eq!(sanitize(html), "<img src=\"x\">-->");
+
+        assert_eq!(
+            sanitize("<!--><img src=x onerror=alert(1)>"),
+            "<img src=\"x\">"
+        );
+        assert_eq!(
+            sanitize("<!---><img src=x onerror=alert(1)>"),
+            "<img src=\"x\">"
+        );
+    }
+
+    #[test]
+    fn drops_urls_with_encoded_schemes() {
+        assert_eq!(
+            sanitize("<a href=\"javascript&#58;alert(1)\">x</a>"),
+            "<a>x</a>"
+        );
+        assert_eq!(
+            sanitize("<a href=\"JaVaScRiPt:alert(1)\">x</a>"),
+            "<a>x</a>"
+        );
+        assert_eq!(
+            sanitize("<a href=\"java\tscript:alert(1)\">x</a>"),
+            "<a>x</a>"
+        );
+    }
+
+    #[test]
+    fn drops_event_handlers_in_any_case() {
+        assert_eq!(
+            sanitize("<img src=\"a.png\" OnErRoR=\"alert(1)\">"),
+            "<img src=\"a.png\">"
+        );
+        assert_eq!(sanitize("<p ONCLICK=alert(1)>x</p>"), "<p>x</p>");
+    }
+
+    #[test]
+    fn drops_event_handlers_even_when_allowed() {
+        let config = SanitizerConfiguration {
+            enabled: true,
+            allowed_attributes: vec!["onclick".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            sanitize_html("<p onclick=alert(1)>x</p>", &config),
+            "<p>x</p>"
+        );
+    }
+
+    #[test]
+    fn drops_scripts_with_their_contents() {
+        assert_eq!(sanitize("a<SCRIPT>alert(1)</script>b"), "ab");
+    }
+
+    #[test]
+    fn omits_raw_html_but_the_excerpt_marker() {
+        let arena = comrak::Arena::new();
+        let options = crate::blog::markdown_options();
+        let markdown =
+            "<div>a</div>\n\n<!-- more -->\n\nSome <b>text</b> [x](javascript:alert(1))\n";
+        let ast = comrak::parse_document(&arena, markdown, &options);
+        omit_raw_html(ast);
+
+        let mut html = vec![];
+        comrak::format_html(ast, &options, &mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+
+        assert!(html.contains("<!-- more -->"), "{}", html);
+        assert!(html.contains("<!-- raw HTML omitted -->"), "{}", html);
+        for omitted in ["<div>", "<b>", "javascript"] {
+            assert!(!html.contains(omitted), "{}", html);
+        }
+    }
+}
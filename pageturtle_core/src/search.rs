@@ -0,0 +1,58 @@
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Serialize;
+
+use crate::blog::{BlogConfiguration, PublishableBlogPost};
+
+/// Post as indexed for the search page, published in `search-index.json`
+#[derive(Serialize)]
+pub struct SearchDocument<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    pub tags: Vec<&'a str>,
+    pub url: String,
+    /// Plain text of the post, without its code blocks
+    pub text: &'a str,
+}
+
+/// Index of the posts that the search page searches through in the
+/// readers' browsers, so that the blog can be searched without a server
+pub fn build_search_index<'a>(
+    posts: &'a [PublishableBlogPost],
+    config: &BlogConfiguration,
+) -> Vec<SearchDocument<'a>> {
+    posts
+        .iter()
+        .map(|post| SearchDocument {
+            title: &post.post.metadata.title,
+            description: &post.description,
+            tags: post
+                .post
+                .metadata
+                .tags
+                .iter()
+                .map(|tag| config.tags.title(tag))
+                .collect(),
+            url: post.url(config),
+            text: post.search_text.as_deref().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Text of a post that is searched, with its blocks separated by spaces.
+/// Code blocks are left out, as they are rarely what readers search for
+pub(crate) fn searchable_text<'a>(ast: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+
+    for node in ast.descendants() {
+        let value = &node.data.borrow().value;
+        match value {
+            NodeValue::Text(ref t) => text.push_str(t),
+            NodeValue::Code(ref c) => text.push_str(&c.literal),
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            _ if value.block() && !text.ends_with(' ') && !text.is_empty() => text.push(' '),
+            _ => {}
+        }
+    }
+
+    text.trim_end().to_owned()
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use crate::utils::escape_html;
+
+/// Arguments given to a shortcode, either by position, e.g:
+/// `{{< youtube dQw4w9WgXcQ >}}`, or by name, e.g: `{{ youtube(id="dQw4w9WgXcQ") }}`
+#[derive(Debug, Default)]
+struct Arguments {
+    positional: Vec<String>,
+    named: HashMap<String, String>,
+}
+
+impl Arguments {
+    fn get(&self, name: &str, position: usize) -> Option<&str> {
+        self.named
+            .get(name)
+            .or_else(|| self.positional.get(position))
+            .map(String::as_str)
+    }
+}
+
+/// Expands the Hugo (`{{< name args >}}`, `{{% name args %}}`) and Zola
+/// (`{{ name(args) }}`) shortcodes supported by pageturtle into markdown and
+/// HTML, so that posts migrated from these generators render without being
+/// edited. Unknown shortcodes and the contents of code blocks are left as
+/// they are.
+pub fn expand(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut in_code_block = false;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+
+        if in_code_block {
+            output.push_str(line);
+        } else {
+            output.push_str(&expand_line(line));
+        }
+    }
+
+    output
+}
+
+fn expand_line(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+
+        match parse_shortcode(candidate)
+            .and_then(|(name, args, len)| render_shortcode(&name, &args).map(|html| (html, len)))
+        {
+            Some((html, len)) => {
+                output.push_str(&html);
+                rest = &candidate[len..];
+            }
+            None => {
+                output.push_str("{{");
+                rest = &candidate[2..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Parses the shortcode at the start of the text, returning its name,
+// arguments and length
+fn parse_shortcode(text: &str) -> Option<(String, Arguments, usize)> {
+    for (open, close) in [("{{<", ">}}"), ("{{%", "%}}")] {
+        if let Some(inner) = text.strip_prefix(open) {
+            let end = inner.find(close)?;
+            let body = inner[..end].trim();
+            let (name, args) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+
+            return Some((
+                name.to_owned(),
+                parse_arguments(args),
+                open.len() + end + close.len(),
+            ));
+        }
+    }
+
+    // Zola's shortcodes look like function calls
+    let inner = text.strip_prefix("{{")?;
+    let end = inner.find("}}")?;
+    let body = inner[..end].trim();
+    let (name, args) = body.strip_suffix(')')?.split_once('(')?;
+    let name = name.trim();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some((name.to_owned(), parse_arguments(args), 2 + end + 2))
+}
+
+fn parse_arguments(text: &str) -> Arguments {
+    let mut arguments = Arguments::default();
+    let mut chars = text.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+        let mut key = None;
+        let mut value = String::new();
+
+        while let Some(&c) = chars.peek() {
+            match c {
+                // Zola separates its arguments with commas
+                c if c.is_whitespace() || c == ',' => break,
+                '=' if key.is_none() => {
+                    key = Some(std::mem::take(&mut value));
+                    chars.next();
+                }
+                '"' | '\'' => {
+                    chars.next();
+                    for quoted in chars.by_ref() {
+                        if quoted == c {
+                            break;
+                        }
+                        value.push(quoted);
+                    }
+                }
+                c => {
+                    value.push(c);
+                    chars.next();
+                }
+            }
+        }
+
+        match key {
+            Some(key) => {
+                arguments.named.insert(key.trim().to_owned(), value);
+            }
+            None if !value.is_empty() => arguments.positional.push(value),
+            None => {}
+        }
+
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+    }
+
+    arguments
+}
+
+fn render_shortcode(name: &str, args: &Arguments) -> Option<String> {
+    match name {
+        "figure" => {
+            let src = args.get("src", 0)?;
+            let alt = args.get("alt", 1).unwrap_or_default();
+            let caption = args
+                .get("caption", 2)
+                .map(|c| format!("<figcaption>{}</figcaption>\n", escape_html(c)))
+                .unwrap_or_default();
+            let title = args
+                .named
+                .get("title")
+                .map(|t| format!(" \"{}\"", t.replace('"', "\\\"")))
+                .unwrap_or_default();
+
+            // The image is kept as markdown so that it is processed as any
+            // other image of the post
+            Some(format!(
+                "\n<figure>\n\n![{}](<{}>{})\n\n{}</figure>\n",
+                alt.replace(']', "\\]"),
+                src,
+                title,
+                caption
+            ))
+        }
+        "youtube" => {
+            let id = args.get("id", 0)?;
+            Some(embed(&format!(
+                "https://www.youtube-nocookie.com/embed/{}",
+                escape_html(id)
+            )))
+        }
+        "vimeo" => {
+            let id = args.get("id", 0)?;
+            Some(embed(&format!(
+                "https://player.vimeo.com/video/{}",
+                escape_html(id)
+            )))
+        }
+        _ => None,
+    }
+}
+
+fn embed(src: &str) -> String {
+    format!(
+        "\n<div class=\"aspect-video\"><iframe class=\"w-full h-full\" src=\"{}\" \
+         loading=\"lazy\" allowfullscreen></iframe></div>\n",
+        src
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YOUTUBE: &str = "\n<div class=\"aspect-video\"><iframe class=\"w-full h-full\" \
+                           src=\"https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ\" \
+                           loading=\"lazy\" allowfullscreen></iframe></div>\n";
+
+    #[test]
+    fn parses_arguments() {
+        let cases: &[(&str, &[&str], &[(&str, &str)])] = &[
+            ("", &[], &[]),
+            ("a b", &["a", "b"], &[]),
+            ("\"a b\" 'c'", &["a b", "c"], &[]),
+            ("id=\"a b\", size=2", &[], &[("id", "a b"), ("size", "2")]),
+            ("a, src='x=y' b", &["a", "b"], &[("src", "x=y")]),
+        ];
+
+        for (text, positional, named) in cases {
+            let arguments = parse_arguments(text);
+            let named: HashMap<String, String> = named
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            assert_eq!(arguments.positional, *positional, "{}", text);
+            assert_eq!(arguments.named, named, "{}", text);
+        }
+    }
+
+    #[test]
+    fn expands_shortcodes() {
+        let cases = [
+            ("{{< youtube dQw4w9WgXcQ >}}", YOUTUBE.to_owned()),
+            ("{{% youtube id=\"dQw4w9WgXcQ\" %}}", YOUTUBE.to_owned()),
+            ("{{ youtube(id=\"dQw4w9WgXcQ\") }}", YOUTUBE.to_owned()),
+            (
+                "Watch {{<youtube dQw4w9WgXcQ>}}!",
+                format!("Watch {}!", YOUTUBE),
+            ),
+            (
+                "{{< vimeo 1234 >}}",
+                "\n<div class=\"aspect-video\"><iframe class=\"w-full h-full\" \
+                 src=\"https://player.vimeo.com/video/1234\" loading=\"lazy\" \
+                 allowfullscreen></iframe></div>\n"
+                    .to_owned(),
+            ),
+            (
+                "{{< figure src=\"cat.png\" alt=\"A [cat]\" caption=\"Our <cat>\" >}}",
+                "\n<figure>\n\n![A [cat\\]](<cat.png>)\n\n\
+                 <figcaption>Our &lt;cat&gt;</figcaption>\n</figure>\n"
+                    .to_owned(),
+            ),
+            (
+                "{{ figure(src='cat.png', title='The \"cat\"') }}",
+                "\n<figure>\n\n![](<cat.png> \"The \\\"cat\\\"\")\n\n</figure>\n".to_owned(),
+            ),
+        ];
+
+        for (content, expected) in cases {
+            assert_eq!(expand(content), expected, "{}", content);
+        }
+    }
+
+    #[test]
+    fn leaves_other_braces_as_they_are() {
+        let cases = [
+            // Unknown shortcodes
+            "{{< gist user 1234 >}}",
+            // Shortcodes without their required arguments
+            "{{< youtube >}}",
+            "{{ figure(alt=\"A cat\") }}",
+            // Template syntax, e.g: in posts about templates
+            "{{ page.title }}",
+            "{{ a + b }}",
+            "{{< youtube dQw4w9WgXcQ",
+            "```\n{{< youtube dQw4w9WgXcQ >}}\n```\n",
+            "~~~md\n{{ youtube(id=\"dQw4w9WgXcQ\") }}\n~~~\n",
+        ];
+
+        for content in cases {
+            assert_eq!(expand(content), content);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::routes::Route;
+
+/// Page listed in the sitemap
+#[derive(Debug)]
+pub struct SitemapUrl {
+    pub loc: String,
+    /// W3C formatted date of the page's last modification, if known
+    pub lastmod: Option<String>,
+}
+
+/// Pages of the blog, as listed in `sitemap.xml` for search engines
+#[derive(Debug)]
+pub struct Sitemap {
+    pub urls: Vec<SitemapUrl>,
+}
+
+/// Lists the routes of the blog that search engines are told about
+pub fn build_sitemap(routes: &[Route]) -> Sitemap {
+    let urls = routes
+        .iter()
+        .filter(|route| route.in_sitemap())
+        .map(|route| SitemapUrl {
+            loc: route.url.clone(),
+            lastmod: route.lastmod.clone(),
+        })
+        .collect();
+
+    Sitemap { urls }
+}
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
+use comrak::{parse_document, Arena, ComrakOptions};
+
+use crate::{
+    blog::{parse_frontmatter, plain_text, BlogConfiguration},
+    defaults::FrontmatterDefaults,
+};
+
+/// Words too common to tell posts apart
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "again", "all", "also", "and", "any", "are", "because", "been", "before",
+    "being", "but", "can", "could", "did", "does", "doing", "each", "even", "few", "for", "from",
+    "get", "got", "had", "has", "have", "here", "how", "its", "just", "like", "more", "most",
+    "much", "not", "now", "only", "other", "our", "out", "over", "same", "should", "some", "such",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "those",
+    "through", "too", "use", "used", "using", "very", "was", "way", "were", "what", "when",
+    "where", "which", "while", "who", "why", "will", "with", "would", "you", "your",
+];
+
+/// Shortest word taken into account
+const MIN_WORD_LENGTH: usize = 3;
+
+/// Text and tags of a post, as analysed for tag suggestions
+#[derive(Debug)]
+pub struct PostTerms {
+    pub tags: Vec<String>,
+    /// Times that each word appears in the post's text
+    counts: HashMap<String, usize>,
+}
+
+impl PostTerms {
+    pub fn parse(
+        content: &str,
+        config: &BlogConfiguration,
+        defaults: &FrontmatterDefaults,
+    ) -> Result<PostTerms, String> {
+        let mut options = ComrakOptions::default();
+        options.extension.front_matter_delimiter = Some("---".to_owned());
+
+        let arena = Arena::new();
+        let ast = parse_document(&arena, content, &options);
+        let metadata = parse_frontmatter(ast, config, defaults)?;
+
+        let mut counts = HashMap::new();
+        for word in words(&format!("{} {}", metadata.title, plain_text(ast))) {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        Ok(PostTerms {
+            tags: metadata.tags,
+            counts,
+        })
+    }
+}
+
+/// Tag suggested for a post
+#[derive(Debug)]
+pub struct TagSuggestion {
+    pub tag: String,
+    /// How strongly the post relates to the tag, from 0 to 1 for the tags
+    /// of the blog
+    pub score: f64,
+    /// Whether other posts of the blog already have the tag
+    pub existing: bool,
+}
+
+/// Suggests up to `limit` tags for a post, by comparing the weight of its
+/// words (TF-IDF) against the rest of the posts of the blog. Tags that the
+/// blog already has come first, ranked by how similar the post is to the
+/// posts that have them, so that taxonomies stay consistent. The remaining
+/// suggestions are the words that stand out the most in the post.
+pub fn suggest_tags(
+    post: &PostTerms,
+    corpus: &[PostTerms],
+    config: &BlogConfiguration,
+    limit: usize,
+) -> Vec<TagSuggestion> {
+    let idf = inverse_document_frequencies(post, corpus);
+    let post_weights = weights(post, &idf);
+    let post_tags: HashSet<String> = post.tags.iter().map(|t| config.tags.slug(t)).collect();
+
+    // Each tag is represented by the sum of the weights of its posts
+    let mut tag_profiles: HashMap<String, (String, HashMap<&str, f64>)> = HashMap::new();
+    for other in corpus {
+        let other_weights = weights(other, &idf);
+        for tag in &other.tags {
+            let slug = config.tags.slug(tag);
+            if post_tags.contains(&slug) {
+                continue;
+            }
+
+            let (_, profile) = tag_profiles
+                .entry(slug)
+                .or_insert_with(|| (config.tags.title(tag).to_owned(), HashMap::new()));
+            for (&word, weight) in &other_weights {
+                *profile.entry(word).or_insert(0.0) += weight;
+            }
+        }
+    }
+
+    let mut suggestions: Vec<TagSuggestion> = tag_profiles
+        .into_values()
+        .map(|(tag, profile)| TagSuggestion {
+            tag,
+            score: cosine_similarity(&post_weights, &profile),
+            existing: true,
+        })
+        .filter(|s| s.score > 0.0)
+        .collect();
+    sort_by_score(&mut suggestions);
+    suggestions.truncate(limit);
+
+    // Words of the post that aren't tags of the blog yet
+    let suggested: HashSet<String> = suggestions
+        .iter()
+        .map(|s| config.tags.slug(&s.tag))
+        .chain(post_tags)
+        .collect();
+    let mut keywords: Vec<TagSuggestion> = post_weights
+        .into_iter()
+        .filter(|(word, _)| !suggested.contains(&config.tags.slug(word)))
+        .map(|(word, score)| TagSuggestion {
+            tag: word.to_owned(),
+            score,
+            existing: false,
+        })
+        .collect();
+    sort_by_score(&mut keywords);
+    keywords.truncate(limit - suggestions.len());
+
+    suggestions.extend(keywords);
+    suggestions
+}
+
+/// Lowercased words of a text, leaving out numbers, short words and stop
+/// words
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '-')
+        .map(|word| word.trim_matches('-').to_lowercase())
+        .filter(|word| word.chars().count() >= MIN_WORD_LENGTH)
+        .filter(|word| !word.chars().all(|c| c.is_numeric() || c == '-'))
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+// Words that appear in fewer posts weigh more. The corpus has the other
+// posts of the blog, so the post is counted along with them
+fn inverse_document_frequencies<'a>(
+    post: &'a PostTerms,
+    corpus: &'a [PostTerms],
+) -> HashMap<&'a str, f64> {
+    let mut frequencies: HashMap<&str, usize> = HashMap::new();
+    for terms in corpus.iter().chain([post]) {
+        for word in terms.counts.keys() {
+            *frequencies.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    let documents = (corpus.len() + 1) as f64;
+    frequencies
+        .into_iter()
+        .map(|(word, frequency)| (word, (documents / frequency as f64).ln() + 1.0))
+        .collect()
+}
+
+fn weights<'a>(terms: &'a PostTerms, idf: &HashMap<&str, f64>) -> HashMap<&'a str, f64> {
+    let total: usize = terms.counts.values().sum();
+
+    terms
+        .counts
+        .iter()
+        .map(|(word, &count)| {
+            let tf = count as f64 / total as f64;
+            (
+                word.as_str(),
+                tf * idf.get(word.as_str()).copied().unwrap_or(1.0),
+            )
+        })
+        .collect()
+}
+
+fn cosine_similarity(a: &HashMap<&str, f64>, b: &HashMap<&str, f64>) -> f64 {
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(word, x)| b.get(*word).map(|y| x * y))
+        .sum();
+    let norm = |v: &HashMap<&str, f64>| v.values().map(|x| x * x).sum::<f64>().sqrt();
+
+    match norm(a) * norm(b) {
+        n if n == 0.0 => 0.0,
+        n => dot / n,
+    }
+}
+
+fn sort_by_score(suggestions: &mut [TagSuggestion]) {
+    // Ties are broken by name, so that the suggestions don't change between
+    // runs
+    suggestions.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+}
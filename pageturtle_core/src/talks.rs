@@ -0,0 +1,60 @@
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+
+use crate::{blog::format_date, utils::date};
+
+/// A talk or appearance listed in `data/talks.yaml`
+#[derive(Debug, Deserialize)]
+pub struct Talk {
+    pub title: String,
+    pub event: String,
+
+    #[serde(with = "date")]
+    pub date: NaiveDate,
+
+    pub description: Option<String>,
+    /// Link to the slides
+    pub slides: Option<String>,
+    /// Link to the recording
+    pub video: Option<String>,
+
+    /// Whether the slides should be embedded in the page
+    #[serde(default)]
+    pub embed_slides: bool,
+}
+
+impl Talk {
+    pub fn format_date(&self) -> String {
+        format_date(self.date)
+    }
+}
+
+#[derive(Debug)]
+pub struct TalksByYear<'a> {
+    pub year: i32,
+    pub talks: Vec<&'a Talk>,
+}
+
+pub fn parse_talks(content: &str) -> Result<Vec<Talk>, serde_yaml::Error> {
+    serde_yaml::from_str(content)
+}
+
+/// Groups the talks by the year they happened, from newest to oldest
+pub fn group_by_year(talks: &[Talk]) -> Vec<TalksByYear<'_>> {
+    let mut sorted: Vec<&Talk> = talks.iter().collect();
+    sorted.sort_by_key(|t| std::cmp::Reverse(t.date));
+
+    let mut years: Vec<TalksByYear> = Vec::new();
+
+    for talk in sorted {
+        match years.last_mut() {
+            Some(group) if group.year == talk.date.year() => group.talks.push(talk),
+            _ => years.push(TalksByYear {
+                year: talk.date.year(),
+                talks: vec![talk],
+            }),
+        }
+    }
+
+    years
+}
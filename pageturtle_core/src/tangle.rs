@@ -0,0 +1,76 @@
+use std::path::{Component, PathBuf};
+
+use comrak::{nodes::NodeValue, parse_document, Arena, ComrakOptions};
+
+use crate::{
+    blog::{parse_frontmatter, BlogConfiguration},
+    defaults::FrontmatterDefaults,
+    highlighting::CodeBlockMeta,
+};
+
+/// Source file assembled from the code blocks of a post that are annotated
+/// with its path, e.g: ```` ```rust file="src/main.rs" ````
+#[derive(Debug)]
+pub struct TangledFile {
+    /// Path of the file, relative to the directory of the post's code
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Code extracted from a post
+#[derive(Debug)]
+pub struct TangledPost {
+    pub files: Vec<TangledFile>,
+    /// Command that checks the code, from the post's `tangle` setting
+    pub command: Option<String>,
+}
+
+/// Extracts the code blocks of a post that are annotated with a file. Blocks
+/// of the same file are appended to it in the order they appear, so that
+/// tutorials can build a file up bit by bit.
+pub fn tangle(
+    content: &str,
+    config: &BlogConfiguration,
+    defaults: &FrontmatterDefaults,
+) -> Result<TangledPost, String> {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_owned());
+
+    let arena = Arena::new();
+    let ast = parse_document(&arena, content, &options);
+    let metadata = parse_frontmatter(ast, config, defaults)?;
+
+    let mut files: Vec<TangledFile> = Vec::new();
+
+    for node in ast.descendants() {
+        let (info, literal) = match node.data.borrow().value {
+            NodeValue::CodeBlock(ref block) => (block.info.clone(), block.literal.clone()),
+            _ => continue,
+        };
+        let path = match CodeBlockMeta::parse(&info).file {
+            Some(file) => PathBuf::from(file),
+            None => continue,
+        };
+
+        // The files are written inside of the post's directory
+        let is_relative = path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !is_relative {
+            return Err(format!("{} is outside of the post's code", path.display()));
+        }
+
+        match files.iter_mut().find(|f| f.path == path) {
+            Some(file) => file.contents.push_str(&literal),
+            None => files.push(TangledFile {
+                path,
+                contents: literal,
+            }),
+        }
+    }
+
+    Ok(TangledPost {
+        files,
+        command: metadata.tangle,
+    })
+}
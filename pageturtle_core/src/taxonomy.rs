@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use comrak::{markdown_to_html, ComrakOptions};
+use serde::Deserialize;
+use slug::slugify;
+
+use crate::blog::{BlogConfiguration, PublishableBlogPost};
+
+/// Settings of the `[tags]` section of the configuration file
+#[derive(Debug, Default, Deserialize)]
+pub struct TagsConfiguration {
+    /// Tags that are merged into another one, e.g: `rustlang = "rust"`
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+
+    /// Publishes a feed with the posts of each tag, e.g: `tags/rust.xml`
+    #[serde(default)]
+    pub feeds: bool,
+
+    /// Titles and descriptions of the tags, given in `[tags.<name>]` tables
+    #[serde(flatten)]
+    pub info: HashMap<String, TagInfo>,
+}
+
+impl TagsConfiguration {
+    /// Slug of a tag, used in URLs and to tell tags apart. Tags that only
+    /// differ in casing or punctuation, or that are aliases of another tag,
+    /// have the same slug
+    pub fn slug(&self, tag: &str) -> String {
+        let slug = slugify(tag);
+
+        match self
+            .aliases
+            .iter()
+            .find(|(alias, _)| slugify(alias) == slug)
+        {
+            Some((_, target)) => slugify(target),
+            None => slug,
+        }
+    }
+
+    pub fn info(&self, slug: &str) -> Option<&TagInfo> {
+        self.info
+            .iter()
+            .find(|(name, _)| self.slug(name) == slug)
+            .map(|(_, info)| info)
+    }
+
+    /// Name shown for a tag, which is its title when it has one
+    pub fn title<'a>(&'a self, tag: &'a str) -> &'a str {
+        let slug = self.slug(tag);
+
+        match self.info(&slug).and_then(|i| i.title.as_deref()) {
+            Some(title) => title,
+            None => self
+                .aliases
+                .iter()
+                .find(|(alias, _)| slugify(alias) == slugify(tag))
+                .map_or(tag, |(_, target)| target.as_str()),
+        }
+    }
+}
+
+/// Custom title and description of a tag, given either in the `[tags.<name>]`
+/// table of the configuration file or in a `tags/<name>.md` file
+#[derive(Debug, Default, Deserialize)]
+pub struct TagInfo {
+    pub title: Option<String>,
+    /// Markdown describing the tag
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagFrontmatter {
+    title: Option<String>,
+}
+
+impl TagInfo {
+    /// Parses a `tags/<name>.md` file. Its frontmatter may set the tag's
+    /// title and the rest of the file is used as its description.
+    pub fn from_markdown(content: &str) -> Result<TagInfo, String> {
+        let (title, body) = match content.strip_prefix("---") {
+            Some(rest) => {
+                let (frontmatter, body) = rest
+                    .split_once("\n---")
+                    .ok_or("frontmatter section is not closed")?;
+                let frontmatter: TagFrontmatter =
+                    serde_yaml::from_str(frontmatter).map_err(|e| e.to_string())?;
+
+                (frontmatter.title, body)
+            }
+            None => (None, content),
+        };
+
+        let body = body.trim();
+
+        Ok(TagInfo {
+            title,
+            description: (!body.is_empty()).then(|| body.to_owned()),
+        })
+    }
+
+    pub fn description_html(&self) -> Option<String> {
+        self.description
+            .as_ref()
+            .map(|d| markdown_to_html(d, &ComrakOptions::default()))
+    }
+}
+
+/// A tag as shown in the tags page
+#[derive(Debug)]
+pub struct TagEntry<'a> {
+    pub slug: String,
+    pub title: &'a str,
+    pub description_html: Option<String>,
+}
+
+impl<'a> TagEntry<'a> {
+    /// Path of the page listing the tag's posts, relative to the output
+    /// directory
+    pub fn path(&self) -> String {
+        format!("tags/{}.html", self.slug)
+    }
+
+    /// URL of the page listing the tag's posts
+    pub fn url(&self, config: &BlogConfiguration) -> String {
+        format!("{}/{}", config.base_url, self.path())
+    }
+
+    /// Path of the feed with the tag's posts, relative to the output
+    /// directory, when tags have feeds
+    pub fn feed_path(&self, config: &BlogConfiguration) -> Option<String> {
+        config.tags.feeds.then(|| format!("tags/{}.xml", self.slug))
+    }
+}
+
+/// Tags of the posts, sorted by their slugs. The first spelling found for a
+/// tag is the one shown, unless it has a title
+pub fn collect_tags<'a>(
+    posts: &'a [PublishableBlogPost],
+    config: &'a BlogConfiguration,
+) -> Vec<TagEntry<'a>> {
+    let mut tags: Vec<TagEntry> = Vec::new();
+
+    for name in posts.iter().flat_map(|p| &p.post.metadata.tags) {
+        let slug = config.tags.slug(name);
+        if tags.iter().any(|t| t.slug == slug) {
+            continue;
+        }
+
+        tags.push(TagEntry {
+            title: config.tags.title(name),
+            description_html: config.tags.info(&slug).and_then(|i| i.description_html()),
+            slug,
+        });
+    }
+
+    tags.sort_by(|a, b| a.slug.cmp(&b.slug));
+    tags
+}
+
+/// Posts that carry the tag with the given slug, newest first
+pub fn tagged_posts<'a>(
+    slug: &str,
+    posts: &'a [PublishableBlogPost],
+    config: &BlogConfiguration,
+) -> Vec<&'a PublishableBlogPost> {
+    let mut tagged: Vec<&PublishableBlogPost> = posts
+        .iter()
+        .filter(|p| {
+            p.post
+                .metadata
+                .tags
+                .iter()
+                .any(|t| config.tags.slug(t) == slug)
+        })
+        .collect();
+
+    tagged.sort_by_key(|p| std::cmp::Reverse((p.post.metadata.date, p.post.metadata.time)));
+    tagged
+}
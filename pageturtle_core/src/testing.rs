@@ -0,0 +1,39 @@
+//! Helpers shared by the tests of the modules
+
+use std::path::PathBuf;
+
+use comrak::Arena;
+
+use crate::{
+    blog::{
+        build_blog_post, markdown_options, prepare_for_publish, BlogConfiguration, PostCompiler,
+        PostRenderers, PublishableBlogPost,
+    },
+    defaults::FrontmatterDefaults,
+};
+
+/// Configuration of a blog from the contents of its `pageturtle.toml`
+pub fn config(toml: &str) -> BlogConfiguration {
+    BlogConfiguration::from_toml(toml).unwrap()
+}
+
+/// Compiles posts as a build does, from their paths and contents, keeping
+/// their order
+pub fn compile_posts(
+    posts: &[(&str, &str)],
+    config: &BlogConfiguration,
+) -> Vec<PublishableBlogPost> {
+    let options = markdown_options();
+    let renderers = PostRenderers::new(config);
+    let plugins = renderers.plugins();
+
+    posts
+        .iter()
+        .map(|(path, content)| {
+            let compiler = PostCompiler::new(Arena::new(), &options, &plugins);
+            let defaults = FrontmatterDefaults::default();
+            let parsed = build_blog_post(content, &compiler, config, &defaults).unwrap();
+            prepare_for_publish(parsed, PathBuf::from(path), &compiler, config)
+        })
+        .collect()
+}
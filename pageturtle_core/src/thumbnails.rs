@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use image::{imageops::FilterType, ImageResult};
+use serde::Deserialize;
+
+use crate::{
+    blog::PostImage,
+    urls,
+    utils::{default_true, escape_html},
+};
+
+/// Settings of the `[thumbnails]` section of the configuration file
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailConfiguration {
+    /// Crops the covers of the posts into thumbnails, which are shown in
+    /// cards instead of the full covers
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    #[serde(default = "default_width")]
+    pub width: u32,
+
+    #[serde(default = "default_height")]
+    pub height: u32,
+}
+
+impl Default for ThumbnailConfiguration {
+    fn default() -> Self {
+        ThumbnailConfiguration {
+            enabled: true,
+            width: default_width(),
+            height: default_height(),
+        }
+    }
+}
+
+fn default_width() -> u32 {
+    400
+}
+
+fn default_height() -> u32 {
+    225
+}
+
+/// Small version of a post's cover, cropped to the configured size
+#[derive(Debug)]
+pub struct PostThumbnail {
+    /// The path of the cover, relative to the post
+    pub original_path: String,
+
+    /// The final path of the thumbnail, relative to the images directory
+    /// (e.g: 400x225-3f2a9c0d1b4e5a67-my-tour.png). As the cover's path, it
+    /// is named after the cover's content, so it is only generated once
+    pub final_path: PathBuf,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PostThumbnail {
+    /// Thumbnail of a cover, if it can be read
+    pub fn new(cover: &PostImage, config: &ThumbnailConfiguration) -> Option<PostThumbnail> {
+        cover.dimensions?;
+
+        let filename = format!(
+            "{}x{}-{}",
+            config.width,
+            config.height,
+            cover.final_path.display()
+        );
+
+        Some(PostThumbnail {
+            original_path: cover.original_path.clone(),
+            final_path: filename.into(),
+            width: config.width,
+            height: config.height,
+        })
+    }
+
+    /// URL of the thumbnail in the published blog
+    pub fn url(&self) -> String {
+        format!("/img/{}", urls::from_path(&self.final_path))
+    }
+
+    pub fn to_html(&self, alt: &str) -> String {
+        format!(
+            "<img src=\"{}\" alt=\"{}\" width=\"{}\" height=\"{}\" loading=\"lazy\" decoding=\"async\" />",
+            escape_html(&self.url()),
+            escape_html(alt),
+            self.width,
+            self.height
+        )
+    }
+}
+
+/// Crops the center of an image to the size of the thumbnail, writing it in
+/// the format of the destination's extension
+pub fn generate(source: &Path, destination: &Path, width: u32, height: u32) -> ImageResult<()> {
+    image::open(source)?
+        .resize_to_fill(width, height, FilterType::Lanczos3)
+        .save(destination)
+}
@@ -0,0 +1,86 @@
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Deserialize;
+
+const NBSP: char = '\u{a0}';
+
+/// Settings of the `[typography]` section of the configuration file
+#[derive(Debug, Default, Deserialize)]
+pub struct TypographyConfiguration {
+    /// Joins the last two words of headings, so that the last word is never
+    /// left alone in a line
+    #[serde(default)]
+    pub prevent_widows: bool,
+
+    /// Language of the posts, e.g: `cs`, whose one-letter words are kept on
+    /// the same line as the word that follows them
+    pub locale: Option<String>,
+}
+
+impl TypographyConfiguration {
+    /// Words that shouldn't be left at the end of a line in the configured
+    /// locale
+    fn short_words(&self) -> &'static [&'static str] {
+        match self.locale.as_deref() {
+            Some("cs") | Some("sk") => &["a", "i", "k", "o", "s", "u", "v", "z"],
+            Some("pl") => &["a", "i", "o", "u", "w", "z"],
+            Some("en") => &["a", "i"],
+            _ => &[],
+        }
+    }
+}
+
+/// Replaces spaces of the post's text with non-breaking spaces, following the
+/// configured typography rules
+pub fn refine<'a>(ast: &'a AstNode<'a>, config: &TypographyConfiguration) {
+    let short_words = config.short_words();
+
+    for node in ast.descendants() {
+        let is_heading = matches!(node.data.borrow().value, NodeValue::Heading(_));
+        if is_heading && config.prevent_widows {
+            prevent_widow(node);
+        }
+
+        if let NodeValue::Text(ref mut text) = node.data.borrow_mut().value {
+            if !short_words.is_empty() {
+                *text = bind_short_words(text, short_words);
+            }
+        }
+    }
+}
+
+// Joins the last two words of a heading, as long as it has more than two
+fn prevent_widow<'a>(heading: &'a AstNode<'a>) {
+    let last_text = heading
+        .descendants()
+        .filter(|n| matches!(n.data.borrow().value, NodeValue::Text(_)))
+        .last();
+
+    if let Some(node) = last_text {
+        if let NodeValue::Text(ref mut text) = node.data.borrow_mut().value {
+            // Posts may be prepared more than once, e.g: drafts
+            let trimmed = text.trim_end();
+            if trimmed.split(' ').count() > 2 && !trimmed.contains(NBSP) {
+                if let Some(i) = trimmed.rfind(' ') {
+                    text.replace_range(i..i + 1, &NBSP.to_string());
+                }
+            }
+        }
+    }
+}
+
+fn bind_short_words(text: &str, short_words: &[&str]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut words = text.split(' ').peekable();
+
+    while let Some(word) = words.next() {
+        result.push_str(word);
+
+        if words.peek().is_some() {
+            let bare = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+            let is_short = short_words.iter().any(|w| w.eq_ignore_ascii_case(bare));
+            result.push(if is_short { NBSP } else { ' ' });
+        }
+    }
+
+    result
+}
@@ -0,0 +1,103 @@
+use std::path::{Component, Path};
+
+/// Converts a relative path, e.g: `drafts\0123456789abcdef.html` on Windows,
+/// into the path of a URL, e.g: `drafts/0123456789abcdef.html`. URLs are
+/// always separated by forward slashes, whatever the platform's separator is.
+pub fn from_path(path: &Path) -> String {
+    let segments: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+            Component::ParentDir => Some("..".to_owned()),
+            // Prefixes (e.g: `C:`), roots and `.` have no place in a URL
+            _ => None,
+        })
+        .collect();
+
+    segments.join("/")
+}
+
+/// Attributes of HTML tags that hold URLs. `srcset` holds several of them,
+/// along with their descriptors
+const URL_ATTRIBUTES: [&str; 4] = ["href", "src", "srcset", "poster"];
+
+/// Makes the links of a page of the blog to the blog itself, at `base_url`,
+/// relative to the page, e.g: `https://example.com/img/a.webp` becomes
+/// `../img/a.webp` in `notes/post.html`. Canonical links are kept absolute,
+/// as search engines expect them to be.
+pub fn relativize(html: &str, base_url: &str, page: &Path) -> String {
+    if base_url.is_empty() {
+        return html.to_owned();
+    }
+
+    let depth = page
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .count()
+        .saturating_sub(1);
+    let prefix = "../".repeat(depth);
+
+    let mut output = String::with_capacity(html.len());
+    let mut copied = 0;
+
+    for (start, _) in html.match_indices("=\"") {
+        let value_start = start + 2;
+        let name_start = html[..start]
+            .rfind(|c: char| c.is_whitespace())
+            .map_or(0, |i| i + 1);
+        if value_start <= copied || !URL_ATTRIBUTES.contains(&&html[name_start..start]) {
+            continue;
+        }
+
+        let value_end = match html[value_start..].find('"') {
+            Some(end) => value_start + end,
+            None => break,
+        };
+        let tag_start = html[..start].rfind('<').unwrap_or(0);
+        let tag_end = html[start..]
+            .find('>')
+            .map_or(html.len(), |end| start + end);
+        if html[tag_start..tag_end].contains("rel=\"canonical\"") {
+            continue;
+        }
+
+        // Each candidate of a `srcset` is a URL followed by its descriptor
+        let value: Vec<String> = html[value_start..value_end]
+            .split(',')
+            .map(|candidate| {
+                let url_start = candidate.len() - candidate.trim_start().len();
+                let url_end = candidate[url_start..]
+                    .find(' ')
+                    .map_or(candidate.len(), |end| url_start + end);
+                match relative_url(&candidate[url_start..url_end], base_url, &prefix) {
+                    Some(url) => format!(
+                        "{}{}{}",
+                        &candidate[..url_start],
+                        url,
+                        &candidate[url_end..]
+                    ),
+                    None => candidate.to_owned(),
+                }
+            })
+            .collect();
+
+        output.push_str(&html[copied..value_start]);
+        output.push_str(&value.join(","));
+        copied = value_end;
+    }
+
+    output.push_str(&html[copied..]);
+    output
+}
+
+// URL relative to a page, given the `../` that lead from the page to the root
+// of the blog, or `None` when the URL isn't one of the blog's
+fn relative_url(url: &str, base_url: &str, prefix: &str) -> Option<String> {
+    match url.strip_prefix(base_url)? {
+        "" | "/" if prefix.is_empty() => Some("./".to_owned()),
+        "" | "/" => Some(prefix.to_owned()),
+        path => path
+            .strip_prefix('/')
+            .map(|path| format!("{}{}", prefix, path)),
+    }
+}
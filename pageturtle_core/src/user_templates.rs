@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tera::{Context, Tera};
+
+use crate::{
+    blog::{BlogConfiguration, PostImage, PublishableBlogPost},
+    external::ExternalPost,
+    related::RelatedPost,
+    taxonomy::TagEntry,
+};
+
+/// Pages whose templates can be replaced by the blog
+pub const OVERRIDABLE: &[&str] = &["index.html", "post.html", "tag.html", "tags.html"];
+
+/// Templates in the `templates` directory of the blog, which replace the
+/// built-in templates of the same name, e.g: `post.html`. The built-in
+/// templates are compiled into pageturtle, so these are written in Tera and
+/// rendered at build time instead.
+#[derive(Debug, Default)]
+pub struct UserTemplates {
+    tera: Option<Tera>,
+}
+
+impl UserTemplates {
+    /// Loads the templates of the directories that exist, including the ones
+    /// that the pages extend or include, e.g: `base.html`. Templates of the
+    /// first directories take precedence, so that a blog can replace some
+    /// of the templates of its theme, which may extend the others
+    pub fn load(directories: &[PathBuf]) -> Result<UserTemplates, tera::Error> {
+        let mut tera: Option<Tera> = None;
+
+        for directory in directories.iter().filter(|d| d.is_dir()) {
+            // Templates may extend the ones of the next directories, so they
+            // are only linked once all of them are loaded
+            let glob = format!("{}/**/*.html", directory.display());
+            let loaded = Tera::parse(&glob)?;
+
+            match tera {
+                Some(ref mut tera) => tera.extend(&loaded)?,
+                None => tera = Some(loaded),
+            }
+        }
+
+        if let Some(ref mut tera) = tera {
+            tera.build_inheritance_chains()?;
+        }
+
+        Ok(UserTemplates { tera })
+    }
+
+    /// Whether the blog replaces the built-in template of a page
+    pub fn overrides(&self, name: &str) -> bool {
+        self.tera
+            .as_ref()
+            .is_some_and(|t| t.get_template_names().any(|n| n == name))
+    }
+
+    /// Names of the templates that replace built-in ones
+    pub fn overridden(&self) -> Vec<&'static str> {
+        OVERRIDABLE
+            .iter()
+            .copied()
+            .filter(|name| self.overrides(name))
+            .collect()
+    }
+
+    pub(crate) fn render(&self, name: &str, context: &Context) -> Result<String, tera::Error> {
+        match self.tera {
+            Some(ref tera) => tera.render(name, context),
+            None => Err(tera::Error::template_not_found(name)),
+        }
+    }
+}
+
+/// The blog, as seen by the templates as `site`
+#[derive(Serialize)]
+pub(crate) struct SiteContext<'a> {
+    title: &'a str,
+    author: &'a str,
+    base_url: &'a str,
+    stylesheets: Vec<String>,
+    feed_url: Option<String>,
+}
+
+impl<'a> SiteContext<'a> {
+    pub fn new(config: &'a BlogConfiguration) -> Self {
+        SiteContext {
+            title: &config.blog_title,
+            author: &config.author,
+            base_url: &config.base_url,
+            stylesheets: vec![config.asset("styles.css"), config.asset("syntax.css")],
+            feed_url: config
+                .enable_rss
+                .then(|| format!("{}/atom.xml", config.base_url)),
+        }
+    }
+}
+
+/// A post, as seen by the templates. Listings leave its `content` out
+#[derive(Serialize)]
+pub(crate) struct PostContext<'a> {
+    title: &'a str,
+    url: String,
+    canonical_url: &'a str,
+    date: String,
+    /// Date in the ISO 8601 format, e.g: for `<time>` elements
+    date_iso: String,
+    description: &'a str,
+    summary: &'a str,
+    tags: Vec<TagLink<'a>>,
+    reading_time: u16,
+    word_count: usize,
+    cover: Option<String>,
+    /// Drafts shouldn't be indexed by search engines, e.g: when previewed
+    draft: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    related_posts: Option<&'a [RelatedPost]>,
+}
+
+#[derive(Serialize)]
+struct TagLink<'a> {
+    name: &'a str,
+    slug: String,
+    url: String,
+}
+
+impl<'a> PostContext<'a> {
+    pub fn summary(post: &'a PublishableBlogPost, config: &BlogConfiguration) -> Self {
+        let metadata = &post.post.metadata;
+
+        PostContext {
+            title: &metadata.title,
+            url: post.url(config),
+            canonical_url: &post.canonical_url,
+            date: metadata.format_date(),
+            date_iso: metadata.date.to_string(),
+            description: &post.description,
+            summary: &post.summary,
+            tags: metadata
+                .tags
+                .iter()
+                .map(|name| {
+                    let slug = config.tags.slug(name);
+                    TagLink {
+                        name,
+                        url: format!("{}/tags/{}.html", config.base_url, slug),
+                        slug,
+                    }
+                })
+                .collect(),
+            reading_time: post.post.reading_time,
+            word_count: post.post.word_count,
+            cover: post.cover.as_ref().map(PostImage::url),
+            draft: metadata.draft,
+            content: None,
+            related_posts: None,
+        }
+    }
+
+    pub fn full(post: &'a PublishableBlogPost, config: &BlogConfiguration) -> Self {
+        PostContext {
+            content: Some(&post.rendered_html),
+            related_posts: Some(&post.related_posts),
+            ..PostContext::summary(post, config)
+        }
+    }
+}
+
+/// A post published somewhere else, as seen by the templates
+#[derive(Serialize)]
+pub(crate) struct ExternalPostContext<'a> {
+    title: &'a str,
+    url: &'a str,
+    date: String,
+    date_iso: String,
+    venue: Option<&'a str>,
+    description: Option<&'a str>,
+}
+
+impl<'a> ExternalPostContext<'a> {
+    /// External posts of the blog, newest first
+    pub fn list(config: &'a BlogConfiguration) -> Vec<Self> {
+        let mut posts: Vec<&ExternalPost> = config.external_posts.iter().collect();
+        posts.sort_by_key(|e| std::cmp::Reverse(e.date));
+
+        posts
+            .into_iter()
+            .map(|post| ExternalPostContext {
+                title: &post.title,
+                url: &post.url,
+                date: post.format_date(),
+                date_iso: post.date.to_string(),
+                venue: post.venue.as_deref(),
+                description: post.description.as_deref(),
+            })
+            .collect()
+    }
+}
+
+/// A tag, as seen by the templates
+#[derive(Serialize)]
+pub(crate) struct TagContext<'a> {
+    title: &'a str,
+    slug: &'a str,
+    url: String,
+    description: Option<&'a str>,
+    feed_url: Option<String>,
+}
+
+impl<'a> TagContext<'a> {
+    pub fn new(tag: &'a TagEntry<'a>, config: &BlogConfiguration) -> Self {
+        TagContext {
+            title: tag.title,
+            slug: &tag.slug,
+            url: tag.url(config),
+            description: tag.description_html.as_deref(),
+            feed_url: tag
+                .feed_path(config)
+                .map(|feed| format!("{}/{}", config.base_url, feed)),
+        }
+    }
+}
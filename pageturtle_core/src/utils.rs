@@ -1,18 +1,105 @@
+/// Date of a post, either in one of the built-in formats or in the blog's
+/// `date_format`, e.g: `2023-01-05`, `2023-01-05 14:30` or
+/// `2023-01-05T14:30:00-03:00`
 pub mod date {
-    use chrono::NaiveDate;
+    use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
     use serde::{self, Deserialize, Deserializer};
 
-    // TODO: accept multiple date formats
-    // TODO: get custom date format from config?
-    const FORMAT: &str = "%Y-%m-%d";
+    /// Format that dates are written back in, e.g: after being parsed
+    pub const FORMAT: &str = "%Y-%m-%d";
+
+    const DATE_TIME_FORMATS: &[&str] = &[
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ];
+
+    /// Parses a date, along with its time when it has one. Dates with an
+    /// offset from UTC are converted to the given offset, e.g: the blog's
+    pub fn parse(
+        s: &str,
+        custom_format: Option<&str>,
+        offset: FixedOffset,
+    ) -> Result<(NaiveDate, Option<NaiveTime>), String> {
+        let s = s.trim();
+
+        if let Some(format) = custom_format {
+            if let Ok(date_time) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok((date_time.date(), Some(date_time.time())));
+            }
+            if let Ok(date) = NaiveDate::parse_from_str(s, format) {
+                return Ok((date, None));
+            }
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(s, FORMAT) {
+            return Ok((date, None));
+        }
+
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(s) {
+            let date_time = date_time.with_timezone(&offset);
+            return Ok((date_time.date_naive(), Some(date_time.time())));
+        }
+
+        for format in DATE_TIME_FORMATS {
+            if let Ok(date_time) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok((date_time.date(), Some(date_time.time())));
+            }
+        }
+
+        let mut expected =
+            String::from("e.g: 2023-01-05, 2023-01-05 14:30 or 2023-01-05T14:30:00Z");
+        if let Some(format) = custom_format {
+            expected.push_str(&format!(", or in the format `{}`", format));
+        }
+        Err(format!(
+            "invalid date `{}`, expected a date {}",
+            s, expected
+        ))
+    }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
     where
         D: Deserializer<'de>,
     {
-        // TODO: Improve error message when failing to parse date
         let s = String::deserialize(deserializer)?;
-        NaiveDate::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
+        parse(&s, None, FixedOffset::east_opt(0).unwrap())
+            .map(|(date, _)| date)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Optional time of the day, e.g: `14:30`
+pub mod time {
+    use chrono::NaiveTime;
+    use serde::{self, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: Option<String> = Option::deserialize(deserializer)?;
+        s.map(|s| {
+            NaiveTime::parse_from_str(&s, "%H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(&s, "%H:%M"))
+                .map_err(serde::de::Error::custom)
+        })
+        .transpose()
+    }
+}
+
+/// Offset from UTC, e.g: `-03:00`
+pub mod utc_offset {
+    use chrono::FixedOffset;
+    use serde::{self, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<FixedOffset, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -23,3 +110,9 @@ pub fn default_true() -> bool {
 pub fn default_empty<T>() -> Vec<T> {
     vec![]
 }
+
+pub fn escape_html(text: &str) -> String {
+    let mut buffer = Vec::new();
+    comrak::html::escape(&mut buffer, text.as_bytes()).unwrap();
+    String::from_utf8(buffer).unwrap()
+}